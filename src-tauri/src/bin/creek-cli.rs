@@ -0,0 +1,115 @@
+// creek-cli: run a transcript through the pipeline without the desktop app
+//
+//   creek-cli <transcript> --workspace <dir> [--recording <id>] [--config <creek.toml>]
+//
+// The transcript is plain text (one turn per line) or JSONL
+// (`{"text": ..., "at_ms": ...}`). The document and its git history end up
+// in `<workspace>/recordings/<id>/`; the recording id defaults to the
+// transcript's file stem. Log level follows RUST_LOG (default info).
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use creek_lib::modules::pipeline::headless::{parse_transcript, HeadlessSession};
+use creek_lib::modules::LogSink;
+use creek_lib::services::llm_provider::LLMConfig;
+use creek_lib::services::telemetry::Telemetry;
+use creek_lib::utils::paths::get_config_path;
+
+const USAGE: &str = "usage: creek-cli <transcript> --workspace <dir> [--recording <id>] [--config <creek.toml>]";
+
+struct Args {
+    transcript: PathBuf,
+    workspace: PathBuf,
+    recording_id: String,
+    config: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut transcript = None;
+    let mut workspace = None;
+    let mut recording_id = None;
+    let mut config = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match arg.as_str() {
+            "--workspace" | "-w" => workspace = Some(PathBuf::from(value("--workspace")?)),
+            "--recording" | "-r" => recording_id = Some(value("--recording")?),
+            "--config" | "-c" => config = Some(PathBuf::from(value("--config")?)),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option {}\n{}", flag, USAGE)),
+            _ if transcript.is_none() => transcript = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument {}\n{}", arg, USAGE)),
+        }
+    }
+
+    let transcript = transcript.ok_or(USAGE)?;
+    let recording_id = match recording_id {
+        Some(id) => id,
+        None => transcript.file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or("Cannot derive a recording id from the transcript name, pass --recording")?
+            .to_string(),
+    };
+    Ok(Args {
+        transcript,
+        workspace: workspace.ok_or(USAGE)?,
+        recording_id,
+        config: config.unwrap_or_else(get_config_path),
+    })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args).await {
+        Ok(failed) if failed > 0 => ExitCode::FAILURE,
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("creek-cli: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns the number of turns that failed (and were rolled back)
+async fn run(args: Args) -> anyhow::Result<usize> {
+    let content = std::fs::read_to_string(&args.transcript)?;
+    let turns = parse_transcript(&content)?;
+    let api_key = std::env::var("OPENAI_API_KEY")
+        .or_else(|_| std::env::var("DASHSCOPE_API_KEY"))
+        .map_err(|_| anyhow::anyhow!("OPENAI_API_KEY (or DASHSCOPE_API_KEY) is not set"))?;
+    let config = LLMConfig::load(&args.config)?;
+    let telemetry = Telemetry::init(&config.telemetry);
+
+    let session = HeadlessSession::open(&args.workspace, &args.recording_id, config, &api_key, Arc::new(LogSink)).await?;
+
+    let mut failed = 0;
+    for (i, turn) in turns.iter().enumerate() {
+        let at = turn.at_ms.map(|ms| format!(" @{}ms", ms)).unwrap_or_default();
+        log::info!("[creek-cli] Turn {}/{}{}: {}", i + 1, turns.len(), at, turn.text);
+        if session.run_turn(&turn.text).await.is_err() {
+            failed += 1;
+        }
+    }
+
+    let doc_path = session.finish()?;
+    if let Some(telemetry) = telemetry {
+        telemetry.export().await?;
+    }
+    println!("{} turns, {} failed -> {}", turns.len(), failed, doc_path.display());
+    Ok(failed)
+}
@@ -0,0 +1,119 @@
+// creek-eval: replay evaluation cases and score them against their goldens
+//
+//   creek-eval <case>... [--out <dir>] [--record] [--bless] [--config <creek.toml>]
+//
+// Cases are `.json` files (turns + goldens) or plain transcripts. By default
+// LLM responses are replayed from `<case>.llm.jsonl`, so no network or API
+// key is needed; `--record` runs against the configured providers and
+// rewrites that file. `--bless` stores the documents of this run as the
+// goldens. Writes `report.json` and `report.md` to the output directory
+// (default `eval-out`); exits non-zero if a turn failed or a request had no
+// recorded response.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use creek_lib::modules::eval::score::EvalReport;
+use creek_lib::modules::eval::{run_case_file, EvalOptions};
+use creek_lib::services::llm_provider::{LLMConfig, LLMRegistry};
+use creek_lib::services::telemetry::Telemetry;
+use creek_lib::utils::paths::get_config_path;
+
+const USAGE: &str = "usage: creek-eval <case>... [--out <dir>] [--record] [--bless] [--config <creek.toml>]";
+
+struct Args {
+    cases: Vec<PathBuf>,
+    out_dir: PathBuf,
+    record: bool,
+    bless: bool,
+    config: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut cases = Vec::new();
+    let mut out_dir = None;
+    let mut record = false;
+    let mut bless = false;
+    let mut config = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match arg.as_str() {
+            "--out" | "-o" => out_dir = Some(PathBuf::from(value("--out")?)),
+            "--config" | "-c" => config = Some(PathBuf::from(value("--config")?)),
+            "--record" => record = true,
+            "--bless" => bless = true,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option {}\n{}", flag, USAGE)),
+            _ => cases.push(PathBuf::from(arg)),
+        }
+    }
+
+    if cases.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    Ok(Args {
+        cases,
+        out_dir: out_dir.unwrap_or_else(|| PathBuf::from("eval-out")),
+        record,
+        bless,
+        config: config.unwrap_or_else(get_config_path),
+    })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args).await {
+        Ok(report) if report.overall.failed_turns > 0 || report.overall.unrecorded_requests > 0 => ExitCode::FAILURE,
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("creek-eval: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> anyhow::Result<EvalReport> {
+    let config = LLMConfig::load(&args.config)?;
+    let telemetry = Telemetry::init(&config.telemetry);
+    let record = if args.record {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .or_else(|_| std::env::var("DASHSCOPE_API_KEY"))
+            .map_err(|_| anyhow::anyhow!("--record needs OPENAI_API_KEY (or DASHSCOPE_API_KEY)"))?;
+        Some(Arc::new(LLMRegistry::from_config(&config, &api_key)?))
+    } else {
+        None
+    };
+
+    std::fs::create_dir_all(&args.out_dir)?;
+    let options = EvalOptions { config, record, out_dir: args.out_dir.clone(), bless: args.bless };
+
+    let mut cases = Vec::with_capacity(args.cases.len());
+    for path in &args.cases {
+        cases.push(run_case_file(path, &options).await?);
+    }
+
+    if let Some(telemetry) = telemetry {
+        telemetry.export().await?;
+    }
+
+    let report = EvalReport::new(if args.record { "record" } else { "replay" }, cases);
+    let markdown = report.to_markdown();
+    std::fs::write(args.out_dir.join("report.json"), serde_json::to_string_pretty(&report)?)?;
+    std::fs::write(args.out_dir.join("report.md"), &markdown)?;
+    println!("{}", markdown);
+    Ok(report)
+}
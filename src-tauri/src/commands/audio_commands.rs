@@ -0,0 +1,97 @@
+// Audio Commands - input device selection, session audio archive, ASR settings
+
+use std::sync::Arc;
+use tauri::State;
+use tokio::sync::RwLock;
+use crate::modules::WorkspaceManager;
+use crate::modules::pipeline::PipelineCommand;
+use crate::state::AppState;
+use crate::services::asr::microphone::{self, InputDeviceInfo};
+use crate::services::asr::{AsrSessionSettings, AudioArchiveSettings};
+
+/// Input devices with their default and supported configs
+#[tauri::command]
+pub async fn list_input_devices() -> Result<Vec<InputDeviceInfo>, String> {
+    tokio::task::spawn_blocking(microphone::list_input_devices)
+        .await
+        .map_err(|e| format!("Device enumeration failed: {}", e))?
+        .map_err(|e| format!("Failed to list input devices: {}", e))
+}
+
+/// Input device chosen for the current workspace (None = system default)
+#[tauri::command]
+pub async fn get_input_device(
+    workspace_manager: State<'_, Arc<RwLock<WorkspaceManager>>>,
+) -> Result<Option<String>, String> {
+    let manager = workspace_manager.read().await;
+    let workspace = manager.get_current_workspace()?
+        .ok_or_else(|| "No active workspace".to_string())?;
+    Ok(workspace.settings.input_device)
+}
+
+/// Choose the input device for the current workspace; takes effect on the next recording
+#[tauri::command]
+pub async fn set_input_device(
+    device_id: Option<String>,
+    workspace_manager: State<'_, Arc<RwLock<WorkspaceManager>>>,
+) -> Result<(), String> {
+    let manager = workspace_manager.read().await;
+    let workspace = manager.get_current_workspace()?
+        .ok_or_else(|| "No active workspace".to_string())?;
+    manager.update_settings(&workspace.id, |settings| settings.input_device = device_id)?;
+    Ok(())
+}
+
+/// Audio archive settings of the current workspace
+#[tauri::command]
+pub async fn get_audio_archive_settings(
+    workspace_manager: State<'_, Arc<RwLock<WorkspaceManager>>>,
+) -> Result<AudioArchiveSettings, String> {
+    let manager = workspace_manager.read().await;
+    let workspace = manager.get_current_workspace()?
+        .ok_or_else(|| "No active workspace".to_string())?;
+    Ok(workspace.settings.audio_archive)
+}
+
+/// Enable/disable audio archiving and set retention for the current workspace;
+/// takes effect on the next recording
+#[tauri::command]
+pub async fn set_audio_archive_settings(
+    settings: AudioArchiveSettings,
+    workspace_manager: State<'_, Arc<RwLock<WorkspaceManager>>>,
+) -> Result<(), String> {
+    let manager = workspace_manager.read().await;
+    let workspace = manager.get_current_workspace()?
+        .ok_or_else(|| "No active workspace".to_string())?;
+    manager.update_settings(&workspace.id, |current| current.audio_archive = settings)?;
+    Ok(())
+}
+
+/// ASR language, server VAD and hotword settings of the current workspace
+#[tauri::command]
+pub async fn get_asr_settings(
+    workspace_manager: State<'_, Arc<RwLock<WorkspaceManager>>>,
+) -> Result<AsrSessionSettings, String> {
+    let manager = workspace_manager.read().await;
+    let workspace = manager.get_current_workspace()?
+        .ok_or_else(|| "No active workspace".to_string())?;
+    Ok(workspace.settings.asr)
+}
+
+/// Change ASR settings for the current workspace; a running session is updated in place
+#[tauri::command]
+pub async fn set_asr_settings(
+    settings: AsrSessionSettings,
+    state: State<'_, AppState>,
+    workspace_manager: State<'_, Arc<RwLock<WorkspaceManager>>>,
+) -> Result<(), String> {
+    {
+        let manager = workspace_manager.read().await;
+        let workspace = manager.get_current_workspace()?
+            .ok_or_else(|| "No active workspace".to_string())?;
+        manager.update_settings(&workspace.id, |current| current.asr = settings)?;
+    }
+    state.pipeline_tx
+        .try_send(PipelineCommand::RefreshAsrSettings)
+        .map_err(|e| format!("Failed to apply ASR settings: {:?}", e))
+}
@@ -1,9 +1,9 @@
 // Git Commands - Tauri commands for version control
 
-use tauri::{State, AppHandle, Emitter};
+use tauri::{State, AppHandle};
 use crate::state::AppState;
 use crate::modules::pipeline::PipelineCommand;
-use crate::models::event::ToastPayload;
+use crate::modules::pipeline::utils::emit_error_toast;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,7 +77,7 @@ pub fn rollback_to_commit(
         .try_send(PipelineCommand::RollbackToCommit(commit_hash))
         .map_err(|e| {
             let error_msg = format!("Failed to send rollback command: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -89,7 +89,7 @@ pub fn undo_last_change(state: State<'_, AppState>, app: AppHandle) -> Result<()
         .try_send(PipelineCommand::UndoLastChange)
         .map_err(|e| {
             let error_msg = format!("Failed to send undo command: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
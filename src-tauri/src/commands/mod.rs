@@ -4,9 +4,14 @@ pub mod todo_commands;
 pub mod git_commands;
 pub mod recording_commands;
 pub mod workspace_commands;
+pub mod usage_commands;
+pub mod trace_commands;
+pub mod question_commands;
+pub mod audio_commands;
 
-use tauri::{AppHandle, Emitter};
-use crate::models::event::ToastPayload;
+use tauri::AppHandle;
+use crate::models::event::{CreekEvent, ToastPayload};
+use crate::modules::EventSink;
 
 /// Show a toast notification to the frontend
 #[tauri::command]
@@ -22,9 +27,7 @@ pub fn show_toast(
         duration,
     };
 
-    app.emit("show-toast", payload)
-        .map_err(|e| format!("Failed to emit toast event: {:?}", e))?;
-
+    app.emit(CreekEvent::Toast(payload));
     Ok(())
 }
 
@@ -40,3 +43,6 @@ pub use workspace_commands::{
     delete_workspace, get_current_workspace, set_current_workspace
 };
 
+// Re-export usage commands
+pub use usage_commands::{get_recording_usage, get_workspace_usage};
+
@@ -0,0 +1,41 @@
+// Question Commands - Socratic questions about a recording's document
+
+use crate::modules::{QuestionStore, SocraticQuestion};
+use crate::utils::paths::get_state_db_path;
+
+fn open_store() -> Result<QuestionStore, String> {
+    QuestionStore::new(&get_state_db_path().to_string_lossy())
+        .map_err(|e| format!("Failed to open question DB: {}", e))
+}
+
+/// Questions of a recording in the order they were asked, answered ones included
+#[tauri::command]
+pub fn list_socratic_questions(recording_id: String) -> Result<Vec<SocraticQuestion>, String> {
+    open_store()?
+        .list(&recording_id)
+        .map_err(|e| format!("Failed to read questions: {}", e))
+}
+
+/// Mark a question answered from the UI (e.g. the user fixed the document by hand)
+#[tauri::command]
+pub fn answer_socratic_question(recording_id: String, question_id: String, answer: Option<String>) -> Result<(), String> {
+    let closed = open_store()?
+        .answer(&recording_id, std::slice::from_ref(&question_id), None, answer.as_deref())
+        .map_err(|e| format!("Failed to update question: {}", e))?;
+    if closed == 0 {
+        return Err(format!("No open question {}", question_id));
+    }
+    Ok(())
+}
+
+/// Stop asking a question the user considers irrelevant
+#[tauri::command]
+pub fn dismiss_socratic_question(recording_id: String, question_id: String) -> Result<(), String> {
+    let dismissed = open_store()?
+        .dismiss(&recording_id, &question_id)
+        .map_err(|e| format!("Failed to update question: {}", e))?;
+    if !dismissed {
+        return Err(format!("No open question {}", question_id));
+    }
+    Ok(())
+}
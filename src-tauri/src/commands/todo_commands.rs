@@ -1,8 +1,8 @@
 // Todo Management Commands
 
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, State};
 use crate::modules::TodoItem;
-use crate::models::event::ToastPayload;
+use crate::modules::pipeline::utils::emit_error_toast;
 use crate::state::AppState;
 use crate::modules::pipeline::PipelineCommand;
 use crate::utils::paths::get_state_db_path;
@@ -55,7 +55,7 @@ pub fn add_todo(desc: String, state: State<'_, AppState>, app: AppHandle) -> Res
         .try_send(PipelineCommand::AddTodo(desc))
         .map_err(|e| {
             let error_msg = format!("Failed to send add_todo command: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })?;
 
@@ -69,7 +69,7 @@ pub fn update_todo(id: String, desc: String, state: State<'_, AppState>, app: Ap
         .try_send(PipelineCommand::UpdateTodo { id, description: desc })
         .map_err(|e| {
             let error_msg = format!("Failed to send update_todo command: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -81,7 +81,7 @@ pub fn toggle_todo(id: String, state: State<'_, AppState>, app: AppHandle) -> Re
         .try_send(PipelineCommand::ToggleTodo(id))
         .map_err(|e| {
             let error_msg = format!("Failed to send toggle_todo command: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })?;
     Ok(true) 
@@ -94,7 +94,7 @@ pub fn delete_todo(id: String, state: State<'_, AppState>, app: AppHandle) -> Re
         .try_send(PipelineCommand::DeleteTodo(id))
         .map_err(|e| {
             let error_msg = format!("Failed to send delete_todo command: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
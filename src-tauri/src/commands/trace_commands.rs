@@ -0,0 +1,27 @@
+// Trace Commands - per-turn decision records
+
+use crate::modules::TraceStore;
+use crate::modules::turn_trace::{TurnTrace, TurnTraceSummary};
+use crate::utils::paths::get_state_db_path;
+
+fn open_store() -> Result<TraceStore, String> {
+    TraceStore::new(&get_state_db_path().to_string_lossy())
+        .map_err(|e| format!("Failed to open trace DB: {}", e))
+}
+
+/// Traced turns of a recording, oldest first
+#[tauri::command]
+pub fn list_turn_traces(recording_id: String) -> Result<Vec<TurnTraceSummary>, String> {
+    open_store()?
+        .list(&recording_id)
+        .map_err(|e| format!("Failed to read turn traces: {}", e))
+}
+
+/// Full trace of one turn: routing, retrieval, steps with raw responses, commit, timings
+#[tauri::command]
+pub fn get_turn_trace(turn_id: String) -> Result<TurnTrace, String> {
+    open_store()?
+        .get(&turn_id)
+        .map_err(|e| format!("Failed to read turn trace: {}", e))?
+        .ok_or_else(|| format!("No trace for turn {}", turn_id))
+}
@@ -0,0 +1,51 @@
+// Usage Commands - token and cost summaries
+
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+use crate::modules::{RouterBenchmark, UsageSummary, UsageTracker, WorkspaceManager};
+use crate::modules::intent_router::RouterModeStats;
+use crate::modules::usage_tracker::recording_ids_in;
+use crate::utils::paths::get_state_db_path;
+
+fn open_tracker() -> Result<UsageTracker, String> {
+    UsageTracker::new(&get_state_db_path().to_string_lossy())
+        .map_err(|e| format!("Failed to open usage DB: {}", e))
+}
+
+/// Token usage and cost of one recording, broken down by caller, model and turn
+#[tauri::command]
+pub fn get_recording_usage(recording_id: String) -> Result<UsageSummary, String> {
+    open_tracker()?
+        .recording_summary(&recording_id)
+        .map_err(|e| format!("Failed to read usage: {}", e))
+}
+
+/// Token usage and cost across all recordings of a workspace (current workspace if omitted)
+#[tauri::command]
+pub async fn get_workspace_usage(app_handle: AppHandle, workspace_id: Option<String>) -> Result<UsageSummary, String> {
+    let workspace = {
+        let workspace_manager = app_handle.state::<Arc<tokio::sync::RwLock<WorkspaceManager>>>();
+        let manager = workspace_manager.read().await;
+        match workspace_id {
+            Some(id) => manager.list_workspaces()?.into_iter().find(|w| w.id == id),
+            None => manager.get_current_workspace()?,
+        }
+    }
+    .ok_or_else(|| "Workspace not found".to_string())?;
+
+    let recording_ids = recording_ids_in(&workspace.path.join("recordings"));
+    open_tracker()?
+        .summary(&recording_ids)
+        .map_err(|e| format!("Failed to read usage: {}", e))
+}
+
+/// Routing latency and token cost per router mode (parallel / fused) since startup
+#[tauri::command]
+pub fn get_router_benchmark(benchmark: State<'_, Arc<RouterBenchmark>>) -> Vec<RouterModeStats> {
+    benchmark.snapshot()
+}
+
+#[tauri::command]
+pub fn reset_router_benchmark(benchmark: State<'_, Arc<RouterBenchmark>>) {
+    benchmark.reset();
+}
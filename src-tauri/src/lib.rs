@@ -6,11 +6,11 @@ pub mod utils;
 pub mod prompts;
 pub mod commands;
 
-use tauri::{State, AppHandle, Emitter, Manager};
+use tauri::{State, AppHandle, Manager};
 use state::AppState;
 use modules::pipeline::{run_pipeline, PipelineCommand};
 use modules::workspace_manager::WorkspaceManager;
-use models::event::ToastPayload;
+use modules::pipeline::utils::emit_error_toast;
 use tokio::sync::mpsc;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -22,7 +22,7 @@ fn start_recording(state: State<'_, AppState>, app: AppHandle, recording_id: Str
         .try_send(PipelineCommand::StartRecording { recording_id })
         .map_err(|e| {
             let error_msg = format!("Failed to start recording: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -33,7 +33,7 @@ fn load_recording(state: State<'_, AppState>, app: AppHandle, recording_id: Stri
         .try_send(PipelineCommand::LoadRecording { recording_id })
         .map_err(|e| {
             let error_msg = format!("Failed to load recording: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -44,7 +44,7 @@ fn pause_recording(state: State<'_, AppState>, app: AppHandle) -> Result<(), Str
         .try_send(PipelineCommand::PauseRecording)
         .map_err(|e| {
             let error_msg = format!("Failed to pause recording: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -55,7 +55,18 @@ fn resume_recording(state: State<'_, AppState>, app: AppHandle) -> Result<(), St
         .try_send(PipelineCommand::ResumeRecording)
         .map_err(|e| {
             let error_msg = format!("Failed to resume recording: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
+            error_msg
+        })
+}
+
+#[tauri::command]
+fn reconnect_asr(state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    state.pipeline_tx
+        .try_send(PipelineCommand::ReconnectAsr)
+        .map_err(|e| {
+            let error_msg = format!("Failed to reconnect ASR: {:?}", e);
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -66,7 +77,7 @@ fn stop_recording(state: State<'_, AppState>, app: AppHandle) -> Result<(), Stri
         .try_send(PipelineCommand::StopRecording)
         .map_err(|e| {
             let error_msg = format!("Failed to stop recording: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -77,7 +88,7 @@ fn reset_document(state: State<'_, AppState>, app: AppHandle) -> Result<(), Stri
         .try_send(PipelineCommand::ResetDocument)
         .map_err(|e| {
             let error_msg = format!("Failed to reset document: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -88,7 +99,7 @@ fn update_document(state: State<'_, AppState>, app: AppHandle, content: String)
         .try_send(PipelineCommand::UpdateDocument(content))
         .map_err(|e| {
             let error_msg = format!("Failed to update document: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -99,7 +110,7 @@ fn ingest_document(state: State<'_, AppState>, app: AppHandle, filename: String,
         .try_send(PipelineCommand::IngestDocument { filename, content })
         .map_err(|e| {
             let error_msg = format!("Failed to ingest document: {:?}", e);
-            let _ = app.emit("show-toast", ToastPayload::error(&error_msg));
+            emit_error_toast(&app, &error_msg);
             error_msg
         })
 }
@@ -127,6 +138,7 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(AppState { pipeline_tx: tx })
+        .manage(Arc::new(modules::RouterBenchmark::default()))
         .setup(|app| {
             let handle = app.handle().clone();
             
@@ -148,6 +160,7 @@ pub fn run() {
             pause_recording,
             resume_recording,
             stop_recording, 
+            reconnect_asr,
             reset_document,
             update_document,
             ingest_document,
@@ -172,6 +185,22 @@ pub fn run() {
             commands::workspace_commands::delete_workspace,
             commands::workspace_commands::get_current_workspace,
             commands::workspace_commands::set_current_workspace,
+            commands::usage_commands::get_recording_usage,
+            commands::usage_commands::get_workspace_usage,
+            commands::usage_commands::get_router_benchmark,
+            commands::usage_commands::reset_router_benchmark,
+            commands::trace_commands::list_turn_traces,
+            commands::trace_commands::get_turn_trace,
+            commands::question_commands::list_socratic_questions,
+            commands::question_commands::answer_socratic_question,
+            commands::question_commands::dismiss_socratic_question,
+            commands::audio_commands::list_input_devices,
+            commands::audio_commands::get_input_device,
+            commands::audio_commands::set_input_device,
+            commands::audio_commands::get_audio_archive_settings,
+            commands::audio_commands::set_audio_archive_settings,
+            commands::audio_commands::get_asr_settings,
+            commands::audio_commands::set_asr_settings,
             load_recording,
         ])
         .run(tauri::generate_context!())
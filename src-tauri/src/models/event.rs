@@ -1,13 +1,112 @@
 use serde::{Serialize, Deserialize};
-use crate::modules::TodoItem;
+use crate::modules::{SocraticQuestion, TodoItem};
+use crate::services::asr::{AsrStatus, AudioLevel};
 
-/// Update from ASR (simulated or real)
+/// Everything the backend tells a frontend. `name()` is the Tauri event
+/// name; the serialized variant (untagged) is the event payload.
+#[derive(Clone, Serialize, Debug)]
+#[serde(untagged)]
+pub enum CreekEvent {
+    DocumentUpdate(DocumentUpdate),
+    AgentStatus(AgentStatusPayload),
+    Toast(ToastPayload),
+    TodoUpdate(TodoUpdate),
+    SocraticQuestion(SocraticUpdate),
+    /// Final transcript of a turn as it enters processing
+    Transcript(String),
+    TranscriptPartial(TranscriptUpdate),
+    RecordingStarted(RecordingStarted),
+    RecordingRenamed(RecordingRenamed),
+    /// The recording list changed; reload it
+    RecordingsUpdated,
+    SpeechStart(SpeechActivity),
+    SpeechEnd(SpeechActivity),
+    AudioLevel(AudioLevel),
+    AsrStatus(AsrStatus),
+    TurnQueued(TurnEvent),
+    TurnStarted(TurnEvent),
+    TurnFinished(TurnEvent),
+    TurnAborted(TurnEvent),
+}
+
+impl CreekEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DocumentUpdate(_) => "document-update",
+            Self::AgentStatus(_) => "agent-status",
+            Self::Toast(_) => "show-toast",
+            Self::TodoUpdate(_) => "todo-update",
+            Self::SocraticQuestion(_) => "socratic-question",
+            Self::Transcript(_) => "transcript-update",
+            Self::TranscriptPartial(_) => "transcript-partial",
+            Self::RecordingStarted(_) => "recording-started",
+            Self::RecordingRenamed(_) => "recording-renamed",
+            Self::RecordingsUpdated => "recordings-updated",
+            Self::SpeechStart(_) => "speech-start",
+            Self::SpeechEnd(_) => "speech-end",
+            Self::AudioLevel(_) => "audio-level",
+            Self::AsrStatus(_) => "asr-status",
+            Self::TurnQueued(_) => "turn-queued",
+            Self::TurnStarted(_) => "turn-started",
+            Self::TurnFinished(_) => "turn-finished",
+            Self::TurnAborted(_) => "turn-aborted",
+        }
+    }
+
+    pub fn payload(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    pub fn agent_status(status: &str) -> Self {
+        Self::AgentStatus(AgentStatusPayload { status: status.to_string() })
+    }
+}
+
+/// Interim ASR text (`transcript-partial`). Partials for an item replace
+/// each other; the one with `is_final` set replaces them all.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct TranscriptUpdate {
+    pub item_id: String,
     pub text: String,
     pub is_final: bool,
 }
 
+/// Local VAD boundary (`speech-start` / `speech-end`)
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SpeechActivity {
+    /// Milliseconds of audio since recording started
+    pub at_ms: u64,
+    /// Length of the speech segment (`speech-end` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Turn lifecycle (`turn-queued` / `turn-started` / `turn-finished` / `turn-aborted`)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TurnEvent {
+    pub turn_id: String,
+    /// Turn text (`turn-queued`; grows when pending turns coalesce)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Place in the queue, 1 = next to run (`turn-queued`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    /// Why the turn was dropped or rolled back (`turn-aborted`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl TurnEvent {
+    pub fn new(turn_id: &str) -> Self {
+        Self { turn_id: turn_id.to_string(), text: None, position: None, reason: None }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
 /// Document state update
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct DocumentUpdate {
@@ -21,12 +120,34 @@ pub struct TodoUpdate {
     pub todos: Vec<TodoItem>,
 }
 
+/// Questions about the document after a Socratic review (`socratic-question`)
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SocraticUpdate {
+    pub recording_id: String,
+    /// Every open question, most important first
+    pub questions: Vec<SocraticQuestion>,
+    /// Ids asked by this review
+    pub asked: Vec<String>,
+    /// Ids the turn answered
+    pub answered: Vec<String>,
+    /// Text to read aloud when `[socratic] speak` is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speak: Option<String>,
+}
+
 /// Recording started event
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RecordingStarted {
     pub recording_id: String,
 }
 
+/// Auto-naming gave a recording its name
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RecordingRenamed {
+    pub id: String,
+    pub new_name: String,
+}
+
 /// Payload for toast notifications
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ToastPayload {
@@ -4,12 +4,13 @@ use futures_util::StreamExt;
 use regex::Regex;
 
 use crate::modules::pipeline::utils::emit_and_save;
-use crate::modules::pipeline::state_updater::update_state_and_git;
+use crate::modules::pipeline::state_updater::update_state;
+use crate::modules::{UsageCaller, PatchOutcome};
 use crate::prompts::document_editing::{
     build_system_message_with_state,
     APPEND_AGENT_PROMPT,
 };
-use crate::services::llm_client::{ChatMessage, LLMClient};
+use crate::services::llm_client::ChatMessage;
 use super::super::{Agent, AgentContext};
 
 pub struct AppendAgent;
@@ -73,7 +74,7 @@ impl Agent for AppendAgent {
         messages.push(ChatMessage { role: "user".to_string(), content: user_content });
 
         // 2. Call LLM & Stream
-        let mut stream = match ctx.llm_coder.stream_completion(messages.clone()).await {
+        let mut stream = match ctx.coder(UsageCaller::AppendAgent).stream_completion(messages.clone()).await {
             Ok(s) => s,
             Err(e) => {
                 error!("[AppendAgent] LLM failed: {:?}", e);
@@ -97,7 +98,7 @@ impl Agent for AppendAgent {
                     first_chunk = false;
                 }
                 ctx.doc_service.append_content(&processed);
-                emit_and_save(&ctx.doc_service, &ctx.app_handle, ctx.recording_id.as_deref());
+                emit_and_save(&ctx.doc_service, &ctx.events, ctx.recording_path.as_deref());
             }
         }
         
@@ -107,19 +108,12 @@ impl Agent for AppendAgent {
             response = re.replace_all(&response, "    ").to_string();
         }
         let response = response.trim().to_string();
+        ctx.trace.llm_response(&response_buffer);
+        ctx.trace.patch_result(if response.is_empty() { PatchOutcome::Unchanged } else { PatchOutcome::Applied });
 
         // 3. Update State & History
         if !response.is_empty() {
-            update_state_and_git(
-                &ctx.doc_service, 
-                &ctx.state_manager, 
-                &ctx.git_manager, 
-                &ctx.todo_agent, 
-                &ctx.llm_flash, 
-                &ctx.app_handle, 
-                ctx.recording_path.as_deref(), 
-                &ctx.transcript
-            ).await;
+            update_state(&ctx.doc_service, &ctx.state_manager);
 
             ctx.chat_history.push(ChatMessage { role: "user".to_string(), content: ctx.transcript.clone() });
             ctx.chat_history.push(ChatMessage { role: "assistant".to_string(), content: response });
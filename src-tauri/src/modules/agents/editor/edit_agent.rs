@@ -3,14 +3,15 @@ use log::{info, error, warn};
 use futures_util::StreamExt;
 
 use crate::modules::pipeline::utils::{emit_and_save, emit_warning_toast};
-use crate::modules::pipeline::state_updater::update_state_and_git;
+use crate::modules::pipeline::state_updater::update_state;
+use crate::modules::{UsageCaller, PatchOutcome};
 use crate::modules::pipeline::types::MAX_EDIT_RETRIES;
 use crate::prompts::document_editing::{
     build_system_message_with_state,
     build_edit_retry_prompt,
     EDIT_AGENT_PROMPT,
 };
-use crate::services::llm_client::{ChatMessage, LLMClient};
+use crate::services::llm_client::ChatMessage;
 use super::super::{Agent, AgentContext};
 
 pub struct EditAgent;
@@ -75,7 +76,7 @@ impl Agent for EditAgent {
         let mut success = false;
         
         // First attempt logic
-        let mut stream = match ctx.llm_coder.stream_completion(messages.clone()).await {
+        let mut stream = match ctx.coder(UsageCaller::EditAgent).stream_completion(messages.clone()).await {
              Ok(s) => s,
              Err(e) => {
                  error!("[EditAgent] LLM failed: {:?}", e);
@@ -87,6 +88,8 @@ impl Agent for EditAgent {
             print!("{}", chunk);
         }
 
+        ctx.trace.llm_response(&current_response);
+
         // Retry Loop
         for attempt in 1..=MAX_EDIT_RETRIES {
              let clean_response = self.cleanup_tags(&current_response);
@@ -106,9 +109,10 @@ impl Agent for EditAgent {
 
              match apply_result {
                  Ok(changed) => {
+                     ctx.trace.patch_result(if changed { PatchOutcome::Applied } else { PatchOutcome::Unchanged });
                      if changed {
                          info!("[EditAgent] Edit applied successfully");
-                         emit_and_save(&ctx.doc_service, &ctx.app_handle, ctx.recording_id.as_deref());
+                         emit_and_save(&ctx.doc_service, &ctx.events, ctx.recording_path.as_deref());
                          self.finalize(&clean_response, ctx).await;
                      } else {
                          warn!("[EditAgent] No changes applied (Content identical?)");
@@ -120,8 +124,11 @@ impl Agent for EditAgent {
                  }
                  Err(e) => {
                      warn!("[EditAgent] Apply failed (Attempt {}): {}", attempt, e);
+                     ctx.trace.patch_result(PatchOutcome::Rejected { reason: e.clone() });
                      if attempt == MAX_EDIT_RETRIES { break; }
                      
+                     tracing::info!(monotonic_counter.edit_retries = 1);
+
                      // Construct Retry Prompt
                      let retry_prompt = build_edit_retry_prompt(&e);
                      
@@ -142,12 +149,13 @@ impl Agent for EditAgent {
                      retry_messages.push(ChatMessage { role: "user".to_string(), content: retry_prompt });
 
                      // Call LLM
-                     if let Ok(mut s) = ctx.llm_coder.stream_completion(retry_messages).await {
+                     if let Ok(mut s) = ctx.coder(UsageCaller::EditAgent).stream_completion(retry_messages).await {
                          let mut new_resp = String::new();
                          while let Some(Ok(chunk)) = s.next().await {
                              new_resp.push_str(&chunk);
                              print!("{}", chunk);
                          }
+                         ctx.trace.llm_response(&new_resp);
                          current_response = new_resp;
                      } else {
                          break;
@@ -158,7 +166,8 @@ impl Agent for EditAgent {
 
         if !success {
             error!("[EditAgent] Failed after retries.");
-            emit_warning_toast(&ctx.app_handle, "Failed to apply edits after retries");
+            tracing::info!(monotonic_counter.edit_failures = 1);
+            emit_warning_toast(&ctx.events, "Failed to apply edits after retries");
         }
 
         Ok(())
@@ -184,16 +193,7 @@ impl EditAgent {
     }
 
     async fn finalize(&self, response: &str, ctx: &mut AgentContext) {
-        update_state_and_git(
-            &ctx.doc_service, 
-            &ctx.state_manager, 
-            &ctx.git_manager, 
-            &ctx.todo_agent, 
-            &ctx.llm_flash, 
-            &ctx.app_handle, 
-            ctx.recording_path.as_deref(), 
-            &ctx.transcript
-        ).await;
+        update_state(&ctx.doc_service, &ctx.state_manager);
         self.finalize_no_save(response, ctx).await;
     }
 
@@ -4,12 +4,13 @@ use futures_util::StreamExt;
 use regex::Regex;
 
 use crate::modules::pipeline::utils::{emit_and_save, emit_warning_toast};
-use crate::modules::pipeline::state_updater::update_state_and_git;
+use crate::modules::pipeline::state_updater::update_state;
+use crate::modules::{UsageCaller, PatchOutcome};
 use crate::prompts::document_editing::{
     build_system_message_with_state,
     GREP_AGENT_PROMPT,
 };
-use crate::services::llm_client::{ChatMessage, LLMClient};
+use crate::services::llm_client::ChatMessage;
 use super::super::{Agent, AgentContext};
 
 pub struct GrepAgent;
@@ -60,7 +61,7 @@ impl Agent for GrepAgent {
         messages.push(ChatMessage { role: "user".to_string(), content: user_content });
 
         // 2. Call LLM
-        let mut stream = match ctx.llm_coder.stream_completion(messages).await {
+        let mut stream = match ctx.coder(UsageCaller::GrepAgent).stream_completion(messages).await {
             Ok(s) => s,
             Err(e) => {
                 error!("[GrepAgent] LLM failed: {:?}", e);
@@ -74,6 +75,8 @@ impl Agent for GrepAgent {
             print!("{}", chunk);
         }
 
+        ctx.trace.llm_response(&response);
+
         // 3. Parse and Apply
         // WRITE LOCK: Re-fetch latest doc
         let latest_doc = ctx.doc_service.get_snapshot().content;
@@ -95,20 +98,12 @@ impl Agent for GrepAgent {
             };
 
             if new_doc != latest_doc {
+                ctx.trace.patch_result(PatchOutcome::Applied);
                 ctx.doc_service.reset(new_doc);
-                emit_and_save(&ctx.doc_service, &ctx.app_handle, ctx.recording_id.as_deref());
+                emit_and_save(&ctx.doc_service, &ctx.events, ctx.recording_path.as_deref());
                 
                 // Update State
-                 update_state_and_git(
-                    &ctx.doc_service, 
-                    &ctx.state_manager, 
-                    &ctx.git_manager, 
-                    &ctx.todo_agent, 
-                    &ctx.llm_flash, 
-                    &ctx.app_handle, 
-                    ctx.recording_path.as_deref(), 
-                    &ctx.transcript
-                ).await;
+                 update_state(&ctx.doc_service, &ctx.state_manager);
 
                 // Update History
                 ctx.chat_history.push(ChatMessage { role: "user".to_string(), content: ctx.transcript.clone() });
@@ -119,10 +114,12 @@ impl Agent for GrepAgent {
                     ctx.chat_history.drain(0..remove);
                 }
             } else {
-                 emit_warning_toast(&ctx.app_handle, &format!("Grep: Pattern not found '{}'", find));
+                 ctx.trace.patch_result(PatchOutcome::Rejected { reason: format!("Pattern not found '{}'", find) });
+                 emit_warning_toast(&ctx.events, &format!("Grep: Pattern not found '{}'", find));
             }
         } else {
             warn!("[GrepAgent] No FIND pattern found in response");
+            ctx.trace.patch_result(PatchOutcome::Rejected { reason: "No FIND pattern in response".to_string() });
         }
 
         Ok(())
@@ -3,8 +3,9 @@ use log::{info, warn};
 use futures_util::StreamExt;
 
 use crate::modules::pipeline::utils::{emit_update, emit_success_toast, emit_error_toast};
-use crate::modules::pipeline::state_updater::update_state_and_git;
-use crate::services::llm_client::{ChatMessage, LLMClient};
+use crate::modules::pipeline::state_updater::update_state;
+use crate::modules::{UsageCaller, PatchOutcome};
+use crate::services::llm_client::ChatMessage;
 use super::super::{Agent, AgentContext};
 
 pub struct UndoAgent;
@@ -46,10 +47,11 @@ impl Agent for UndoAgent {
                     
                     let messages = vec![ChatMessage { role: "user".to_string(), content: prompt }];
                     
-                    let target_hash = match ctx.llm_flash.stream_completion(messages).await {
+                    let target_hash = match ctx.flash(UsageCaller::UndoAgent).stream_completion(messages).await {
                         Ok(mut stream) => {
                             let mut s = String::new();
                             while let Some(Ok(chunk)) = stream.next().await { s.push_str(&chunk); }
+                            ctx.trace.llm_response(&s);
                             s.trim().to_string()
                         },
                         Err(_) => commits[1].0.clone() // Fallback
@@ -58,26 +60,19 @@ impl Agent for UndoAgent {
                      info!("[Undo Target] {}", clean_hash);
 
                      if let Ok(restored) = ctx.git_manager.rollback(rec_path, &clean_hash) {
+                         ctx.trace.patch_result(PatchOutcome::Applied);
                          ctx.doc_service.reset(restored.clone());
-                         emit_update(&ctx.doc_service, &ctx.app_handle);
+                         emit_update(&ctx.doc_service, &ctx.events);
                          ctx.state_manager.update_document(restored);
-                         emit_success_toast(&ctx.app_handle, "Rollback successful");
+                         emit_success_toast(&ctx.events, "Rollback successful");
                          
-                         update_state_and_git(
-                            &ctx.doc_service, 
-                            &ctx.state_manager, 
-                            &ctx.git_manager, 
-                            &ctx.todo_agent, 
-                            &ctx.llm_flash, 
-                            &ctx.app_handle, 
-                            ctx.recording_path.as_deref(), 
-                            &ctx.transcript
-                        ).await;
+                         update_state(&ctx.doc_service, &ctx.state_manager);
                         
                         ctx.chat_history.push(ChatMessage { role: "user".to_string(), content: ctx.transcript.clone() });
                         ctx.chat_history.push(ChatMessage { role: "assistant".to_string(), content: format!("ACTION: UNDO (to {})", clean_hash) });
                      } else {
-                         emit_error_toast(&ctx.app_handle, "Rollback failed");
+                         ctx.trace.patch_result(PatchOutcome::Rejected { reason: format!("Rollback to {} failed", clean_hash) });
+                         emit_error_toast(&ctx.events, "Rollback failed");
                      }
             }
             _ => { warn!("Not enough history to undo"); }
@@ -1,15 +1,16 @@
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::path::PathBuf;
-use tauri::AppHandle;
 
 use crate::modules::document_service::DocumentService;
-use crate::modules::{StateManager, GitManager, TodoAgent, RagService, DocIntent, ToolIntent};
+use crate::modules::{StateManager, GitManager, TodoAgent, RagService, DocIntent, ToolIntent, UsageScope, UsageCaller, EventSink, TraceRecorder};
 use crate::modules::intent_router::PlanStep;
-use crate::services::llm_client::{OpenAILikeClient, ChatMessage};
+use crate::services::llm_client::{LLMClient, ChatMessage};
+use crate::services::llm_provider::{LLMRegistry, ModelRole};
 
 pub mod rag_agent;
 pub mod search_agent;
+pub mod socratic_agent;
 pub mod editor;
 
 /// Context passed between agents in the pipeline
@@ -17,9 +18,16 @@ pub struct AgentContext {
     // --- Inputs / Services ---
     pub transcript: String,
     pub doc_service: Arc<DocumentService>,
-    pub llm_coder: Arc<OpenAILikeClient>,
-    pub llm_flash: Arc<OpenAILikeClient>,
-    pub app_handle: AppHandle,
+    pub llm_coder: Arc<dyn LLMClient>,
+    pub llm_flash: Arc<dyn LLMClient>,
+    /// All role clients (focus, todo, commit message, ...)
+    pub llms: Arc<LLMRegistry>,
+    /// Token usage attribution for this turn
+    pub usage: UsageScope,
+    /// Where progress and toasts go (webview or log)
+    pub events: Arc<dyn EventSink>,
+    /// Structured record of this turn (responses, patch results)
+    pub trace: TraceRecorder,
     pub state_manager: Arc<StateManager>,
     pub git_manager: Arc<GitManager>,
     pub todo_agent: Arc<TodoAgent>,
@@ -57,9 +65,9 @@ impl AgentContext {
     pub fn new(
         transcript: String,
         doc_service: Arc<DocumentService>,
-        llm_coder: Arc<OpenAILikeClient>,
-        llm_flash: Arc<OpenAILikeClient>,
-        app_handle: AppHandle,
+        llms: Arc<LLMRegistry>,
+        usage: UsageScope,
+        events: Arc<dyn EventSink>,
         state_manager: Arc<StateManager>,
         git_manager: Arc<GitManager>,
         todo_agent: Arc<TodoAgent>,
@@ -74,9 +82,12 @@ impl AgentContext {
         Self {
             transcript,
             doc_service,
-            llm_coder,
-            llm_flash,
-            app_handle,
+            llm_coder: llms.get(ModelRole::Coder),
+            llm_flash: llms.get(ModelRole::Flash),
+            llms,
+            usage,
+            events,
+            trace: TraceRecorder::default(),
             state_manager,
             git_manager,
             todo_agent,
@@ -95,6 +106,18 @@ impl AgentContext {
     }
 }
 
+impl AgentContext {
+    /// Coder client with its calls attributed to `caller`
+    pub fn coder(&self, caller: UsageCaller) -> Arc<dyn LLMClient> {
+        self.usage.meter(self.llm_coder.clone(), caller)
+    }
+
+    /// Flash client with its calls attributed to `caller`
+    pub fn flash(&self, caller: UsageCaller) -> Arc<dyn LLMClient> {
+        self.usage.meter(self.llm_flash.clone(), caller)
+    }
+}
+
 /// The base trait that all sub-agents must implement
 #[async_trait]
 pub trait Agent: Send + Sync {
@@ -3,7 +3,8 @@ use log::{info, warn, error};
 use tokio::time::Duration;
 
 use crate::modules::pipeline::utils::emit_warning_toast;
-use crate::modules::QueryAgent;
+use crate::modules::{QueryAgent, UsageCaller, TraceRecorder};
+use crate::services::llm_provider::ModelRole;
 use super::{Agent, AgentContext};
 
 pub struct RagAgent;
@@ -15,14 +16,16 @@ impl Agent for RagAgent {
     }
 
     async fn execute(&self, ctx: &mut AgentContext) -> anyhow::Result<()> {
+        let llm_query = ctx.usage.meter(ctx.llms.get(ModelRole::RagQuery), UsageCaller::RagQuery);
         let result = Self::gather(
             ctx.need_rag,
             ctx.recording_id.as_deref(),
             &ctx.transcript,
             &ctx.doc_service.get_snapshot().content,
-            &ctx.llm_flash,
+            llm_query.as_ref(),
             &ctx.rag_service,
-            &ctx.app_handle
+            &ctx.events,
+            &ctx.trace,
         ).await?;
         
         ctx.retrieved_context = result;
@@ -31,14 +34,16 @@ impl Agent for RagAgent {
 }
 
 impl RagAgent {
+    #[tracing::instrument(name = "rag", skip_all)]
     pub async fn gather(
         need_rag: bool,
         recording_id: Option<&str>,
         transcript: &str,
         doc_content: &str,
-        llm_flash: &crate::services::llm_client::OpenAILikeClient,
+        llm_query: &dyn crate::services::llm_client::LLMClient,
         rag_service: &crate::modules::RagService,
-        app_handle: &tauri::AppHandle,
+        events: &dyn crate::modules::EventSink,
+        trace: &TraceRecorder,
     ) -> anyhow::Result<String> {
         if !need_rag {
             info!("[RagAgent] Skipped (Router 2: context sufficient)");
@@ -56,28 +61,19 @@ impl RagAgent {
         info!("[RagAgent] Context missing detected, retrieving...");
         let query_agent = QueryAgent::new();
 
-        // 1. Generate Query
-        let query_result = tokio::time::timeout(
-            Duration::from_millis(3000), 
-            query_agent.generate_query(llm_flash, transcript, doc_content)
-        ).await;
-
-        let query = match query_result {
-            Ok(Ok(q)) => q,
-            Ok(Err(e)) => {
+        // 1. Generate Query (RagQuery role deadline; falls back to the raw transcript)
+        let query = match query_agent.generate_query(llm_query, transcript, doc_content).await {
+            Ok(q) => q,
+            Err(e) => {
                 let error_msg = format!("RAG query generation failed: {:?}", e);
                 error!("[RagAgent] {}", error_msg);
-                emit_warning_toast(app_handle, &error_msg);
+                emit_warning_toast(events, &error_msg);
                 return Ok(String::new()); // Fail gracefully
             }
-            Err(_) => {
-                warn!("[RagAgent] Query generation timeout");
-                emit_warning_toast(app_handle, "RAG query generation timeout");
-                return Ok(String::new());
-            }
         };
 
         info!("[RagAgent Query] {}", query);
+        trace.rag_query(&query);
 
         // 2. Retrieve Documents
         let retrieve_result = tokio::time::timeout(
@@ -87,6 +83,9 @@ impl RagAgent {
 
         match retrieve_result {
             Ok(Ok(results)) => {
+                trace.rag_hits(&results);
+                let outcome = if results.is_empty() { "empty" } else { "hit" };
+                tracing::info!(monotonic_counter.rag_retrievals = 1, outcome);
                 if !results.is_empty() {
                     info!("[RagAgent Retrieved] {} items", results.len());
                     let mut retrieved_text = String::new();
@@ -104,13 +103,15 @@ impl RagAgent {
                 }
             },
             Ok(Err(e)) => {
+                tracing::info!(monotonic_counter.rag_retrievals = 1, outcome = "error");
                 let error_msg = format!("RAG retrieval failed: {:?}", e);
                 error!("[RagAgent] {}", error_msg);
-                emit_warning_toast(app_handle, &error_msg);
+                emit_warning_toast(events, &error_msg);
             },
             Err(_) => {
+                tracing::info!(monotonic_counter.rag_retrievals = 1, outcome = "timeout");
                 warn!("[RagAgent] Retrieve timeout");
-                emit_warning_toast(app_handle, "RAG retrieval timeout");
+                emit_warning_toast(events, "RAG retrieval timeout");
             },
         }
 
@@ -0,0 +1,340 @@
+// Socratic Agent
+//
+// Runs in the background after a turn changed the document and pushes back
+// on it instead of writing: vague claims, missing specifics, unstated
+// assumptions, and contradictions within the document or with what was said
+// in earlier turns (RAG history). Each review also decides which open
+// questions the turn answered; turns that leave the document alone (a spoken
+// answer routed to NO-OP) still get that check while questions are open.
+// New questions are stored (see socratic.rs), capped by `[socratic]` and sent
+// to the frontend as `socratic-question` by the pipeline.
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use tokio::time::Duration;
+
+use crate::models::event::SocraticUpdate;
+use crate::modules::socratic::NewQuestion;
+use crate::modules::{QuestionStore, RagService, SearchResult, SocraticQuestion};
+use crate::prompts::socratic::build_socratic_prompt;
+use crate::services::llm_client::{collect_stream, ChatMessage, LLMClient, UsageSink};
+use crate::services::llm_json::{complete_json_lenient, parse_lenient, ResponseFormat};
+use crate::services::llm_provider::SocraticConfig;
+
+/// Earlier turns shown to the reviewer
+const HISTORY_TOP_K: usize = 5;
+/// Documents shorter than this have nothing to question yet
+const MIN_DOC_CHARS: usize = 80;
+
+/// What one review decided
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SocraticReview {
+    /// Ids of open questions the turn answered
+    #[serde(default)]
+    pub answered: Vec<String>,
+    #[serde(default)]
+    pub questions: Vec<NewQuestion>,
+}
+
+fn review_format() -> ResponseFormat {
+    ResponseFormat::new("socratic_review", json!({
+        "type": "object",
+        "properties": {
+            "answered": { "type": "array", "items": { "type": "string" } },
+            "questions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["contradiction", "assumption", "missing_specifics", "vague"] },
+                        "quote": { "type": "string" },
+                        "question": { "type": "string" },
+                        "priority": { "type": "integer", "enum": [1, 2, 3] }
+                    },
+                    "required": ["kind", "quote", "question", "priority"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["answered", "questions"],
+        "additionalProperties": false
+    }))
+}
+
+pub struct SocraticAgent {
+    store: Arc<QuestionStore>,
+    config: RwLock<SocraticConfig>,
+}
+
+impl SocraticAgent {
+    pub fn new(store: Arc<QuestionStore>) -> Self {
+        Self {
+            store,
+            config: RwLock::new(SocraticConfig::default()),
+        }
+    }
+
+    pub fn with_config(self, config: SocraticConfig) -> Self {
+        self.configure(config);
+        self
+    }
+
+    /// Swap in settings from a reloaded creek.toml
+    pub fn configure(&self, config: SocraticConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.read().unwrap().enabled
+    }
+
+    pub fn store(&self) -> &Arc<QuestionStore> {
+        &self.store
+    }
+
+    /// Ask the model what the document leaves open (at most `max_questions`
+    /// new questions; 0 only checks which open ones were answered)
+    pub async fn review<T: LLMClient + ?Sized>(
+        &self,
+        llm: &T,
+        current_doc: &str,
+        user_input: &str,
+        history: &[SearchResult],
+        open_questions: &[SocraticQuestion],
+        max_questions: usize,
+    ) -> Result<SocraticReview> {
+        let prompt = build_socratic_prompt(current_doc, user_input, history, open_questions, max_questions);
+        let messages = vec![
+            ChatMessage {
+                role: "user".to_string(),
+                content: prompt,
+            }
+        ];
+
+        let review = complete_json_lenient(llm, messages.clone(), review_format(), UsageSink::none()).await
+            .map_err(|e| anyhow::anyhow!("Review failed: {}", e))?;
+        if let Some(review) = review {
+            return Ok(review);
+        }
+
+        // The prompt spells out the same JSON shape
+        let stream = llm.stream_completion(messages).await
+            .map_err(|e| anyhow::anyhow!("LLM error: {:?}", e))?;
+        let raw = collect_stream(stream).await
+            .map_err(|e| anyhow::anyhow!("Stream error: {:?}", e))?;
+        parse_lenient(&raw).map_err(|e| anyhow::anyhow!("Failed to parse review: {}", e))
+    }
+
+    /// Drop questions that repeat an open one, then keep the most important
+    /// within the per-turn and open-question limits
+    fn select(&self, mut proposed: Vec<NewQuestion>, open_questions: &[SocraticQuestion], max_questions: usize) -> Vec<NewQuestion> {
+        let config = self.config.read().unwrap();
+        let mut seen: HashSet<String> = open_questions.iter().map(|q| normalize(&q.question)).collect();
+        proposed.retain(|q| !q.question.trim().is_empty() && seen.insert(normalize(&q.question)));
+        for q in &mut proposed {
+            q.priority = q.priority.clamp(1, 3);
+        }
+        proposed.sort_by_key(|q| (q.priority, q.kind.rank()));
+
+        let room = config.max_open.saturating_sub(open_questions.len());
+        proposed.truncate(max_questions.min(room));
+        proposed
+    }
+
+    /// How many new questions a turn may raise: none when it left the
+    /// document alone (or it is too short), in which case only the open
+    /// questions are checked; None when there is nothing to review
+    fn question_budget(&self, current_doc: &str, changed: bool, open_questions: usize) -> Option<usize> {
+        if changed && current_doc.trim().chars().count() >= MIN_DOC_CHARS {
+            Some(self.config.read().unwrap().max_questions)
+        } else if open_questions > 0 {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Review the document after `turn_id`: close what the turn answered and,
+    /// if it `changed` the document, ask new questions. Returns the update for
+    /// the frontend when anything changed.
+    pub async fn review_turn(
+        &self,
+        llm: &dyn LLMClient,
+        rag_service: &RagService,
+        recording_id: &str,
+        turn_id: &str,
+        transcript: &str,
+        current_doc: &str,
+        changed: bool,
+    ) -> Result<Option<SocraticUpdate>> {
+        if !self.enabled() {
+            return Ok(None);
+        }
+        let open_questions = self.store.open(recording_id)?;
+        let Some(max_questions) = self.question_budget(current_doc, changed, open_questions.len()) else {
+            return Ok(None);
+        };
+        let history = if max_questions == 0 {
+            vec![]
+        } else {
+            self.history(rag_service, recording_id, transcript).await
+        };
+
+        info!("[Socratic Agent] Reviewing (open questions: {}, history: {})", open_questions.len(), history.len());
+        let review = self.review(llm, current_doc, transcript, &history, &open_questions, max_questions).await?;
+        self.apply(recording_id, turn_id, transcript, review, open_questions, max_questions)
+    }
+
+    /// Store what a review decided
+    fn apply(
+        &self,
+        recording_id: &str,
+        turn_id: &str,
+        transcript: &str,
+        review: SocraticReview,
+        open_questions: Vec<SocraticQuestion>,
+        max_questions: usize,
+    ) -> Result<Option<SocraticUpdate>> {
+        let open_ids: HashSet<&str> = open_questions.iter().map(|q| q.id.as_str()).collect();
+        let answered: Vec<String> = review.answered.into_iter()
+            .filter(|id| open_ids.contains(id.trim()))
+            .map(|id| id.trim().to_string())
+            .collect();
+        if !answered.is_empty() {
+            self.store.answer(recording_id, &answered, Some(turn_id), Some(transcript))?;
+            tracing::info!(monotonic_counter.socratic_questions = answered.len() as u64, status = "answered");
+        }
+
+        let still_open: Vec<SocraticQuestion> = open_questions.into_iter()
+            .filter(|q| !answered.contains(&q.id))
+            .collect();
+        let asked = self.store.ask(recording_id, Some(turn_id), &self.select(review.questions, &still_open, max_questions))?;
+        if !asked.is_empty() {
+            tracing::info!(monotonic_counter.socratic_questions = asked.len() as u64, status = "asked");
+        }
+
+        if answered.is_empty() && asked.is_empty() {
+            return Ok(None);
+        }
+        info!("[Socratic Agent] {} answered, {} asked", answered.len(), asked.len());
+
+        let speak = if self.config.read().unwrap().speak {
+            asked.iter()
+                .min_by_key(|q| (q.priority, q.kind.rank()))
+                .map(|q| q.question.clone())
+        } else {
+            None
+        };
+        Ok(Some(SocraticUpdate {
+            recording_id: recording_id.to_string(),
+            questions: self.store.open(recording_id)?,
+            asked: asked.into_iter().map(|q| q.id).collect(),
+            answered,
+            speak,
+        }))
+    }
+
+    /// Earlier statements related to this turn, without the turn itself
+    /// (stored in the background, so it may already be indexed)
+    async fn history(&self, rag_service: &RagService, recording_id: &str, transcript: &str) -> Vec<SearchResult> {
+        match tokio::time::timeout(
+            Duration::from_millis(1000),
+            rag_service.retrieve_unified(recording_id, transcript, HISTORY_TOP_K + 1),
+        ).await {
+            Ok(Ok(results)) => results.into_iter()
+                .filter(|r| r.content.trim() != transcript.trim())
+                .take(HISTORY_TOP_K)
+                .collect(),
+            Ok(Err(e)) => {
+                warn!("[Socratic Agent] History retrieval failed: {:?}", e);
+                vec![]
+            }
+            Err(_) => {
+                warn!("[Socratic Agent] History retrieval timed out");
+                vec![]
+            }
+        }
+    }
+}
+
+fn normalize(question: &str) -> String {
+    question.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::QuestionKind;
+    use crate::services::llm_cassette::testing::ScriptedClient;
+    use crate::services::llm_cassette::{Cassette, CassetteClient};
+
+    fn proposed(kind: QuestionKind, question: &str, priority: u8) -> NewQuestion {
+        NewQuestion { kind, question: question.to_string(), quote: None, priority }
+    }
+
+    #[tokio::test]
+    async fn test_review_parses_text_reply_and_selects_new_questions() {
+        let store = Arc::new(QuestionStore::new(":memory:").unwrap());
+        let open = store.ask("rec-1", Some("t1"), &[proposed(QuestionKind::Vague, "Which users?", 2)]).unwrap();
+        let agent = SocraticAgent::new(store)
+            .with_config(SocraticConfig { max_questions: 2, max_open: 3, ..Default::default() });
+
+        // No JSON mode: the text fallback is parsed leniently
+        let llm = ScriptedClient::text(r#"```json
+{"answered": ["q-1"], "questions": [
+  {"kind": "vague", "quote": "many users", "question": "Which users, exactly?", "priority": 3},
+  {"kind": "contradiction", "quote": "ship in Q3", "question": "Q3 here, Q4 earlier: which is it?", "priority": 1}
+]}
+```"#);
+        let review = agent.review(&llm, "# Plan\nShip in Q3 to many users.", "We ship in Q3", &[], &open, 2).await.unwrap();
+        assert_eq!(review.answered, vec!["q-1"]);
+        assert_eq!(review.questions[1].kind, QuestionKind::Contradiction);
+        assert_eq!(review.questions[1].quote.as_deref(), Some("ship in Q3"));
+
+        // Repeats of open questions are dropped; the rest is capped by max_open
+        let mut questions = review.questions;
+        questions.push(proposed(QuestionKind::Assumption, "which users?", 1));
+        questions.push(proposed(QuestionKind::MissingSpecifics, "Who owns it?", 0));
+        let selected = agent.select(questions, &open, 2);
+        assert_eq!(selected.iter().map(|q| q.question.as_str()).collect::<Vec<_>>(),
+            vec!["Q3 here, Q4 earlier: which is it?", "Who owns it?"]);
+        assert_eq!(selected[1].priority, 1);
+        assert!(agent.select(vec![proposed(QuestionKind::Vague, "Why?", 2)], &[open[0].clone(), open[0].clone(), open[0].clone()], 2).is_empty());
+        assert!(agent.select(vec![proposed(QuestionKind::Vague, "Why?", 2)], &[], 0).is_empty());
+
+        // A replay miss fails the review rather than falling back to text
+        let cassette = Arc::new(Cassette::new());
+        let replay = CassetteClient::replay("socratic", cassette.clone());
+        assert!(agent.review(&replay, "# Plan\nShip soon.", "Ship soon", &[], &[], 2).await.is_err());
+        assert_eq!(cassette.unmatched().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_document_only_closes_answered_questions() {
+        let store = Arc::new(QuestionStore::new(":memory:").unwrap());
+        let agent = SocraticAgent::new(store.clone());
+        let doc = "# Launch plan\n\nWe launch soon to many users, with a small team and a modest budget.\n";
+        assert_eq!(agent.question_budget(doc, true, 0), Some(2));
+        assert_eq!(agent.question_budget(doc, false, 0), None);
+        assert_eq!(agent.question_budget("# Plan", true, 0), None);
+
+        let open = store.ask("rec-1", Some("t1"), &[proposed(QuestionKind::MissingSpecifics, "What date?", 1)]).unwrap();
+        // A NO-OP turn that answers: checked, and nothing new is asked even if proposed
+        let budget = agent.question_budget(doc, false, open.len()).unwrap();
+        assert_eq!(budget, 0);
+        let llm = ScriptedClient::text(r#"{"answered": ["q-1"], "questions": [
+            {"kind": "vague", "quote": "many users", "question": "Which users?", "priority": 2}]}"#);
+        let review = agent.review(&llm, doc, "The launch is on March 3rd", &[], &open, budget).await.unwrap();
+        let update = agent.apply("rec-1", "t2", "The launch is on March 3rd", review, open, budget).unwrap().unwrap();
+        assert_eq!(update.answered, vec!["q-1"]);
+        assert!(update.asked.is_empty() && update.questions.is_empty());
+        assert_eq!(store.list("rec-1").unwrap()[0].answer.as_deref(), Some("The launch is on March 3rd"));
+    }
+}
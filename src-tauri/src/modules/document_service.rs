@@ -1,5 +1,5 @@
 use std::sync::{Arc, Mutex};
-use crate::utils::diff::{DiffParser, apply_patch, PendingPatch};
+use crate::utils::diff::{DiffParser, apply_patch, PendingPatch, PatchError};
 
 /// Represents the current state of the document
 #[derive(Debug, Clone)]
@@ -17,9 +17,17 @@ impl DocumentState {
     }
 }
 
+/// SEARCH/REPLACE patches applied and rejected since the service was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatchStats {
+    pub applied: usize,
+    pub failed: usize,
+}
+
 /// Service for managing document state and applying incremental edits
 pub struct DocumentService {
     state: Arc<Mutex<DocumentState>>,
+    patch_stats: Mutex<PatchStats>,
     // Keep one parser for incremental streaming use cases, but we will not rely on its state
     // across responses in the current pipeline (we parse whole response per call).
     #[allow(dead_code)]
@@ -30,6 +38,7 @@ impl DocumentService {
     pub fn new(initial_content: String) -> Self {
         Self {
             state: Arc::new(Mutex::new(DocumentState::new(initial_content))),
+            patch_stats: Mutex::new(PatchStats::default()),
             parser: Mutex::new(DiffParser::new()),
         }
     }
@@ -39,6 +48,10 @@ impl DocumentService {
         self.state.lock().unwrap().clone()
     }
 
+    pub fn patch_stats(&self) -> PatchStats {
+        *self.patch_stats.lock().unwrap()
+    }
+
     /// Reset the document with new content
     pub fn reset(&self, new_content: String) {
         let mut state = self.state.lock().unwrap();
@@ -87,13 +100,25 @@ impl DocumentService {
 
     fn apply_patch_to_state(&self, patch: PendingPatch) -> Result<(), String> {
         let mut state = self.state.lock().unwrap();
-        match apply_patch(&state.content, patch) {
+        let result = apply_patch(&state.content, patch);
+        let mut stats = self.patch_stats.lock().unwrap();
+        match result {
             Ok(new_content) => {
+                tracing::info!(monotonic_counter.patches = 1, result = "applied");
+                stats.applied += 1;
                 state.content = new_content;
                 state.version += 1;
                 Ok(())
             }
-            Err(e) => Err(e.to_string()),
+            Err(e) => {
+                let reason = match e {
+                    PatchError::NotFound => "not_found",
+                    PatchError::ApplyFailed(_) => "apply_failed",
+                };
+                tracing::info!(monotonic_counter.patches = 1, result = reason);
+                stats.failed += 1;
+                Err(e.to_string())
+            }
         }
     }
 }
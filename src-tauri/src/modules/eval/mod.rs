@@ -0,0 +1,196 @@
+// Evaluation Harness
+//
+// Replays a recorded conversation through the real pipeline (routing,
+// agents, commits) and scores the document after every turn against a
+// golden document, so prompt and router changes can be compared offline.
+//
+// A case is a JSON file of `ConversationTurn`s, each with an optional golden:
+//   {"initial_document": "", "turns": [
+//     {"id": "...", "timestamp": 1700000000000, "asr_input": "Add a Risks section",
+//      "golden": "## Risks\n"}]}
+// or a plain transcript (see `headless::parse_transcript`), which has no goldens.
+// LLM responses live next to it in the cassette `<case>.llm.jsonl`: record
+// them once against a real provider, then replay runs without network. Blessing writes
+// the run's snapshots back as goldens (as `<case>.json`) for review.
+//
+// Each case runs in `<out_dir>/<case>/`, a fresh headless workspace that is
+// left behind (document, git history) for inspection.
+
+pub mod score;
+
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::modules::pipeline::headless::{parse_transcript, HeadlessSession};
+use crate::modules::{ConversationTurn, LogSink};
+use crate::services::llm_cassette::{Cassette, CassetteClient};
+use crate::services::llm_provider::{LLMConfig, LLMRegistry};
+use score::{score_turn, CaseReport, TurnResult};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+    #[serde(default)]
+    pub initial_document: String,
+    pub turns: Vec<EvalTurn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalTurn {
+    #[serde(flatten)]
+    pub turn: ConversationTurn,
+    /// Expected document after this turn; the turn is not scored without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub golden: Option<String>,
+}
+
+impl EvalCase {
+    /// Load a case file (`.json`), or a transcript as a case without goldens
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let mut case: Self = serde_json::from_str(&content)
+                .with_context(|| format!("Invalid eval case {}", path.display()))?;
+            case.turns.sort_by_key(|t| t.turn.timestamp);
+            return Ok(case);
+        }
+        let turns = parse_transcript(&content)?.into_iter()
+            .map(|t| EvalTurn { turn: ConversationTurn::new(t.text), golden: None })
+            .collect();
+        Ok(Self { initial_document: String::new(), turns })
+    }
+}
+
+pub struct EvalOptions {
+    /// Router, pricing and budget settings for the run
+    pub config: LLMConfig,
+    /// Real clients to record through; recorded responses are replayed when unset
+    pub record: Option<Arc<LLMRegistry>>,
+    /// Case workspaces are created here
+    pub out_dir: PathBuf,
+    /// Write the run's snapshots back as the case's goldens
+    pub bless: bool,
+}
+
+/// Case name: the file name without its extension
+pub fn case_name(path: &Path) -> Result<String> {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .map(str::to_string)
+        .with_context(|| format!("Cannot name case {}", path.display()))
+}
+
+pub async fn run_case_file(path: &Path, options: &EvalOptions) -> Result<CaseReport> {
+    let name = case_name(path)?;
+    let mut case = EvalCase::load(path)?;
+    let cassette_path = path.with_extension("llm.jsonl");
+
+    let (cassette, llms) = match &options.record {
+        Some(real) => {
+            let cassette = Arc::new(Cassette::new());
+            let llms = LLMRegistry::from_fn(|role| {
+                Arc::new(CassetteClient::record(role.as_str(), real.get(role), cassette.clone()))
+            });
+            (cassette, llms)
+        }
+        None => {
+            let cassette = Arc::new(Cassette::load(&cassette_path)?);
+            if cassette.is_empty() {
+                anyhow::bail!("No recorded responses at {}; record the case first", cassette_path.display());
+            }
+            let llms = LLMRegistry::from_fn(|role| Arc::new(CassetteClient::replay(role.as_str(), cassette.clone())));
+            (cassette, llms)
+        }
+    };
+
+    // Fresh workspace, seeded with the initial document
+    let workspace = options.out_dir.join(&name);
+    if workspace.exists() {
+        std::fs::remove_dir_all(&workspace).context("Failed to clear previous case workspace")?;
+    }
+    let recording_path = workspace.join("recordings").join(&name);
+    std::fs::create_dir_all(&recording_path)?;
+    if !case.initial_document.is_empty() {
+        std::fs::write(recording_path.join(format!("{}.md", name)), &case.initial_document)?;
+    }
+    let session = HeadlessSession::open_with_llms(
+        &workspace, &name, options.config.clone(), Arc::new(llms), Arc::new(LogSink),
+    ).await?;
+
+    let mut results = Vec::with_capacity(case.turns.len());
+    let mut golden_before = Some(case.initial_document.clone());
+    for (i, turn) in case.turns.iter().enumerate() {
+        info!("[Eval] {} turn {}/{}: {}", name, i + 1, case.turns.len(), turn.turn.asr_input);
+        let before = session.document();
+        let patches_before = session.patch_stats();
+        let unmatched_before = cassette.unmatched().len();
+
+        let outcome = session.run_turn(&turn.turn.asr_input).await;
+        // Focus/todo updates run in the background and feed the next turn's prompts
+        cassette.settle().await;
+
+        let after = session.document();
+        let patches = session.patch_stats();
+        let score = turn.golden.as_deref()
+            .map(|golden| score_turn(&before, &after, golden_before.as_deref(), golden));
+        golden_before = turn.golden.clone();
+        results.push(TurnResult {
+            transcript: turn.turn.asr_input.clone(),
+            document: after,
+            error: outcome.err().map(|e| format!("{:#}", e)),
+            patches_applied: patches.applied - patches_before.applied,
+            patches_failed: patches.failed - patches_before.failed,
+            unrecorded_requests: cassette.unmatched().len() - unmatched_before,
+            score,
+        });
+    }
+    session.finish()?;
+
+    if options.record.is_some() {
+        cassette.save(&cassette_path)?;
+        info!("[Eval] Recorded {} responses to {}", cassette.len(), cassette_path.display());
+    }
+    if options.bless {
+        for (turn, result) in case.turns.iter_mut().zip(&results) {
+            turn.golden = Some(result.document.clone());
+        }
+        let case_path = path.with_extension("json");
+        std::fs::write(&case_path, serde_json::to_string_pretty(&case)?)
+            .with_context(|| format!("Failed to write {}", case_path.display()))?;
+        info!("[Eval] Blessed {} goldens into {}", results.len(), case_path.display());
+    }
+
+    Ok(CaseReport::new(name, results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_file_orders_turns_and_keeps_goldens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.json");
+        std::fs::write(&path, r###"{"turns": [
+            {"id": "b", "timestamp": 2, "asr_input": "Add risks", "golden": "## Risks\n"},
+            {"id": "a", "timestamp": 1, "asr_input": "Never mind"}
+        ]}"###).unwrap();
+
+        let case = EvalCase::load(&path).unwrap();
+        let inputs: Vec<&str> = case.turns.iter().map(|t| t.turn.asr_input.as_str()).collect();
+        assert_eq!(inputs, vec!["Never mind", "Add risks"]);
+        assert_eq!(case.turns[1].golden.as_deref(), Some("## Risks\n"));
+        assert_eq!(case_name(&path).unwrap(), "plan");
+
+        // Transcripts become cases without goldens
+        let transcript = dir.path().join("notes.txt");
+        std::fs::write(&transcript, "first\nsecond\n").unwrap();
+        let case = EvalCase::load(&transcript).unwrap();
+        assert_eq!(case.turns.len(), 2);
+        assert!(case.turns.iter().all(|t| t.golden.is_none()));
+        assert_eq!(transcript.with_extension("llm.jsonl"), dir.path().join("notes.llm.jsonl"));
+    }
+}
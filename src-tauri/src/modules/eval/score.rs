@@ -0,0 +1,305 @@
+// Evaluation Scoring
+//
+// Compares the document after each turn with the case's golden document:
+//   - line similarity: lines in common (LCS, blank lines ignored) over all lines
+//   - heading coverage: share of golden headings present, plus missing/extra
+//   - NO-OP accuracy: the turn left the document alone exactly when the
+//     golden did
+// and counts SEARCH/REPLACE patches the edit agent got rejected. Reports
+// serialize to JSON and render to a markdown table.
+
+use serde::Serialize;
+
+/// Outcome of one replayed turn
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnResult {
+    pub transcript: String,
+    /// Document after the turn
+    pub document: String,
+    /// Set when the turn failed and was rolled back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub patches_applied: usize,
+    pub patches_failed: usize,
+    /// LLM requests replay had no recorded response for
+    pub unrecorded_requests: usize,
+    /// Absent for turns without a golden document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<TurnScore>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurnScore {
+    pub exact: bool,
+    pub line_similarity: f64,
+    pub heading_coverage: f64,
+    pub missing_headings: Vec<String>,
+    pub extra_headings: Vec<String>,
+    /// Unknown when the previous turn had no golden to compare against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_noop: Option<bool>,
+    pub was_noop: bool,
+}
+
+/// Score a turn that took the document from `before` to `after`.
+/// `golden_before` is the previous turn's golden (the initial document for the first turn).
+pub fn score_turn(before: &str, after: &str, golden_before: Option<&str>, golden: &str) -> TurnScore {
+    let actual_headings = headings(after);
+    let golden_headings = headings(golden);
+    let missing_headings: Vec<String> = golden_headings.iter()
+        .filter(|h| !actual_headings.contains(h))
+        .cloned()
+        .collect();
+    let extra_headings: Vec<String> = actual_headings.iter()
+        .filter(|h| !golden_headings.contains(h))
+        .cloned()
+        .collect();
+    let heading_coverage = if golden_headings.is_empty() {
+        1.0
+    } else {
+        (golden_headings.len() - missing_headings.len()) as f64 / golden_headings.len() as f64
+    };
+
+    TurnScore {
+        exact: after.trim() == golden.trim(),
+        line_similarity: line_similarity(after, golden),
+        heading_coverage,
+        missing_headings,
+        extra_headings,
+        expected_noop: golden_before.map(|prev| prev.trim() == golden.trim()),
+        was_noop: before.trim() == after.trim(),
+    }
+}
+
+/// Markdown headings outside code fences, normalized to `## Title`
+pub fn headings(doc: &str) -> Vec<String> {
+    let mut in_fence = false;
+    let mut out = Vec::new();
+    for line in doc.lines().map(str::trim) {
+        if line.starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let title = line[level..].trim().trim_end_matches('#').trim();
+        if (1..=6).contains(&level) && line[level..].starts_with(' ') && !title.is_empty() {
+            let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+            out.push(format!("{} {}", "#".repeat(level), title));
+        }
+    }
+    out
+}
+
+/// 2 * LCS / (len a + len b) over trimmed, non-blank lines; 1.0 for two empty documents
+pub fn line_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<&str> = a.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let b: Vec<&str> = b.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut prev = vec![0usize; b.len() + 1];
+    for line in &a {
+        let mut row = vec![0usize; b.len() + 1];
+        for (j, other) in b.iter().enumerate() {
+            row[j + 1] = if line == other { prev[j] + 1 } else { prev[j + 1].max(row[j]) };
+        }
+        prev = row;
+    }
+    2.0 * prev[b.len()] as f64 / (a.len() + b.len()) as f64
+}
+
+/// Aggregates over a set of turns; ratios are absent when nothing was measured
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub turns: usize,
+    pub failed_turns: usize,
+    pub scored_turns: usize,
+    pub exact_turns: usize,
+    pub mean_line_similarity: Option<f64>,
+    pub mean_heading_coverage: Option<f64>,
+    pub patches_applied: usize,
+    pub patches_failed: usize,
+    pub patch_failure_rate: Option<f64>,
+    pub noop_accuracy: Option<f64>,
+    pub unrecorded_requests: usize,
+}
+
+impl Summary {
+    pub fn of<'a>(turns: impl IntoIterator<Item = &'a TurnResult>) -> Self {
+        let mut summary = Self::default();
+        let (mut similarity, mut coverage) = (0.0, 0.0);
+        let (mut noop_known, mut noop_correct) = (0usize, 0usize);
+        for turn in turns {
+            summary.turns += 1;
+            summary.failed_turns += turn.error.is_some() as usize;
+            summary.patches_applied += turn.patches_applied;
+            summary.patches_failed += turn.patches_failed;
+            summary.unrecorded_requests += turn.unrecorded_requests;
+            let Some(score) = &turn.score else { continue };
+            summary.scored_turns += 1;
+            summary.exact_turns += score.exact as usize;
+            similarity += score.line_similarity;
+            coverage += score.heading_coverage;
+            if let Some(expected) = score.expected_noop {
+                noop_known += 1;
+                noop_correct += (expected == score.was_noop) as usize;
+            }
+        }
+        let ratio = |n: f64, d: usize| (d > 0).then(|| n / d as f64);
+        summary.mean_line_similarity = ratio(similarity, summary.scored_turns);
+        summary.mean_heading_coverage = ratio(coverage, summary.scored_turns);
+        summary.patch_failure_rate = ratio(summary.patches_failed as f64, summary.patches_applied + summary.patches_failed);
+        summary.noop_accuracy = ratio(noop_correct as f64, noop_known);
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseReport {
+    pub name: String,
+    pub summary: Summary,
+    pub turns: Vec<TurnResult>,
+}
+
+impl CaseReport {
+    pub fn new(name: String, turns: Vec<TurnResult>) -> Self {
+        Self { name, summary: Summary::of(&turns), turns }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    /// "record" or "replay"
+    pub mode: String,
+    pub overall: Summary,
+    pub cases: Vec<CaseReport>,
+}
+
+impl EvalReport {
+    pub fn new(mode: &str, cases: Vec<CaseReport>) -> Self {
+        let overall = Summary::of(cases.iter().flat_map(|c| &c.turns));
+        Self { mode: mode.to_string(), overall, cases }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Creek evaluation ({})\n\n", self.mode);
+        out.push_str("| Case | Turns | Failed | Exact | Line similarity | Heading coverage | Patch failures | NO-OP accuracy | Unrecorded |\n");
+        out.push_str("|---|---|---|---|---|---|---|---|---|\n");
+        for case in &self.cases {
+            out.push_str(&summary_row(&case.name, &case.summary));
+        }
+        out.push_str(&summary_row("**All**", &self.overall));
+
+        for case in &self.cases {
+            let notes: Vec<String> = case.turns.iter().enumerate()
+                .filter_map(|(i, turn)| turn_notes(turn).map(|note| format!("- Turn {}: {}", i + 1, note)))
+                .collect();
+            if !notes.is_empty() {
+                out.push_str(&format!("\n## {}\n\n{}\n", case.name, notes.join("\n")));
+            }
+        }
+        out
+    }
+}
+
+fn summary_row(name: &str, s: &Summary) -> String {
+    let percent = |r: Option<f64>| r.map(|r| format!("{:.0}%", r * 100.0)).unwrap_or_else(|| "–".to_string());
+    format!(
+        "| {} | {} | {} | {}/{} | {} | {} | {}/{} ({}) | {} | {} |\n",
+        name,
+        s.turns,
+        s.failed_turns,
+        s.exact_turns,
+        s.scored_turns,
+        s.mean_line_similarity.map(|r| format!("{:.2}", r)).unwrap_or_else(|| "–".to_string()),
+        percent(s.mean_heading_coverage),
+        s.patches_failed,
+        s.patches_applied + s.patches_failed,
+        percent(s.patch_failure_rate),
+        percent(s.noop_accuracy),
+        s.unrecorded_requests,
+    )
+}
+
+/// What went wrong in a turn, if anything
+fn turn_notes(turn: &TurnResult) -> Option<String> {
+    let mut notes = Vec::new();
+    if let Some(error) = &turn.error {
+        notes.push(format!("failed ({})", error));
+    }
+    if turn.patches_failed > 0 {
+        notes.push(format!("{} rejected patch(es)", turn.patches_failed));
+    }
+    if let Some(score) = &turn.score {
+        match score.expected_noop {
+            Some(true) if !score.was_noop => notes.push("changed the document, expected NO-OP".to_string()),
+            Some(false) if score.was_noop => notes.push("left the document unchanged, expected an edit".to_string()),
+            _ => {}
+        }
+        if !score.missing_headings.is_empty() {
+            notes.push(format!("missing `{}`", score.missing_headings.join("`, `")));
+        }
+        if !score.extra_headings.is_empty() {
+            notes.push(format!("extra `{}`", score.extra_headings.join("`, `")));
+        }
+    }
+    (!notes.is_empty()).then(|| notes.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headings_and_line_similarity() {
+        let doc = "# Plan\n\n##  Risks ##\n```\n# not a heading\n```\n#hashtag\n- item\n";
+        assert_eq!(headings(doc), vec!["# Plan", "## Risks"]);
+
+        assert_eq!(line_similarity("", "\n"), 1.0);
+        assert_eq!(line_similarity("a\nb\n\nc", "a\nb\nc"), 1.0);
+        assert_eq!(line_similarity("a\nb", "a\nc"), 0.5);
+        assert_eq!(line_similarity("a", ""), 0.0);
+    }
+
+    #[test]
+    fn test_turn_scores_roll_up_into_summary() {
+        let golden = "# Plan\n\n## Risks\n- churn\n";
+        let edited = score_turn("# Plan\n", "# Plan\n\n## Scope\n- churn\n", Some("# Plan\n"), golden);
+        assert!(!edited.exact);
+        assert_eq!(edited.heading_coverage, 0.5);
+        assert_eq!(edited.missing_headings, vec!["## Risks"]);
+        assert_eq!(edited.extra_headings, vec!["## Scope"]);
+        assert_eq!((edited.expected_noop, edited.was_noop), (Some(false), false));
+
+        // Golden unchanged but the turn edited anyway: a missed NO-OP
+        let noop = score_turn(golden, "# Plan\n", Some(golden), golden);
+        assert_eq!((noop.expected_noop, noop.was_noop), (Some(true), false));
+
+        let turn = |score: Option<TurnScore>, applied, failed| TurnResult {
+            transcript: String::new(),
+            document: String::new(),
+            error: None,
+            patches_applied: applied,
+            patches_failed: failed,
+            unrecorded_requests: 0,
+            score,
+        };
+        let report = EvalReport::new("replay", vec![CaseReport::new("plan".into(), vec![
+            turn(Some(edited), 3, 1),
+            turn(Some(noop), 0, 0),
+            turn(None, 0, 0),
+        ])]);
+        let s = &report.overall;
+        assert_eq!((s.turns, s.scored_turns, s.exact_turns), (3, 2, 0));
+        assert_eq!(s.patch_failure_rate, Some(0.25));
+        assert_eq!(s.noop_accuracy, Some(0.5));
+        assert_eq!(s.mean_heading_coverage, Some(0.5));
+
+        let md = report.to_markdown();
+        assert!(md.contains("| plan | 3 | 0 | 0/2 |"));
+        assert!(md.contains("- Turn 2: changed the document, expected NO-OP"));
+    }
+}
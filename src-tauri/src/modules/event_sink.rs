@@ -0,0 +1,87 @@
+// Event Sink
+//
+// Where the pipeline reports progress (document updates, toasts, todo, ASR
+// and turn events), as typed `CreekEvent`s. The desktop app forwards them to
+// the webview through `AppHandle::emit`; the headless CLI logs them; a
+// `ChannelSink` hands them to anything else (tests asserting on the exact
+// sequence, a non-Tauri frontend). Processing code only sees `dyn EventSink`,
+// so it runs the same with or without Tauri.
+
+use log::{debug, info, warn};
+use tokio::sync::mpsc;
+
+use crate::models::event::CreekEvent;
+
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: CreekEvent);
+}
+
+impl<T: EventSink + ?Sized> EventSink for std::sync::Arc<T> {
+    fn emit(&self, event: CreekEvent) {
+        (**self).emit(event)
+    }
+}
+
+impl EventSink for tauri::AppHandle {
+    fn emit(&self, event: CreekEvent) {
+        if let Err(e) = tauri::Emitter::emit(self, event.name(), event.payload()) {
+            warn!("[Events] Failed to emit '{}': {:?}", event.name(), e);
+        }
+    }
+}
+
+/// Forwards events into a channel, in order
+pub struct ChannelSink(mpsc::UnboundedSender<CreekEvent>);
+
+impl ChannelSink {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<CreekEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self(tx), rx)
+    }
+}
+
+impl EventSink for ChannelSink {
+    fn emit(&self, event: CreekEvent) {
+        // Nobody listening any more is not an error for the emitter
+        let _ = self.0.send(event);
+    }
+}
+
+/// Logs events instead of delivering them: toasts at info/warn, the rest at debug
+pub struct LogSink;
+
+impl EventSink for LogSink {
+    fn emit(&self, event: CreekEvent) {
+        match &event {
+            CreekEvent::Toast(toast) if matches!(toast.toast_type.as_str(), "error" | "warning") => {
+                warn!("[{}] {}", toast.toast_type, toast.message);
+            }
+            CreekEvent::Toast(toast) => info!("[{}] {}", toast.toast_type, toast.message),
+            _ => debug!("[Event] {} {}", event.name(), event.payload()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::event::{RecordingRenamed, ToastPayload};
+
+    #[test]
+    fn test_channel_sink_keeps_order_and_wire_format() {
+        let (sink, mut rx) = ChannelSink::new();
+        let sink: std::sync::Arc<dyn EventSink> = std::sync::Arc::new(sink);
+        sink.emit(CreekEvent::Toast(ToastPayload::error("boom")));
+        sink.emit(CreekEvent::RecordingRenamed(RecordingRenamed { id: "r1".into(), new_name: "Roadmap".into() }));
+        sink.emit(CreekEvent::RecordingsUpdated);
+
+        let events: Vec<CreekEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let names: Vec<&str> = events.iter().map(CreekEvent::name).collect();
+        assert_eq!(names, vec!["show-toast", "recording-renamed", "recordings-updated"]);
+
+        // Payloads are what the webview has always received
+        assert_eq!(events[0].payload(), serde_json::json!({ "message": "boom", "type": "error", "duration": 5000 }));
+        assert_eq!(events[1].payload(), serde_json::json!({ "id": "r1", "new_name": "Roadmap" }));
+        assert_eq!(events[2].payload(), serde_json::Value::Null);
+    }
+}
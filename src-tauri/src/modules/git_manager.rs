@@ -11,7 +11,7 @@ use anyhow::{Result, Context};
 use git2::{Repository, Signature, IndexAddOption, Oid};
 use std::path::Path;
 use std::fs;
-use crate::services::llm_client::{LLMClient, ChatMessage};
+use crate::services::llm_client::{LLMClient, LLMError, ChatMessage};
 use crate::prompts::state_manager::generate_commit_message_prompt;
 use crate::utils::text::clean_concise_output;
 use futures_util::StreamExt;
@@ -27,6 +27,21 @@ fn document_filename(recording_path: &Path) -> String {
         .unwrap_or_else(|| DOCUMENT_FILENAME.to_string())
 }
 
+/// Archived session audio lives next to the document but is not versioned
+const UNVERSIONED_DIRS: &[&str] = &["audio"];
+
+/// Stage everything in the recording except unversioned directories
+fn stage_all(index: &mut git2::Index) -> Result<()> {
+    let mut skip_unversioned = |path: &Path, _: &[u8]| -> i32 {
+        let skip = path.components().next()
+            .and_then(|c| c.as_os_str().to_str())
+            .is_some_and(|first| UNVERSIONED_DIRS.contains(&first));
+        if skip { 1 } else { 0 }
+    };
+    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, Some(&mut skip_unversioned))?;
+    Ok(())
+}
+
 /// GitManager handles git operations for document version control
 pub struct GitManager;
 
@@ -68,7 +83,7 @@ impl GitManager {
     }
     
     /// Generate commit message using LLM
-    pub async fn generate_commit_message<T: LLMClient>(
+    pub async fn generate_commit_message<T: LLMClient + ?Sized>(
         &self,
         llm: &T,
         diff: &str,
@@ -88,12 +103,9 @@ impl GitManager {
             }
         ];
         
-        // Call LLM with timeout and fallback
-        let commit_msg = match tokio::time::timeout(
-            std::time::Duration::from_secs(10),
-            self.collect_llm_response(llm, messages)
-        ).await {
-            Ok(Ok(response)) => {
+        // Call LLM with fallback (deadline comes from the client's role settings)
+        let commit_msg = match self.collect_llm_response(llm, messages).await {
+            Ok(response) => {
                 let cleaned = clean_concise_output(&response);
                 // Validate: must be reasonable length
                 if cleaned.is_empty() || cleaned.len() > 150 {
@@ -102,6 +114,8 @@ impl GitManager {
                     cleaned
                 }
             }
+            // A replay miss must surface, not turn into a plausible message
+            Err(e) if matches!(e.downcast_ref::<LLMError>(), Some(LLMError::NotRecorded(_))) => return Err(e),
             _ => "Document updated".to_string(), // Fallback
         };
         
@@ -111,19 +125,19 @@ impl GitManager {
     }
     
     /// Helper to collect full LLM response from stream
-    async fn collect_llm_response<T: LLMClient>(
+    async fn collect_llm_response<T: LLMClient + ?Sized>(
         &self,
         llm: &T,
         messages: Vec<ChatMessage>,
     ) -> Result<String> {
         let mut stream = llm.stream_completion(messages).await
-            .map_err(|e| anyhow::anyhow!("LLM error: {:?}", e))?;
+            .map_err(|e| anyhow::Error::new(e).context("LLM error"))?;
         
         let mut response = String::new();
         while let Some(chunk_result) = stream.next().await {
             match chunk_result {
                 Ok(chunk) => response.push_str(&chunk),
-                Err(e) => return Err(anyhow::anyhow!("Stream error: {:?}", e)),
+                Err(e) => return Err(anyhow::Error::new(e).context("Stream error")),
             }
         }
         
@@ -179,7 +193,7 @@ impl GitManager {
         let mut index = repo.index()?;
         
         // Add all files
-        index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+        stage_all(&mut index)?;
         index.write()?;
         
         let tree_id = index.write_tree()?;
@@ -259,7 +273,7 @@ impl GitManager {
         
         // Get working directory tree (current state)
         let mut index = repo.index()?;
-        index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+        stage_all(&mut index)?;
         let index_tree_id = index.write_tree()?;
         let index_tree = repo.find_tree(index_tree_id)?;
         
@@ -314,3 +328,31 @@ impl Default for GitManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::services::llm_cassette::testing::{reload, ScriptedClient};
+    use crate::services::llm_cassette::{Cassette, CassetteClient};
+
+    #[tokio::test]
+    async fn test_commit_message_from_cassette() {
+        let git = GitManager::new();
+        let diff = "+## Risks\n+- Churn after the trial\n";
+
+        let cassette = Arc::new(Cassette::new());
+        let provider = Arc::new(ScriptedClient::text("```\nAdd risks section\n```"));
+        let recorder = CassetteClient::record("commit_message", provider, cassette.clone());
+        assert_eq!(git.generate_commit_message(&recorder, diff).await.unwrap(), "Add risks section");
+
+        let cassette = reload(&cassette);
+        let llm = CassetteClient::replay("commit_message", cassette.clone());
+        assert_eq!(git.generate_commit_message(&llm, diff).await.unwrap(), "Add risks section");
+        assert!(cassette.unmatched().is_empty());
+
+        // A diff the cassette never saw fails the call, and the miss is reported
+        assert!(git.generate_commit_message(&llm, "+- Pricing\n").await.is_err());
+        assert_eq!(cassette.unmatched().len(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+// Intent Rules
+//
+// Deterministic fast path in front of the LLM routers. Short, unambiguous
+// utterances ("undo", "撤销", "clear everything", "change all X to Y", pure
+// filler) are matched against per-language regex tables and turned into a
+// plan directly, with a confidence score. The LLM routers only run when no
+// rule fires at or above `min_confidence`.
+//
+// Patterns are anchored to the whole utterance (trailing punctuation
+// stripped), so "undo" matches but "undo the bold in the second paragraph"
+// goes to the LLM. Destructive rules also need an explicit object: "clear
+// the document" is a command, but bare "clear", "start over" or "go back" are
+// as likely to be dictation and are left to the LLM to confirm. Extra rules
+// come from `[[router.rules.custom]]`.
+
+use log::warn;
+use regex::Regex;
+
+use super::{DocIntent, PlanStep};
+use crate::services::llm_provider::RouterRules;
+
+/// (name, intent, confidence, pattern, instruction template)
+type RuleRow = (&'static str, &'static str, f32, &'static str, &'static str);
+
+const EN_RULES: &[RuleRow] = &[
+    (
+        "filler.en",
+        "NO-OP",
+        0.95,
+        r"(?i)^(?:(?:u+m+|u+h+|a+h+|e+r+m?|h+m+|m+h*m+|uh-huh)[\s,.…]*)+$",
+        "Ignore filler",
+    ),
+    (
+        "undo.en",
+        "UNDO",
+        0.95,
+        r"(?i)^(?:please\s+)?(?:(?:undo|revert)(?:\s+(?:that|it|this|the last (?:change|edit|step)))?|scratch that|take that back|go back (?:a|one) step|go back to the (?:last|previous) version)(?:,?\s*please)?$",
+        "Undo the last change",
+    ),
+    (
+        "clear.en",
+        "CLEAR",
+        0.95,
+        r"(?i)^(?:please\s+)?(?:(?:clear|delete|erase|wipe|remove)\s+(?:everything|all|it all|the (?:whole |entire )?document)|start the document over|reset the document)(?:,?\s*please)?$",
+        "Clear the document",
+    ),
+    (
+        "replace_all.en",
+        "GREP",
+        0.9,
+        r#"(?i)^(?:please\s+)?(?:change|replace|rename|switch)\s+(?:all|every)\s+(?:(?:the\s+)?(?:occurrences|instances|mentions)\s+of\s+)?["'“‘]?(?P<from>.+?)["'”’]?\s+(?:to|with|into)\s+["'“‘]?(?P<to>.+?)["'”’]?$"#,
+        r#"Change all "${from}" to "${to}""#,
+    ),
+];
+
+const ZH_RULES: &[RuleRow] = &[
+    (
+        "filler.zh",
+        "NO-OP",
+        0.95,
+        r"^(?:(?:嗯+|啊+|呃+|额+|哦+|那个|这个|就是)[\s，,。…]*)+$",
+        "Ignore filler",
+    ),
+    (
+        "undo.zh",
+        "UNDO",
+        0.95,
+        r"^(?:请|帮我)?(?:(?:撤销|撤消|撤回|回退)(?:一下|上一步|刚才的(?:修改|操作))?|(?:恢复|回到)上一(?:步|个版本))吧?$",
+        "Undo the last change",
+    ),
+    (
+        "clear.zh",
+        "CLEAR",
+        0.95,
+        r"^(?:请|帮我)?(?:清空(?:文档|全部|所有内容)|全部删除|删除全部|全部删掉|删掉所有内容)吧?$",
+        "Clear the document",
+    ),
+    (
+        "replace_all.zh",
+        "GREP",
+        0.9,
+        r#"^(?:请|帮我)?(?:把|将)?(?:文中)?(?:所有的?|全部的?)[“"‘']?(?P<from>.+?)[”"’']?(?:都|全部|统一)?(?:改成|改为|替换成|替换为|换成)[“"‘']?(?P<to>.+?)[”"’']?$"#,
+        r#"Change all "${from}" to "${to}""#,
+    ),
+    (
+        "replace_all_ba.zh",
+        "GREP",
+        0.9,
+        r#"^(?:请|帮我)?(?:把|将)[“"‘']?(?P<from>.+?)[”"’']?(?:全部|都|统一)(?:改成|改为|替换成|替换为|换成)[“"‘']?(?P<to>.+?)[”"’']?$"#,
+        r#"Change all "${from}" to "${to}""#,
+    ),
+];
+
+struct Rule {
+    name: String,
+    /// Canonical intent label, as the LLM router would emit it
+    intent: String,
+    pattern: Regex,
+    confidence: f32,
+    instruction: String,
+}
+
+/// A rule that fired for an utterance
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    pub rule: String,
+    pub confidence: f32,
+    pub plan: Vec<PlanStep>,
+}
+
+pub struct RuleEngine {
+    rules: Vec<Rule>,
+    enabled: bool,
+    min_confidence: f32,
+}
+
+impl RuleEngine {
+    /// Load the built-in tables for the configured languages plus custom rules.
+    /// Custom rules with an unknown intent are skipped (patterns are validated
+    /// when the config is parsed).
+    pub fn from_config(config: &RouterRules) -> Self {
+        let mut rules = Vec::new();
+        for lang in &config.languages {
+            let table = match lang.as_str() {
+                "en" => EN_RULES,
+                "zh" => ZH_RULES,
+                other => {
+                    warn!("[Intent Rules] No built-in rules for language '{}'", other);
+                    continue;
+                }
+            };
+            for (name, intent, confidence, pattern, instruction) in table {
+                rules.push(Rule {
+                    name: name.to_string(),
+                    intent: intent.to_string(),
+                    pattern: Regex::new(pattern).expect("built-in rule pattern must compile"),
+                    confidence: *confidence,
+                    instruction: instruction.to_string(),
+                });
+            }
+        }
+
+        for (i, spec) in config.custom.iter().enumerate() {
+            let (Some(_), Ok(pattern)) = (DocIntent::from_str(&spec.intent), Regex::new(&spec.pattern)) else {
+                warn!("[Intent Rules] Skipping custom rule '{}' ({})", spec.pattern, spec.intent);
+                continue;
+            };
+            rules.push(Rule {
+                name: format!("custom.{}", i),
+                intent: spec.intent.trim().to_uppercase(),
+                pattern,
+                confidence: spec.confidence,
+                instruction: spec.instruction.clone()
+                    .unwrap_or_else(|| "Execute user request based on transcript".to_string()),
+            });
+        }
+
+        Self {
+            rules,
+            enabled: config.enabled,
+            min_confidence: config.min_confidence,
+        }
+    }
+
+    /// The most confident rule matching the utterance, if any.
+    /// Ties go to the rule listed first.
+    pub fn classify(&self, utterance: &str) -> Option<RuleMatch> {
+        let utterance = utterance
+            .trim()
+            .trim_end_matches(['.', '!', '?', '。', '！', '？', '，', ','])
+            .trim();
+        if utterance.is_empty() {
+            return None;
+        }
+
+        self.rules.iter()
+            .filter_map(|rule| {
+                let caps = rule.pattern.captures(utterance)?;
+                let mut instruction = String::new();
+                caps.expand(&rule.instruction, &mut instruction);
+                Some(RuleMatch {
+                    rule: rule.name.clone(),
+                    confidence: rule.confidence,
+                    plan: vec![PlanStep {
+                        intent: rule.intent.clone(),
+                        instruction,
+                    }],
+                })
+            })
+            .reduce(|best, hit| if hit.confidence > best.confidence { hit } else { best })
+    }
+
+    /// A match confident enough to skip the LLM routers
+    pub fn fast_path(&self, utterance: &str) -> Option<RuleMatch> {
+        if !self.enabled {
+            return None;
+        }
+        self.classify(utterance)
+            .filter(|hit| hit.confidence >= self.min_confidence)
+    }
+}
+
+impl Default for RuleEngine {
+    fn default() -> Self {
+        Self::from_config(&RouterRules::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_provider::RuleSpec;
+
+    fn intent_of(engine: &RuleEngine, utterance: &str) -> Option<DocIntent> {
+        engine.fast_path(utterance)
+            .and_then(|hit| hit.plan[0].to_doc_intent())
+    }
+
+    #[test]
+    fn test_commands_per_language() {
+        let engine = RuleEngine::default();
+        assert_eq!(intent_of(&engine, "Undo."), Some(DocIntent::Undo));
+        assert_eq!(intent_of(&engine, "scratch that"), Some(DocIntent::Undo));
+        assert_eq!(intent_of(&engine, "撤销"), Some(DocIntent::Undo));
+        assert_eq!(intent_of(&engine, "帮我撤销一下。"), Some(DocIntent::Undo));
+        assert_eq!(intent_of(&engine, "go back one step"), Some(DocIntent::Undo));
+        assert_eq!(intent_of(&engine, "Clear everything!"), Some(DocIntent::Clear));
+        assert_eq!(intent_of(&engine, "clear the document, please"), Some(DocIntent::Clear));
+        assert_eq!(intent_of(&engine, "清空文档"), Some(DocIntent::Clear));
+        assert_eq!(intent_of(&engine, "um, uh..."), Some(DocIntent::NoOp));
+        assert_eq!(intent_of(&engine, "嗯，那个"), Some(DocIntent::NoOp));
+    }
+
+    #[test]
+    fn test_replace_all_extracts_terms() {
+        let engine = RuleEngine::default();
+        let hit = engine.fast_path("Change all \"Creek\" to \"River\".").unwrap();
+        assert_eq!(hit.plan[0].intent, "GREP");
+        assert_eq!(hit.plan[0].instruction, "Change all \"Creek\" to \"River\"");
+
+        let hit = engine.fast_path("把所有的苹果都改成香蕉").unwrap();
+        assert_eq!(hit.plan[0].instruction, "Change all \"苹果\" to \"香蕉\"");
+        let hit = engine.fast_path("把张三全部替换成李四").unwrap();
+        assert_eq!(hit.plan[0].instruction, "Change all \"张三\" to \"李四\"");
+    }
+
+    #[test]
+    fn test_substantive_input_goes_to_llm() {
+        let engine = RuleEngine::default();
+        assert!(engine.fast_path("undo the bold formatting in the second paragraph").is_none());
+        assert!(engine.fast_path("Let's talk about the clear skies over the bay").is_none());
+        assert!(engine.fast_path("嗯，我们下周三开会讨论预算").is_none());
+
+        // Destructive intents without an object could be dictation
+        for utterance in ["clear", "Start over.", "go back", "let's go back to the intro", "重新开始", "清空"] {
+            assert!(engine.fast_path(utterance).is_none(), "{}", utterance);
+        }
+        assert!(engine.fast_path("").is_none());
+    }
+
+    #[test]
+    fn test_custom_rules_and_threshold() {
+        let config = RouterRules {
+            languages: vec![],
+            custom: vec![
+                RuleSpec {
+                    intent: "undo".to_string(),
+                    pattern: "(?i)^nope$".to_string(),
+                    confidence: 0.99,
+                    instruction: None,
+                },
+                RuleSpec {
+                    intent: "EDIT".to_string(),
+                    pattern: "(?i)^fix (?P<what>.+)$".to_string(),
+                    confidence: 0.5,
+                    instruction: Some("Fix ${what}".to_string()),
+                },
+            ],
+            ..Default::default()
+        };
+        let engine = RuleEngine::from_config(&config);
+        assert_eq!(intent_of(&engine, "Nope"), Some(DocIntent::Undo));
+        assert!(engine.fast_path("undo").is_none());
+
+        // Below min_confidence: visible to classify, but not a fast path
+        assert!(engine.fast_path("fix the typo").is_none());
+        assert_eq!(engine.classify("fix the typo").unwrap().plan[0].instruction, "Fix the typo");
+    }
+}
@@ -11,11 +11,20 @@ pub mod tool_extension;
 pub mod todo_agent;
 pub mod workspace_manager;
 pub mod agents;
+pub mod usage_tracker;
+pub mod turn_trace;
+pub mod socratic;
+pub mod event_sink;
+pub mod eval;
 
     // Re-exports
     pub use state_manager::{StateManager, DocumentState, TodoItem};
     pub use git_manager::GitManager;
     pub use todo_agent::{TodoAgent, TodoOperation};
     pub use rag::{RagService, QueryAgent, ConversationTurn, SearchResult};
-    pub use intent_router::{IntentRouter, DocIntent, ToolIntent};
+    pub use intent_router::{IntentRouter, DocIntent, ToolIntent, RouterBenchmark};
     pub use workspace_manager::{WorkspaceManager, Workspace, WorkspaceConfig};
+    pub use usage_tracker::{UsageTracker, UsageScope, UsageCaller, UsageSummary};
+    pub use turn_trace::{TraceRecorder, TraceStore, TurnTrace, PatchOutcome};
+    pub use socratic::{QuestionStore, SocraticQuestion, QuestionKind, QuestionStatus};
+    pub use event_sink::{EventSink, ChannelSink, LogSink};
@@ -1,11 +1,11 @@
 use std::sync::Arc;
 use log::info;
-use crate::services::llm_client::{OpenAILikeClient, LLMClient};
+use crate::services::llm_client::LLMClient;
 use crate::prompts::auto_naming::{AUTO_NAME_SYSTEM_PROMPT, AUTO_NAME_USER_TEMPLATE};
 
 pub async fn generate_recording_name(
     content: &str,
-    llm: &Arc<OpenAILikeClient>,
+    llm: &Arc<dyn LLMClient>,
 ) -> Result<String, String> {
     info!("[Auto-Naming] Generating name based on content...");
     
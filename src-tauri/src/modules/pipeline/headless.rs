@@ -0,0 +1,225 @@
+// Headless Session
+//
+// Runs transcript turns through routing and the agents without the desktop
+// app. The recording is laid out exactly as the app does it, under
+// `<workspace>/recordings/<id>/` (markdown, metadata.json, git history), so
+// the result can be opened in the app afterwards. State, usage and the RAG
+// store live in `<workspace>/.creek/`. Events go to an `EventSink` (the CLI
+// logs them). Used by the `creek-cli` binary.
+//
+// Transcripts are either plain text, one turn per line, or JSONL with an
+// optional timestamp per turn (turns run in timestamp order):
+//   {"text": "Add a heading called Risks", "at_ms": 12500}
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::commands::recording_commands::{get_recording_metadata, save_recording_metadata};
+use crate::modules::document_service::{DocumentService, PatchStats};
+use crate::modules::{EventSink, GitManager, IntentRouter, QuestionStore, RagService, StateManager, TodoAgent, TraceStore, UsageTracker};
+use crate::modules::agents::socratic_agent::SocraticAgent;
+use crate::services::llm_client::ChatMessage;
+use crate::services::llm_provider::{LLMConfig, LLMRegistry, ModelRole};
+use super::transcript_processor::process_transcript;
+
+/// Workspace-local data directory (state db, RAG store)
+const DATA_DIR: &str = ".creek";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TranscriptTurn {
+    pub text: String,
+    /// Milliseconds since the session started
+    #[serde(default)]
+    pub at_ms: Option<u64>,
+}
+
+/// Parse a transcript file: JSONL if every line is a JSON object, else one turn per line
+pub fn parse_transcript(content: &str) -> Result<Vec<TranscriptTurn>> {
+    let lines: Vec<(usize, &str)> = content.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    if !lines.is_empty() && lines.iter().all(|(_, line)| line.starts_with('{')) {
+        let mut turns = lines.iter()
+            .map(|(n, line)| serde_json::from_str::<TranscriptTurn>(line)
+                .with_context(|| format!("Invalid transcript line {}", n)))
+            .collect::<Result<Vec<_>>>()?;
+        turns.retain(|turn| !turn.text.trim().is_empty());
+        // Stable: turns without a timestamp keep their place among equals
+        turns.sort_by_key(|turn| turn.at_ms.unwrap_or(0));
+        return Ok(turns);
+    }
+
+    Ok(lines.into_iter()
+        .map(|(_, line)| TranscriptTurn { text: line.to_string(), at_ms: None })
+        .collect())
+}
+
+pub struct HeadlessSession {
+    recording_id: String,
+    recording_path: PathBuf,
+    doc_service: Arc<DocumentService>,
+    llms: Arc<LLMRegistry>,
+    events: Arc<dyn EventSink>,
+    chat_history: Arc<tokio::sync::RwLock<Vec<ChatMessage>>>,
+    state_manager: Arc<StateManager>,
+    git_manager: Arc<GitManager>,
+    todo_agent: Arc<TodoAgent>,
+    socratic_agent: Arc<SocraticAgent>,
+    rag_service: Arc<RagService>,
+    intent_router: Arc<IntentRouter>,
+    usage_tracker: Arc<UsageTracker>,
+    trace_store: Arc<TraceStore>,
+}
+
+impl HeadlessSession {
+    /// Open (or create) a recording in `workspace`, picking up any existing document
+    pub async fn open(
+        workspace: &Path,
+        recording_id: &str,
+        config: LLMConfig,
+        api_key: &str,
+        events: Arc<dyn EventSink>,
+    ) -> Result<Self> {
+        let llms = Arc::new(LLMRegistry::from_config(&config, api_key)?);
+        Self::open_with_llms(workspace, recording_id, config, llms, events).await
+    }
+
+    /// `open` with ready-made clients (recorded responses for evaluation runs)
+    pub async fn open_with_llms(
+        workspace: &Path,
+        recording_id: &str,
+        config: LLMConfig,
+        llms: Arc<LLMRegistry>,
+        events: Arc<dyn EventSink>,
+    ) -> Result<Self> {
+        let data_dir = workspace.join(DATA_DIR);
+        let recording_path = workspace.join("recordings").join(recording_id);
+        std::fs::create_dir_all(&data_dir).context("Failed to create workspace data directory")?;
+        std::fs::create_dir_all(&recording_path).context("Failed to create recording directory")?;
+
+        let db_path = data_dir.join("state.db");
+        let state_manager = Arc::new(StateManager::new(&db_path.to_string_lossy())?);
+        let usage_tracker = Arc::new(UsageTracker::new(&db_path.to_string_lossy())?);
+        usage_tracker.configure(config.pricing.clone(), config.budget.clone());
+        let trace_store = Arc::new(TraceStore::new(&db_path.to_string_lossy())?);
+        let question_store = Arc::new(QuestionStore::new(&db_path.to_string_lossy())?);
+
+        let intent_router = Arc::new(
+            IntentRouter::new(llms.get(ModelRole::Router)).with_config(config.router.clone())
+        );
+        let rag_service = Arc::new(RagService::new(data_dir.join("rag_db.lance")).await?);
+        rag_service.init_recording(recording_id).await?;
+
+        let git_manager = Arc::new(GitManager::new());
+        git_manager.init_repo(&recording_path)?;
+        if get_recording_metadata(&recording_path).is_none() {
+            // Named after its id, so auto-naming picks it up like a new app recording
+            save_recording_metadata(&recording_path, recording_id)?;
+        }
+
+        state_manager.set_current_recording(recording_id.to_string())?;
+        let doc_path = recording_path.join(format!("{}.md", recording_id));
+        let content = std::fs::read_to_string(&doc_path).unwrap_or_default();
+        if !content.is_empty() {
+            info!("[Headless] Continuing {} ({} chars)", recording_id, content.len());
+        }
+        state_manager.update_document(content.clone());
+
+        Ok(Self {
+            recording_id: recording_id.to_string(),
+            recording_path,
+            doc_service: Arc::new(DocumentService::new(content)),
+            llms,
+            events,
+            chat_history: Arc::new(tokio::sync::RwLock::new(Vec::new())),
+            state_manager,
+            git_manager,
+            todo_agent: Arc::new(TodoAgent::new()),
+            socratic_agent: Arc::new(SocraticAgent::new(question_store).with_config(config.socratic)),
+            rag_service,
+            intent_router,
+            usage_tracker,
+            trace_store,
+        })
+    }
+
+    /// Run one turn. A failed turn is rolled back whole, as in the app.
+    pub async fn run_turn(&self, text: &str) -> Result<()> {
+        let before = self.doc_service.get_snapshot().content;
+        let result = process_transcript(
+            &uuid::Uuid::new_v4().to_string(),
+            text.to_string(),
+            &self.doc_service,
+            &self.llms,
+            &self.events,
+            &self.chat_history,
+            &self.state_manager,
+            &self.git_manager,
+            &self.todo_agent,
+            &self.socratic_agent,
+            &self.rag_service,
+            &self.intent_router,
+            &self.usage_tracker,
+            &self.trace_store,
+            Some(&self.recording_id),
+            Some(&self.recording_path),
+            None,
+        ).await;
+
+        if let Err(e) = &result {
+            warn!("[Headless] Turn failed, rolling back: {:#}", e);
+            self.doc_service.reset(before.clone());
+            self.state_manager.update_document(before);
+        }
+        result
+    }
+
+    pub fn document(&self) -> String {
+        self.doc_service.get_snapshot().content
+    }
+
+    pub fn patch_stats(&self) -> PatchStats {
+        self.doc_service.patch_stats()
+    }
+
+    /// Write the final document and persist state. Returns the document path.
+    pub fn finish(&self) -> Result<PathBuf> {
+        let doc_path = self.recording_path.join(format!("{}.md", self.recording_id));
+        std::fs::write(&doc_path, self.document()).context("Failed to save final document")?;
+        self.state_manager.persist_state()?;
+        Ok(doc_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_transcript_is_one_turn_per_line() {
+        let turns = parse_transcript("Add a heading called Risks\n\n  List three risks  \n").unwrap();
+        assert_eq!(turns, vec![
+            TranscriptTurn { text: "Add a heading called Risks".into(), at_ms: None },
+            TranscriptTurn { text: "List three risks".into(), at_ms: None },
+        ]);
+    }
+
+    #[test]
+    fn test_jsonl_transcript_runs_in_timestamp_order() {
+        let content = r#"{"text": "second", "at_ms": 2000}
+{"text": "first", "at_ms": 500}
+{"text": "   ", "at_ms": 700}
+"#;
+        let texts: Vec<String> = parse_transcript(content).unwrap().into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["first", "second"]);
+
+        let err = parse_transcript("{\"text\": \"ok\"}\n{\"txt\": 1}\n").unwrap_err();
+        assert!(format!("{:#}", err).contains("line 2"));
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Manager};
 use tokio_util::sync::CancellationToken;
 use tokio::time::{Duration, Instant};
 
@@ -9,18 +9,27 @@ pub mod utils;
 pub mod state_updater;
 pub mod transcript_processor;
 pub mod auto_naming;
+pub mod turn_queue;
+pub mod headless;
 
-pub use types::{PipelineCommand, SpeechAggregator, FLUSH_TIMEOUT_MS};
-use crate::utils::paths::{get_app_data_dir, get_state_db_path};
-use self::utils::{emit_error_toast, emit_warning_toast, emit_success_toast, emit_update};
+pub use types::{PipelineCommand, SpeechAggregator, CONFIG_POLL_SECS};
+use crate::utils::paths::{get_app_data_dir, get_state_db_path, get_config_path};
+use self::utils::{emit_error_toast, emit_warning_toast, emit_success_toast, emit_update, emit_and_save};
 use transcript_processor::process_transcript;
+use turn_queue::{QueuedTurn, Rollback, TurnOutcome, TurnQueue};
 use log::{info, error, warn};
 
-use crate::models::event::DocumentUpdate;
+use crate::models::event::{CreekEvent, DocumentUpdate, RecordingStarted, SpeechActivity, TranscriptUpdate};
 use crate::modules::document_service::DocumentService;
-use crate::modules::{StateManager, GitManager, TodoAgent, RagService, IntentRouter, WorkspaceManager};
+use crate::modules::{StateManager, GitManager, TodoAgent, RagService, IntentRouter, RouterBenchmark, WorkspaceManager, UsageTracker, TraceStore, QuestionStore, UsageScope, UsageCaller, EventSink};
+use crate::modules::agents::socratic_agent::SocraticAgent;
+use crate::modules::usage_tracker::{recording_ids_in, BudgetStatus};
+use crate::modules::workspace_manager::WorkspaceSettings;
+use crate::services::asr::{archive, hotwords, ArchiveTarget, AudioSpan, AsrSessionSettings, AsrStatus, AudioLevel, SessionControl, Transcript, TurnSpans, VadEvent};
 use crate::services::asr_service::AsrService;
-use crate::services::llm_client::OpenAILikeClient;
+use crate::services::llm_client::ChatMessage;
+use crate::services::llm_provider::{ConfigWatcher, LLMConfig, LLMRegistry, ModelRole};
+use crate::services::telemetry::Telemetry;
 
 /// Helper to get the recordings directory for the CURRENT workspace.
 /// If no workspace is active or error occurs, falls back to global (legacy) or empty path,
@@ -34,6 +43,182 @@ async fn get_current_workspace_recordings_dir(app_handle: &AppHandle) -> Option<
     }
 }
 
+/// Settings of the CURRENT workspace (defaults if none is active)
+async fn get_current_workspace_settings(app_handle: &AppHandle) -> WorkspaceSettings {
+    let workspace_manager = app_handle.state::<Arc<tokio::sync::RwLock<WorkspaceManager>>>();
+    let manager = workspace_manager.read().await;
+    match manager.get_current_workspace() {
+        Ok(Some(workspace)) => workspace.settings,
+        _ => WorkspaceSettings::default(),
+    }
+}
+
+/// ASR session settings of the CURRENT workspace, with hotwords seeded from the document
+async fn current_asr_settings(app_handle: &AppHandle, document: &str) -> AsrSessionSettings {
+    let mut settings = get_current_workspace_settings(app_handle).await.asr;
+    if settings.seed_hotwords {
+        settings.hotwords = hotwords::merge(&settings.hotwords, &hotwords::seed_from_document(document));
+    }
+    settings
+}
+
+/// Enforce token/cost budgets before a turn runs. Returns false if the turn must be skipped.
+async fn check_usage_budget(app_handle: &AppHandle, usage_tracker: &UsageTracker, recording_id: Option<&String>) -> bool {
+    let Some(rec_id) = recording_id else {
+        return true;
+    };
+    let workspace_recordings = get_current_workspace_recordings_dir(app_handle).await
+        .map(|dir| recording_ids_in(&dir))
+        .unwrap_or_default();
+
+    match usage_tracker.check_budget(rec_id, &workspace_recordings) {
+        Ok(BudgetStatus::Ok) => true,
+        Ok(BudgetStatus::Warn(msg)) => {
+            warn!("[Usage] {}", msg);
+            emit_warning_toast(app_handle, &msg);
+            true
+        }
+        Ok(BudgetStatus::Block(msg)) => {
+            warn!("[Usage] {} - turn skipped", msg);
+            emit_error_toast(app_handle, format!("{} - turn skipped", msg));
+            false
+        }
+        Err(e) => {
+            warn!("[Usage] Budget check failed: {:?}", e);
+            true
+        }
+    }
+}
+
+/// Services a turn task runs against; rebuilt clients only reach turns started after a reload
+#[derive(Clone)]
+struct TurnServices {
+    doc_service: Arc<DocumentService>,
+    llms: Arc<LLMRegistry>,
+    app_handle: AppHandle,
+    events: Arc<dyn EventSink>,
+    chat_history: Arc<tokio::sync::RwLock<Vec<ChatMessage>>>,
+    state_manager: Arc<StateManager>,
+    git_manager: Arc<GitManager>,
+    todo_agent: Arc<TodoAgent>,
+    socratic_agent: Arc<SocraticAgent>,
+    rag_service: Arc<RagService>,
+    intent_router: Arc<IntentRouter>,
+    usage_tracker: Arc<UsageTracker>,
+    trace_store: Arc<TraceStore>,
+}
+
+type TurnDone = mpsc::UnboundedSender<(String, TurnOutcome)>;
+
+/// Start the next queued turn if none is running. Aborted turns never report back.
+/// The queue keeps the task, so aborting the turn can wait for it to stop.
+fn start_next_turn(queue: &mut TurnQueue, services: &TurnServices, done: &TurnDone) {
+    let Some((turn, cancel, event)) = queue.start_next(|| services.doc_service.get_snapshot().content) else {
+        return;
+    };
+    info!("[Turn {}] Started: {} chars", turn.id, turn.text.len());
+    services.events.emit(event);
+
+    let id = turn.id.clone();
+    let services = services.clone();
+    let done = done.clone();
+    let task = tokio::spawn(async move {
+        let outcome = tokio::select! {
+            _ = cancel.cancelled() => {
+                info!("[Processing Aborted] Turn {} cancelled", turn.id);
+                return;
+            }
+            result = process_transcript(
+                &turn.id,
+                turn.text,
+                &services.doc_service,
+                &services.llms,
+                &services.events,
+                &services.chat_history,
+                &services.state_manager,
+                &services.git_manager,
+                &services.todo_agent,
+                &services.socratic_agent,
+                &services.rag_service,
+                &services.intent_router,
+                &services.usage_tracker,
+                &services.trace_store,
+                turn.recording_id.as_ref(),
+                turn.recording_path.as_deref(),
+                turn.audio,
+            ) => match result {
+                Ok(()) => TurnOutcome::Completed,
+                Err(e) => TurnOutcome::Failed(format!("{:#}", e)),
+            }
+        };
+        let _ = done.send((turn.id, outcome));
+    });
+    queue.attach(&id, task);
+}
+
+fn emit_all(sink: &dyn EventSink, events: Vec<CreekEvent>) {
+    for event in events {
+        sink.emit(event);
+    }
+}
+
+/// Queue a turn of the current recording (budget permitting) and start it if nothing is running
+async fn submit_turn(
+    text: String,
+    audio: Option<AudioSpan>,
+    recording_id: Option<&String>,
+    queue: &mut TurnQueue,
+    services: &TurnServices,
+    done: &TurnDone,
+) {
+    let app_handle = &services.app_handle;
+    let recording_path = match recording_id {
+        Some(rec_id) => get_current_workspace_recordings_dir(app_handle).await.map(|d| d.join(rec_id)),
+        None => None,
+    };
+    if !check_usage_budget(app_handle, &services.usage_tracker, recording_id).await {
+        return;
+    }
+
+    let turn = QueuedTurn::new(text)
+        .with_audio(audio)
+        .with_recording(recording_id.cloned(), recording_path);
+    let (events, restore) = queue.enqueue(turn);
+    if let Some(rollback) = restore {
+        restore_document(services, rollback).await;
+    }
+    emit_all(app_handle, events);
+    start_next_turn(queue, services, done);
+}
+
+/// Abort queued and running turns (pause, stop, or a command that replaces the
+/// document) and wait until the running one has stopped. `restore` rolls its
+/// edits back; commands that replace the document anyway skip that, so the
+/// rollback doesn't overwrite the new content.
+async fn abort_turns(queue: &mut TurnQueue, services: &TurnServices, reason: &str, restore: bool) {
+    let (events, rollback) = queue.abort_all(reason);
+    match rollback {
+        Some(rollback) if restore => restore_document(services, rollback).await,
+        Some(rollback) => {
+            rollback.stopped().await;
+        }
+        None => {}
+    }
+    if !events.is_empty() {
+        info!("[Processing Cancelled] {} turn(s) aborted", events.len());
+    }
+    emit_all(&services.app_handle, events);
+}
+
+/// Put the document back as it was before an aborted or failed turn, on disk
+/// too, once the turn's task has stopped
+async fn restore_document(services: &TurnServices, rollback: Rollback) {
+    let rollback = rollback.stopped().await;
+    services.doc_service.reset(rollback.snapshot.clone());
+    services.state_manager.update_document(rollback.snapshot);
+    emit_and_save(&services.doc_service, &services.app_handle, rollback.recording_path.as_deref());
+}
+
 pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<PipelineCommand>) {
     // Start with a clean, empty canvas (no default title/template)
     let initial_content = String::new();
@@ -60,25 +245,67 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                 StateManager::new(":memory:").expect("Failed to create in-memory StateManager")
             })
     );
+    let usage_tracker = Arc::new(
+        UsageTracker::new(&get_state_db_path().to_string_lossy())
+            .unwrap_or_else(|e| {
+                warn!("Usage Tracker initialization failed: {:?}", e);
+                UsageTracker::new(":memory:").expect("Failed to create in-memory UsageTracker")
+            })
+    );
+    let trace_store = Arc::new(
+        TraceStore::new(&get_state_db_path().to_string_lossy())
+            .unwrap_or_else(|e| {
+                warn!("Turn Trace Store initialization failed: {:?}", e);
+                TraceStore::new(":memory:").expect("Failed to create in-memory TraceStore")
+            })
+    );
+    let question_store = Arc::new(
+        QuestionStore::new(&get_state_db_path().to_string_lossy())
+            .unwrap_or_else(|e| {
+                warn!("Socratic Question Store initialization failed: {:?}", e);
+                QuestionStore::new(":memory:").expect("Failed to create in-memory QuestionStore")
+            })
+    );
     let git_manager = Arc::new(GitManager::new());
     let todo_agent = Arc::new(TodoAgent::new());
-    let intent_router = Arc::new(IntentRouter::new(api_key.clone()));
     
     // Services
     let doc_service = Arc::new(DocumentService::new(initial_content.clone()));
     
-    // LLM Client:
-    let llm_flash = Arc::new(OpenAILikeClient::new(
-        "https://dashscope.aliyuncs.com/compatible-mode/v1".to_string(),
-        api_key.clone(),
-        "qwen-flash".to_string(),
-    ));
-    
-    let llm_coder = Arc::new(OpenAILikeClient::new(
-        "https://dashscope.aliyuncs.com/compatible-mode/v1".to_string(),
-        api_key.clone(),
-        "qwen3-coder-flash".to_string(),
-    ));
+    // LLM Clients: one per role, resolved from creek.toml (DashScope defaults)
+    let mut config_watcher = ConfigWatcher::new(get_config_path());
+    let mut config_poll = tokio::time::interval(Duration::from_secs(CONFIG_POLL_SECS));
+    let (llm_config, registry, mut asr) = LLMConfig::load(&get_config_path())
+        .and_then(|config| {
+            let registry = LLMRegistry::from_config(&config, &api_key)?;
+            let asr = AsrService::from_config(&config.asr, &api_key)?;
+            Ok((config, registry, asr))
+        })
+        .unwrap_or_else(|e| {
+            let error_msg = format!("Invalid model config, using defaults: {:?}", e);
+            warn!("{}", error_msg);
+            emit_warning_toast(&app_handle, &error_msg);
+            let config = LLMConfig::defaults();
+            let registry = LLMRegistry::from_config(&config, &api_key)
+                .expect("Default LLM registry must build");
+            (config, registry, AsrService::new(api_key.clone()))
+        });
+    // Telemetry settings apply at startup only (one global subscriber)
+    if let Some(telemetry) = Telemetry::init(&llm_config.telemetry) {
+        telemetry.spawn();
+    }
+    usage_tracker.configure(llm_config.pricing, llm_config.budget);
+    let socratic_agent = Arc::new(SocraticAgent::new(question_store).with_config(llm_config.socratic));
+    let mut turn_queue = TurnQueue::new(llm_config.turns.policy);
+    let mut chunking = llm_config.chunking.clone();
+    let (turn_done_tx, mut turn_done_rx) = mpsc::unbounded_channel::<(String, TurnOutcome)>();
+    let mut llms = Arc::new(registry);
+    let router_benchmark = app_handle.state::<Arc<RouterBenchmark>>().inner().clone();
+    let mut intent_router = Arc::new(
+        IntentRouter::new(llms.get(ModelRole::Router))
+            .with_config(llm_config.router)
+            .with_benchmark(router_benchmark.clone())
+    );
     
     // Initialize RAG Service
     let rag_db_path = get_app_data_dir().join("rag_db.lance");
@@ -95,24 +322,50 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
     
     // ASR State
     let mut asr_cancellation_token: Option<CancellationToken> = None;
-    let mut processing_cancellation_token: Option<CancellationToken> = None;
     let mut is_paused = false;
-    let (asr_tx, mut asr_rx) = mpsc::unbounded_channel::<String>();
-    let mut asr = AsrService::new(api_key);
-    asr.set_callback(asr_tx);
-    let mut speech_agg = SpeechAggregator::default();
+    let (asr_tx, mut asr_rx) = mpsc::unbounded_channel::<Transcript>();
+    let (vad_tx, mut vad_rx) = mpsc::unbounded_channel::<VadEvent>();
+    asr.set_callback(asr_tx.clone());
+    let (level_tx, mut level_rx) = mpsc::unbounded_channel::<AudioLevel>();
+    asr.set_vad_callback(vad_tx.clone());
+    asr.set_level_callback(level_tx.clone());
+    let (status_tx, mut status_rx) = mpsc::unbounded_channel::<AsrStatus>();
+    asr.set_status_callback(status_tx.clone());
+    // Reconnect handle of the running recording's ASR
+    let mut asr_control: Option<SessionControl> = None;
+    // With local VAD, turns end at speech boundaries rather than on the holdback timer
+    let mut local_turns = false;
+    let mut speaking = false;
+    // Audio span of the turn being collected; Some while archiving audio
+    let mut turn_spans: Option<TurnSpans> = None;
+    let mut speech_agg = SpeechAggregator::new(&chunking);
 
     // Chat History State
     let chat_history = Arc::new(tokio::sync::RwLock::new(Vec::new()));
+    let mut turn_services = TurnServices {
+        doc_service: doc_service.clone(),
+        llms: llms.clone(),
+        app_handle: app_handle.clone(),
+        events: Arc::new(app_handle.clone()),
+        chat_history: chat_history.clone(),
+        state_manager: state_manager.clone(),
+        git_manager: git_manager.clone(),
+        todo_agent: todo_agent.clone(),
+        socratic_agent: socratic_agent.clone(),
+        rag_service: rag_service.clone(),
+        intent_router: intent_router.clone(),
+        usage_tracker: usage_tracker.clone(),
+        trace_store,
+    };
     
     // Recording State
     let mut current_recording_id: Option<String> = None;
 
     // Initial Emit
-    let _ = app_handle.emit("document-update", DocumentUpdate {
+    app_handle.emit(CreekEvent::DocumentUpdate(DocumentUpdate {
         content: initial_content,
         version: 0,
-    });
+    }));
 
     // Recovery: check for last active recording on startup
     // DISABLED: Causing auto-recording bug without frontend sync.
@@ -125,8 +378,10 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
 
     let mut flush_deadline: Option<Instant> = None;
     let mut holdback_deadline: Option<Instant> = None;
-    let mut pending_transcript = String::new();
-    const HOLDBACK_MS: u64 = 450; 
+    // Local VAD: wait this long after speech ends for its last transcript,
+    // and this long after a transcript once the speaker is silent
+    const VAD_END_SETTLE_MS: u64 = 1500;
+    const VAD_TRANSCRIPT_SETTLE_MS: u64 = 150;
 
     loop {
         let timeout_fut = async {
@@ -150,6 +405,9 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         flush_deadline = None;
                         is_paused = false;
                         if asr_cancellation_token.is_none() {
+                            // Speech left over from the previous recording is not part of this one
+                            speech_agg = SpeechAggregator::new(&chunking);
+                            holdback_deadline = None;
                             // Get workspace recordings dir
                             if let Some(recordings_dir) = get_current_workspace_recordings_dir(&app_handle).await {
                                 info!("Starting Recording... ID: {}", recording_id);
@@ -206,7 +464,24 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                                 
                                 let token = CancellationToken::new();
                                 asr_cancellation_token = Some(token.clone());
-                                let asr_clone = asr.clone();
+                                local_turns = asr.has_local_vad();
+                                speaking = false;
+                                let settings = get_current_workspace_settings(&app_handle).await;
+                                let archive_target = if settings.audio_archive.enabled {
+                                    let retention = settings.audio_archive.clone();
+                                    let dir = recordings_dir.clone();
+                                    let _ = tokio::task::spawn_blocking(move || archive::enforce_retention(&dir, &retention)).await;
+                                    Some(ArchiveTarget::new(&recording_path, settings.audio_archive.segment_secs))
+                                } else {
+                                    None
+                                };
+                                let asr_clone = asr.clone()
+                                    .with_input_device(settings.input_device)
+                                    .with_archive(archive_target.clone());
+                                turn_spans = archive_target.map(|target| TurnSpans::new(target.session, asr_clone.audio_clock()));
+                                let control = asr_clone.session_control();
+                                control.set_settings(current_asr_settings(&app_handle, &doc_service.get_snapshot().content).await);
+                                asr_control = Some(control);
                                 tokio::spawn(async move {
                                     if let Err(e) = asr_clone.start_recording(token).await {
                                         error!("ASR Error: {}", e);
@@ -223,19 +498,36 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         info!("Pausing Recording and all processing...");
                         is_paused = true;
                         
-                        // Cancel any ongoing processing
-                        if let Some(token) = processing_cancellation_token.take() {
-                            token.cancel();
-                            info!("[Processing Cancelled] All agent tasks aborted");
-                        }
+                        // Abort queued and running turns; a half-applied turn is rolled back
+                        abort_turns(&mut turn_queue, &turn_services, "paused", true).await;
+                        // Buffered speech is dropped with them, not submitted while paused
+                        speech_agg = SpeechAggregator::new(&chunking);
                         flush_deadline = None;
+                        holdback_deadline = None;
                     }
                     PipelineCommand::ResumeRecording => {
                         info!("Resuming Recording...");
                         is_paused = false;
                     }
+                    PipelineCommand::RefreshAsrSettings => {
+                        if let Some(control) = &asr_control {
+                            control.set_settings(current_asr_settings(&app_handle, &doc_service.get_snapshot().content).await);
+                        }
+                    }
+                    PipelineCommand::ReconnectAsr => {
+                        match &asr_control {
+                            Some(control) => {
+                                info!("[ASR] Forcing reconnect...");
+                                control.request_reconnect();
+                            }
+                            None => warn!("[ASR] Reconnect ignored: not recording"),
+                        }
+                    }
                     PipelineCommand::StopRecording => {
                         info!("Stopping Recording...");
+
+                        // Abort turns before saving, so the saved document has no half-applied turn
+                        abort_turns(&mut turn_queue, &turn_services, "stopped", true).await;
                         
                         if let Some(rec_id) = &current_recording_id {
                              if let Some(recordings_dir) = get_current_workspace_recordings_dir(&app_handle).await {
@@ -266,7 +558,9 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         info!("  Git history: {} entries", state.git_history.len());
                         info!("  Todos: {}", state.todo_list.len());
                         
+                        speech_agg = SpeechAggregator::new(&chunking);
                         flush_deadline = None;
+                        holdback_deadline = None;
                         is_paused = false;
                         
                         // Cancel ASR
@@ -274,12 +568,7 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                             token.cancel();
                             info!("[ASR Cancelled]");
                         }
-                        
-                        // Cancel any ongoing processing
-                        if let Some(token) = processing_cancellation_token.take() {
-                            token.cancel();
-                            info!("[Processing Cancelled] All agent tasks aborted");
-                        }
+                        asr_control = None;
 
                         if let Err(e) = state_manager.persist_state() {
                             error!("Failed to persist state on stop: {:?}", e);
@@ -294,6 +583,8 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                     }
                     PipelineCommand::ResetDocument => {
                         info!("Resetting Document (Hard Reset)...");
+                        // No turn may keep writing to the document being replaced
+                        abort_turns(&mut turn_queue, &turn_services, "document reset", false).await;
                         flush_deadline = None;
                         chat_history.write().await.clear();
                         doc_service.reset(String::new());
@@ -341,11 +632,12 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                                 }
                             }
                             
-                            let _ = app_handle.emit("show-toast", crate::models::event::ToastPayload::success("Document and memory cleared"));
+                            emit_success_toast(&app_handle, "Document and memory cleared");
                         }
                     }
                     PipelineCommand::UpdateDocument(new_content) => {
                         info!("[Manual Edit]");
+                        abort_turns(&mut turn_queue, &turn_services, "manual edit", false).await;
                         
                         // 1. Update StateManager (Memory)
                         state_manager.update_document(new_content.clone());
@@ -370,7 +662,9 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                                     let diff = git_manager.get_diff(&recording_path).unwrap_or_default();
                                     
                                     // 2. Generate commit message using LLM
-                                    let commit_msg = git_manager.generate_commit_message(&*llm_flash, &diff).await.unwrap_or_else(|_| "Manual edit by user".to_string());
+                                    let usage = UsageScope::new(usage_tracker.clone(), Some(rec_id.clone()));
+                                    let llm = usage.meter(llms.get(ModelRole::CommitMessage), UsageCaller::CommitMessage);
+                                    let commit_msg = git_manager.generate_commit_message(&*llm, &diff).await.unwrap_or_else(|_| "Manual edit by user".to_string());
                                     
                                     // 3. Commit
                                     if let Err(e) = git_manager.commit_existing(&recording_path, &commit_msg) {
@@ -406,6 +700,7 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         }
                     }
                     PipelineCommand::RollbackToCommit(commit_hash) => {
+                        abort_turns(&mut turn_queue, &turn_services, "rolled back", false).await;
                         if let Some(rec_id) = &current_recording_id {
                              if let Some(recordings_dir) = get_current_workspace_recordings_dir(&app_handle).await {
                                 info!("Rolling back to commit: {}", commit_hash);
@@ -431,6 +726,7 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         }
                     }
                     PipelineCommand::UndoLastChange => {
+                        abort_turns(&mut turn_queue, &turn_services, "undone", false).await;
                         if let Some(rec_id) = &current_recording_id {
                              if let Some(recordings_dir) = get_current_workspace_recordings_dir(&app_handle).await {
                                 info!("Undoing last change...");
@@ -475,7 +771,10 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                     }
                     PipelineCommand::LoadRecording { recording_id } => {
                         info!("[Load Recording] {}", recording_id);
+                        abort_turns(&mut turn_queue, &turn_services, "recording changed", false).await;
+                        speech_agg = SpeechAggregator::new(&chunking);
                         flush_deadline = None;
+                        holdback_deadline = None;
                         is_paused = false;
 
                         // Clear history from previous session
@@ -510,7 +809,7 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         emit_update(&doc_service, &app_handle);
 
 
-                        let _ = app_handle.emit("recording-started", serde_json::json!({ "recording_id": recording_id }));
+                        app_handle.emit(CreekEvent::RecordingStarted(RecordingStarted { recording_id: recording_id.clone() }));
                         
                         let rag_clone = rag_service.clone();
                         let rec_id_clone = recording_id.clone();
@@ -534,6 +833,7 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                         // If we deleted the current recording, reset state
                         if let Some(curr) = &current_recording_id {
                             if curr == &recording_id {
+                                abort_turns(&mut turn_queue, &turn_services, "recording deleted", false).await;
                                 current_recording_id = None;
                                 doc_service.reset(String::new());
                                 state_manager.update_document(String::new());
@@ -543,9 +843,7 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                     PipelineCommand::AddTodo(desc) => {
                         info!("[Add Todo] {}", desc);
                         if let Some(_) = &current_recording_id {
-                             let todo_id = uuid::Uuid::new_v4().to_string();
-                             // Correct method: add_todo takes (id, desc)
-                             state_manager.add_todo(todo_id.clone(), desc.clone());
+                             state_manager.add_todo(desc.clone());
                              emit_success_toast(&app_handle, "Todo added");
                              
                              if let Err(e) = state_manager.persist_state() {
@@ -590,149 +888,176 @@ pub async fn run_pipeline(app_handle: AppHandle, mut cmd_rx: mpsc::Receiver<Pipe
                 }
             }
 
-            // Handle Transcripts from ASR
+            // Handle Transcripts from ASR: partials are only shown, finals are acted on
             Some(transcript) = asr_rx.recv() => {
                 if let Some(token) = &asr_cancellation_token {
                     if !token.is_cancelled() && !is_paused {
-                        // Start/Reset Holdback deadline
-                        pending_transcript.push_str(&transcript);
-                        holdback_deadline = Some(Instant::now() + Duration::from_millis(HOLDBACK_MS));
-                        info!("[ASR Chaining] Added to pending batch. Waiting {}ms...", HOLDBACK_MS);
+                        app_handle.emit(CreekEvent::TranscriptPartial(TranscriptUpdate {
+                            item_id: transcript.item_id,
+                            text: transcript.text.clone(),
+                            is_final: transcript.is_final,
+                        }));
+                        if !transcript.is_final {
+                            continue;
+                        }
+                        // A turn that is already full runs now; the rest waits for the timers
+                        for text in speech_agg.feed(&transcript.text) {
+                            info!("[ASR Chunking] Turn reached {} chars, firing {} chars now", chunking.max_chars, text.len());
+                            let turn_audio = turn_spans.as_mut().map(TurnSpans::close_turn);
+                            submit_turn(text, turn_audio, current_recording_id.as_ref(), &mut turn_queue, &turn_services, &turn_done_tx).await;
+                        }
+                        // An unfinished sentence waits at most the flush timeout, however long the speaker goes on
+                        if speech_agg.has_partial() {
+                            flush_deadline.get_or_insert(Instant::now() + Duration::from_millis(chunking.flush_timeout_ms));
+                        } else {
+                            flush_deadline = None;
+                        }
+                        if speech_agg.is_empty() {
+                            holdback_deadline = None;
+                        } else if !local_turns {
+                            // Start/Reset Holdback deadline
+                            holdback_deadline = Some(Instant::now() + Duration::from_millis(chunking.holdback_ms));
+                            info!("[ASR Chaining] Added to pending batch. Waiting {}ms...", chunking.holdback_ms);
+                        } else if speaking {
+                            // The turn ends when the speaker does
+                            holdback_deadline = None;
+                            info!("[ASR Chaining] Added to pending batch. Waiting for speech end...");
+                        } else {
+                            holdback_deadline = Some(Instant::now() + Duration::from_millis(VAD_TRANSCRIPT_SETTLE_MS));
+                            info!("[ASR Chaining] Added to pending batch after speech end. Firing in {}ms...", VAD_TRANSCRIPT_SETTLE_MS);
+                        }
                     }
                 }
             }
 
-            _ = timeout_fut => {
-                let now = Instant::now();
-                
-                // 1. Check Holdback (ASR Batching)
-                if let Some(deadline) = holdback_deadline {
-                    if now >= deadline {
-                        info!("[ASR Holdback] Deadline met. Firing processing for {} chars...", pending_transcript.len());
-                        let text = std::mem::take(&mut pending_transcript);
-                        holdback_deadline = None;
-                        
-                        // Combine any internal buffered text from aggregator with the new text
-                        // to ensure the entire turn is processed as a WHOLE unit.
-                        let mut turn_text = speech_agg.flush();
-                        if !text.is_empty() {
-                            if !turn_text.is_empty() { turn_text.push(' '); }
-                            turn_text.push_str(&text);
-                        }
+            // A turn task ended: roll back if it failed, then run the next one
+            Some((turn_id, outcome)) = turn_done_rx.recv() => {
+                if let Some((event, restore)) = turn_queue.finish(&turn_id, outcome) {
+                    if let Some(rollback) = restore {
+                        warn!("[Turn {}] Failed, rolling back its edits", turn_id);
+                        restore_document(&turn_services, rollback).await;
+                        emit_warning_toast(&app_handle, "Turn failed; its edits were rolled back");
+                    } else {
+                        info!("[Turn {}] Finished", turn_id);
+                    }
+                    app_handle.emit(event);
+                }
+                start_next_turn(&mut turn_queue, &turn_services, &turn_done_tx);
+            }
 
-                        if turn_text.is_empty() {
-                            continue;
-                        }
+            // Connection state of the ASR backend
+            Some(status) = status_rx.recv() => {
+                if let AsrStatus::Failed { error } = &status {
+                    let error_msg = format!("Speech recognition disconnected: {}", error);
+                    emit_error_toast(&app_handle, &error_msg);
+                }
+                app_handle.emit(CreekEvent::AsrStatus(status));
+            }
 
-                        // Resolve recording path
-                        let recording_path = if let Some(rec_id) = &current_recording_id {
-                            get_current_workspace_recordings_dir(&app_handle).await.map(|d| d.join(rec_id))
-                        } else {
-                            None
-                        };
+            // Input level meter for the UI
+            Some(level) = level_rx.recv() => {
+                app_handle.emit(CreekEvent::AudioLevel(level));
+            }
 
-                        // Create new cancellation token for this processing
-                        let cancel_token = CancellationToken::new();
-                        processing_cancellation_token = Some(cancel_token.clone());
-                        
-                        let doc_service_clone = doc_service.clone();
-                        let llm_coder_clone = llm_coder.clone();
-                        let llm_flash_clone = llm_flash.clone();
-                        let app_handle_clone = app_handle.clone();
-                        let state_manager_clone = state_manager.clone();
-                        let git_manager_clone = git_manager.clone();
-                        let todo_agent_clone = todo_agent.clone();
-                        let rag_service_clone = rag_service.clone();
-                        let intent_router_clone = intent_router.clone();
-                        let current_recording_id_clone = current_recording_id.clone();
-                        let recording_path_clone = recording_path.clone();
-                        let chat_history_clone = chat_history.clone();
-                        
-                        tokio::spawn(async move {
-                            tokio::select! {
-                                _ = cancel_token.cancelled() => {
-                                    info!("[Processing Aborted] Task cancelled");
-                                }
-                                _ = process_transcript(
-                                    turn_text, 
-                                    &doc_service_clone, 
-                                    &llm_coder_clone,
-                                    &llm_flash_clone,
-                                    &app_handle_clone, 
-                                    &chat_history_clone,
-                                    &state_manager_clone,
-                                    &git_manager_clone,
-                                    &todo_agent_clone,
-                                    &rag_service_clone,
-                                    &intent_router_clone,
-                                    current_recording_id_clone.as_ref(),
-                                    recording_path_clone.as_deref(),
-                                ) => {
-                                    info!("[Processing Complete]");
-                                }
-                            }
-                        });
+            // Speech boundaries from the local VAD
+            Some(event) = vad_rx.recv() => {
+                match event {
+                    VadEvent::SpeechStart { at_ms } => {
+                        speaking = true;
+                        if let Some(spans) = &mut turn_spans {
+                            spans.speech_started(at_ms);
+                        }
+                        holdback_deadline = None;
+                        app_handle.emit(CreekEvent::SpeechStart(SpeechActivity { at_ms, duration_ms: None }));
+                    }
+                    VadEvent::SpeechEnd { at_ms, duration_ms } => {
+                        speaking = false;
+                        info!("[VAD] Speech ended after {}ms", duration_ms);
+                        // Pending text is held until the last segment's transcript lands
+                        if !speech_agg.is_empty() {
+                            holdback_deadline = Some(Instant::now() + Duration::from_millis(VAD_END_SETTLE_MS));
+                        }
+                        app_handle.emit(CreekEvent::SpeechEnd(SpeechActivity { at_ms, duration_ms: Some(duration_ms) }));
+                    }
+                }
+            }
 
-                        flush_deadline = None;
+            // Hot reload: rebuild role clients and the ASR backend when creek.toml changes.
+            // Running turns keep the clients they were started with.
+            _ = config_poll.tick() => {
+                if let Some(reloaded) = config_watcher.poll() {
+                    let rebuilt = reloaded.and_then(|config| {
+                        let registry = LLMRegistry::from_config(&config, &api_key)?;
+                        let asr = AsrService::from_config(&config.asr, &api_key)?;
+                        Ok((config, registry, asr))
+                    });
+                    match rebuilt {
+                        Ok((config, registry, rebuilt_asr)) => {
+                            usage_tracker.configure(config.pricing, config.budget);
+                            socratic_agent.configure(config.socratic);
+                            turn_queue.set_policy(config.turns.policy);
+                            speech_agg.configure(&config.chunking);
+                            chunking = config.chunking;
+                            llms = Arc::new(registry);
+                            intent_router = Arc::new(
+                                IntentRouter::new(llms.get(ModelRole::Router))
+                                    .with_config(config.router)
+                                    .with_benchmark(router_benchmark.clone())
+                            );
+                            turn_services.llms = llms.clone();
+                            turn_services.intent_router = intent_router.clone();
+                            // Takes effect from the next recording
+                            asr = rebuilt_asr;
+                            asr.set_callback(asr_tx.clone());
+                            asr.set_vad_callback(vad_tx.clone());
+                            asr.set_level_callback(level_tx.clone());
+                            asr.set_status_callback(status_tx.clone());
+                            info!("[Pipeline] Model config reloaded");
+                            emit_success_toast(&app_handle, "Model config reloaded");
+                        }
+                        Err(e) => {
+                            let error_msg = format!("Model config reload failed, keeping previous: {:?}", e);
+                            warn!("{}", error_msg);
+                            emit_warning_toast(&app_handle, &error_msg);
+                        }
                     }
                 }
+            }
 
-                // 2. Check Aggregator Flush (Timeout)
-                if let Some(deadline) = flush_deadline {
-                    if now >= deadline {
-                        let text = speech_agg.flush();
-                        flush_deadline = None;
-                        
-                        // Resolve recording path
-                        let recording_path = if let Some(rec_id) = &current_recording_id {
-                            get_current_workspace_recordings_dir(&app_handle).await.map(|d| d.join(rec_id))
-                        } else {
-                            None
-                        };
+            _ = timeout_fut => {
+                let now = Instant::now();
+                
+                // 1. Holdback: the speaker paused (or stopped), the whole turn runs
+                // 2. Flush: an unfinished sentence waited too long, run what we have
+                let holdback_due = holdback_deadline.is_some_and(|deadline| now >= deadline);
+                let flush_due = flush_deadline.is_some_and(|deadline| now >= deadline);
+                if !holdback_due && !flush_due {
+                    continue;
+                }
+                holdback_deadline = None;
+                flush_deadline = None;
+                // Only a running recording submits turns
+                if is_paused || current_recording_id.is_none() {
+                    continue;
+                }
 
-                        // Create new cancellation token for this processing
-                        let cancel_token = CancellationToken::new();
-                        processing_cancellation_token = Some(cancel_token.clone());
-                        
-                        let doc_service_clone = doc_service.clone();
-                        let llm_coder_clone = llm_coder.clone();
-                        let llm_flash_clone = llm_flash.clone();
-                        let app_handle_clone = app_handle.clone();
-                        let state_manager_clone = state_manager.clone();
-                        let git_manager_clone = git_manager.clone();
-                        let todo_agent_clone = todo_agent.clone();
-                        let rag_service_clone = rag_service.clone();
-                        let intent_router_clone = intent_router.clone();
-                        let current_recording_id_clone = current_recording_id.clone();
-                        let recording_path_clone = recording_path.clone();
-                        let chat_history_clone = chat_history.clone();
-                        
-                        tokio::spawn(async move {
-                            tokio::select! {
-                                _ = cancel_token.cancelled() => {
-                                    info!("[Processing Aborted] Flush task cancelled");
-                                }
-                                _ = process_transcript(
-                                    text, 
-                                    &doc_service_clone, 
-                                    &llm_coder_clone,
-                                    &llm_flash_clone,
-                                    &app_handle_clone, 
-                                    &chat_history_clone,
-                                    &state_manager_clone,
-                                    &git_manager_clone,
-                                    &todo_agent_clone,
-                                    &rag_service_clone,
-                                    &intent_router_clone,
-                                    current_recording_id_clone.as_ref(),
-                                    recording_path_clone.as_deref(),
-                                ) => {
-                                    info!("[Flush Processing Complete]");
-                                }
-                            }
-                        });
-                    }
+                let turn_text = speech_agg.take_turn();
+                if turn_text.is_empty() {
+                    continue;
                 }
+                if holdback_due {
+                    info!("[ASR Holdback] Deadline met. Firing processing for {} chars...", turn_text.len());
+                } else {
+                    info!("[ASR Flush] Unfinished sentence timed out. Firing processing for {} chars...", turn_text.len());
+                }
+                let turn_audio = turn_spans.as_mut().map(TurnSpans::close_turn);
+
+                // The document has grown since the session started: refresh seeded hotwords
+                if let Some(control) = &asr_control {
+                    control.set_settings(current_asr_settings(&app_handle, &doc_service.get_snapshot().content).await);
+                }
+
+                submit_turn(turn_text, turn_audio, current_recording_id.as_ref(), &mut turn_queue, &turn_services, &turn_done_tx).await;
             }
         }
     }
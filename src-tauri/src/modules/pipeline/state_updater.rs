@@ -1,34 +1,33 @@
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
 use log::{info, warn, error};
-use crate::models::event::TodoUpdate;
+use crate::models::event::{CreekEvent, TodoUpdate};
 use crate::modules::document_service::DocumentService;
-use crate::modules::{StateManager, GitManager, TodoAgent, TodoOperation};
-use crate::services::llm_client::OpenAILikeClient;
+use crate::modules::{StateManager, GitManager, TodoAgent, TodoOperation, UsageScope, UsageCaller, EventSink, TraceRecorder};
+use crate::services::llm_provider::{LLMRegistry, ModelRole};
 
 use super::utils::emit_warning_toast;
 
-/// Update state, git, and todos after document change
-pub async fn update_state_and_git(
-    doc_service: &Arc<DocumentService>,
+/// Sync the edited document into the StateManager. Focus and todos follow
+/// once per turn, after it has committed (see `refresh_after_turn`), so an
+/// aborted turn leaves them alone.
+pub fn update_state(doc_service: &Arc<DocumentService>, state_manager: &Arc<StateManager>) {
+    state_manager.update_document(doc_service.get_snapshot().content);
+}
+
+/// Regenerate focus and maintain todos for a committed turn (background)
+pub fn refresh_after_turn(
     state_manager: &Arc<StateManager>,
-    git_manager: &Arc<GitManager>,
     todo_agent: &Arc<TodoAgent>,
-    llm: &Arc<OpenAILikeClient>,
-    app_handle: &AppHandle,
-    recording_path: Option<&std::path::Path>,
+    llms: &Arc<LLMRegistry>,
+    usage: &UsageScope,
+    events: &Arc<dyn EventSink>,
     user_input: &str,
+    content: &str,
 ) {
-    let snapshot = doc_service.get_snapshot();
-    let content = snapshot.content.clone();
-    
-    // 1. Update document in StateManager
-    state_manager.update_document(content.clone());
-    
-    // 2. Generate focus description (non-blocking, with timeout)
+    // 1. Generate focus description (non-blocking, with timeout)
     let state_mgr = state_manager.clone();
-    let llm_clone = llm.clone();
-    let content_clone = content.clone();
+    let llm_clone = usage.meter(llms.get(ModelRole::Focus), UsageCaller::Focus);
+    let content_clone = content.to_string();
     tokio::spawn(async move {
         if let Err(e) = state_mgr.generate_and_update_focus(&*llm_clone, &content_clone).await {
             let error_msg = format!("Focus generation failed: {:?}", e);
@@ -37,12 +36,12 @@ pub async fn update_state_and_git(
         }
     });
     
-    // 2.5. Maintain todos using TodoAgent (non-blocking)
+    // 2. Maintain todos using TodoAgent (non-blocking)
     let state_mgr = state_manager.clone();
     let todo_ag = todo_agent.clone();
-    let llm_clone = llm.clone();
-    let app_clone = app_handle.clone();
-    let content_clone = content.clone();
+    let llm_clone = usage.meter(llms.get(ModelRole::Todo), UsageCaller::Todo);
+    let events = events.clone();
+    let content_clone = content.to_string();
     let user_input = user_input.to_string();
     
     tokio::spawn(async move {
@@ -84,8 +83,7 @@ pub async fn update_state_and_git(
                                 }
                             }
                             TodoOperation::Add { desc } => {
-                                let new_id = uuid::Uuid::new_v4().to_string();
-                                state_mgr.add_todo(new_id.clone(), desc.clone());
+                                let new_id = state_mgr.add_todo(desc.clone());
                                 info!("  + Added: {} ({})", desc, new_id);
                             }
                         }
@@ -96,82 +94,89 @@ pub async fn update_state_and_git(
                     
                     // Emit todo update to frontend
                     let todos = state_mgr.get_todos();
-                    let _ = app_clone.emit("todo-update", TodoUpdate { todos });
+                    events.emit(CreekEvent::TodoUpdate(TodoUpdate { todos }));
                 }
             }
             Err(e) => {
                 let error_msg = format!("Todo Agent failed: {:?}", e);
                 warn!("[Todo Agent] {}", error_msg);
-                emit_warning_toast(&app_clone, &error_msg);
+                emit_warning_toast(&events, &error_msg);
             }
         }
     });
-    
-    // 3. Git commit (if we have a recording)
-    if let Some(recording_path) = recording_path {
-        let recording_path = recording_path.to_path_buf();
+}
 
-        
-        // Get diff and generate commit message (spawn to not block)
-        let git_mgr = git_manager.clone();
-        let state_mgr = state_manager.clone();
-        let llm_clone = llm.clone();
-        let content_clone = content.clone();
-        let recording_path_clone = recording_path.clone();
-        
-        tokio::spawn(async move {
-            // Write document file first (so we can get diff)
-            // Extract recording_id from path
-            let rec_id = recording_path_clone.file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("document");
-            let doc_path = recording_path_clone.join(format!("{}.md", rec_id));
-            if let Err(e) = std::fs::write(&doc_path, &content_clone) {
-                error!("[Git] Failed to write document: {:?}", e);
-                return;
-            }
-            
-            // Get diff
-            let diff = match git_mgr.get_diff(&recording_path_clone) {
-                Ok(d) => d,
-                Err(e) => {
-                    let error_msg = format!("Git diff failed: {:?}", e);
-                    warn!("[Git] {}", error_msg);
-                    // Don't emit toast for git diff failure - it's internal operation
-                    return;
-                }
-            };
-            
-            // Generate commit message
-            let commit_msg = match git_mgr.generate_commit_message(&*llm_clone, &diff).await {
-                Ok(msg) => msg,
-                Err(e) => {
-                    let error_msg = format!("Commit message generation failed: {:?}", e);
-                    warn!("[Git] {}", error_msg);
-                    // Don't emit toast - use fallback message
-                    "Document updated".to_string()
-                }
-            };
-            
-            // Commit (file already written, just need to git add & commit)
-            if let Err(e) = git_mgr.commit_existing(&recording_path_clone, &commit_msg) {
-                let error_msg = format!("Git commit failed: {:?}", e);
-                error!("[Git] {}", error_msg);
-                // Don't emit toast for git commit failure - it's internal operation
-                return;
-            }
-            
-            // Update git history in StateManager
-            state_mgr.add_git_history(commit_msg.clone());
-            
-            // Persist state
-            if let Err(e) = state_mgr.persist_state() {
-                let error_msg = format!("State persistence failed: {:?}", e);
-                warn!("[State Manager] {}", error_msg);
-                // Don't emit toast for state persistence failure - it's internal operation
-            }
-            
-            info!("[Committed] {}", commit_msg);
-        });
+/// Write the document and commit it, once a turn has fully applied
+#[tracing::instrument(name = "commit", skip_all)]
+pub async fn commit_turn(
+    doc_service: &Arc<DocumentService>,
+    state_manager: &Arc<StateManager>,
+    git_manager: &Arc<GitManager>,
+    llms: &Arc<LLMRegistry>,
+    usage: &UsageScope,
+    trace: &TraceRecorder,
+    recording_path: &std::path::Path,
+) {
+    let content = doc_service.get_snapshot().content;
+
+    // Write document file first (so we can get diff)
+    // Extract recording_id from path
+    let rec_id = recording_path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("document");
+    let doc_path = recording_path.join(format!("{}.md", rec_id));
+    if let Err(e) = std::fs::write(&doc_path, &content) {
+        error!("[Git] Failed to write document: {:?}", e);
+        return;
+    }
+
+    // Get diff
+    let diff = match git_manager.get_diff(recording_path) {
+        Ok(d) => d,
+        Err(e) => {
+            let error_msg = format!("Git diff failed: {:?}", e);
+            warn!("[Git] {}", error_msg);
+            // Don't emit toast for git diff failure - it's internal operation
+            return;
+        }
+    };
+    if diff.trim().is_empty() {
+        info!("[Git] Turn left the document unchanged, nothing to commit");
+        return;
+    }
+
+    // Generate commit message
+    let llm = usage.meter(llms.get(ModelRole::CommitMessage), UsageCaller::CommitMessage);
+    let commit_msg = match git_manager.generate_commit_message(&*llm, &diff).await {
+        Ok(msg) => msg,
+        Err(e) => {
+            let error_msg = format!("Commit message generation failed: {:?}", e);
+            warn!("[Git] {}", error_msg);
+            // Don't emit toast - use fallback message
+            "Document updated".to_string()
+        }
+    };
+
+    // Commit (file already written, just need to git add & commit)
+    match git_manager.commit_existing(recording_path, &commit_msg) {
+        Ok(oid) => trace.commit(oid.to_string(), &commit_msg),
+        Err(e) => {
+            let error_msg = format!("Git commit failed: {:?}", e);
+            error!("[Git] {}", error_msg);
+            // Don't emit toast for git commit failure - it's internal operation
+            return;
+        }
     }
+
+    // Update git history in StateManager
+    state_manager.add_git_history(commit_msg.clone());
+
+    // Persist state
+    if let Err(e) = state_manager.persist_state() {
+        let error_msg = format!("State persistence failed: {:?}", e);
+        warn!("[State Manager] {}", error_msg);
+        // Don't emit toast for state persistence failure - it's internal operation
+    }
+
+    info!("[Committed] {}", commit_msg);
 }
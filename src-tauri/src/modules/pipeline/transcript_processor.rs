@@ -1,15 +1,20 @@
 use std::sync::Arc;
 use std::path::Path;
-use tauri::{AppHandle, Emitter};
+use std::time::Instant;
+use tracing::Instrument;
 use log::{info, warn, error};
 
+use crate::models::event::{CreekEvent, RecordingRenamed};
 use crate::modules::document_service::DocumentService;
-use crate::modules::{StateManager, GitManager, TodoAgent, RagService, ConversationTurn, IntentRouter, DocIntent};
-use crate::services::llm_client::{OpenAILikeClient, ChatMessage};
+use crate::modules::{StateManager, GitManager, TodoAgent, RagService, ConversationTurn, IntentRouter, DocIntent, UsageTracker, UsageScope, UsageCaller, EventSink, TraceRecorder, TraceStore};
+use crate::services::asr::{archive, AudioSpan};
+use crate::services::llm_client::ChatMessage;
+use crate::services::llm_provider::{LLMRegistry, ModelRole};
 // Import New Agent System
 use crate::modules::agents::{Agent, AgentContext};
 use crate::modules::agents::rag_agent::RagAgent;
 use crate::modules::agents::search_agent::SearchAgent;
+use crate::modules::agents::socratic_agent::SocraticAgent;
 use crate::modules::agents::editor::append_agent::AppendAgent;
 use crate::modules::agents::editor::edit_agent::EditAgent;
 use crate::modules::agents::editor::grep_agent::GrepAgent;
@@ -17,46 +22,52 @@ use crate::modules::agents::editor::undo_agent::UndoAgent;
 use crate::modules::agents::editor::clear_agent::ClearAgent;
 use crate::commands::recording_commands::{get_recording_metadata, save_recording_metadata};
 use super::auto_naming::generate_recording_name;
+use super::state_updater::{commit_turn, refresh_after_turn};
 
 use super::utils::{emit_update, emit_warning_toast};
 use super::types::MAX_HISTORY;
 
+#[tracing::instrument(name = "turn", skip_all)]
 pub async fn process_transcript(
+    turn_id: &str,
     transcript: String,
     doc_service: &Arc<DocumentService>,
-    llm_coder: &Arc<OpenAILikeClient>,
-    llm_flash: &Arc<OpenAILikeClient>,
-    app_handle: &AppHandle,
+    llms: &Arc<LLMRegistry>,
+    events: &Arc<dyn EventSink>,
     history: &Arc<tokio::sync::RwLock<Vec<ChatMessage>>>,
     state_manager: &Arc<StateManager>,
     git_manager: &Arc<GitManager>,
     todo_agent: &Arc<TodoAgent>,
+    socratic_agent: &Arc<SocraticAgent>,
     rag_service: &Arc<RagService>,
     intent_router: &Arc<IntentRouter>,
+    usage_tracker: &Arc<UsageTracker>,
+    trace_store: &Arc<TraceStore>,
     recording_id: Option<&String>,
     recording_path: Option<&Path>,
-) {
+    audio: Option<AudioSpan>,
+) -> anyhow::Result<()> {
     info!("==================================================");
     info!("[ASR Input] {}", transcript);
-    let _ = app_handle.emit("transcript-update", &transcript);
+
+    // Every LLM call below is attributed to this turn, under the queue's turn id
+    let usage = UsageScope::new(usage_tracker.clone(), recording_id.cloned()).with_turn_id(turn_id);
+    let intent_router = &Arc::new(intent_router.with_usage(&usage));
+    // ...and recorded under the same turn id
+    let trace = TraceRecorder::new(usage.turn_id().to_string(), recording_id.cloned(), transcript.clone());
+    events.emit(CreekEvent::Transcript(transcript.clone()));
 
     // Notify frontend: Thinking started
-    let _ = app_handle.emit("agent-status", crate::models::event::AgentStatusPayload { status: "thinking".to_string() });
+    events.emit(CreekEvent::agent_status("thinking"));
     
-    // 0. Store turn in RAG (async/fire-and-forget to not block pipeline)
-    if let Some(rec_id) = recording_id {
-        let rag_clone = rag_service.clone();
-        let rec_id_clone = rec_id.clone();
-        let transcript_clone = transcript.clone();
-        let app_clone = app_handle.clone();
-        tokio::spawn(async move {
-            let turn = ConversationTurn::new(transcript_clone);
-            if let Err(e) = rag_clone.store_turn(&rec_id_clone, &turn).await {
-                let error_msg = format!("Failed to store conversation in RAG: {:?}", e);
-                warn!("{}", error_msg);
-                emit_warning_toast(&app_clone, &error_msg);
+    // 0. Index the turn's audio; it goes into RAG once the turn has committed
+    let rag_turn = recording_id.map(|_| ConversationTurn::new(transcript.clone()).with_audio(audio));
+    if let Some(turn) = &rag_turn {
+        if let (Some(span), Some(rec_path)) = (&turn.audio, recording_path) {
+            if let Err(e) = archive::append_turn_index(rec_path, &turn.id, span) {
+                warn!("Failed to index turn audio: {:?}", e);
             }
-        });
+        }
     }
 
     let snapshot = doc_service.get_snapshot();
@@ -67,7 +78,7 @@ pub async fn process_transcript(
         info!("[Auto-Normalizing tabs to spaces]");
         full_doc = full_doc.replace("\t", "    ");
         doc_service.reset(full_doc.clone());
-        emit_update(&doc_service, app_handle);
+        emit_update(&doc_service, events);
     }
     
     // ===================================================================
@@ -81,60 +92,17 @@ pub async fn process_transcript(
         .collect::<Vec<_>>()
         .join("\n");
     
-    // Router 1: Document intent planning
-    let router1 = intent_router.clone();
-    let doc_clone = full_doc.clone();
-    let transcript_clone = transcript.clone();
-    let router1_task = tokio::spawn(async move {
-        router1.plan_doc_intents(&doc_clone, &transcript_clone).await
-    });
-    
-    // Router 2: RAG need
-    let router2 = intent_router.clone();
-    let doc_clone = full_doc.clone();
-    let focus_clone = state.focus.clone();
-    let git_history_clone = state.git_history.clone();
-    let todos_clone = todos_str.clone();
-    let transcript_clone = transcript.clone();
-    let router2_task = tokio::spawn(async move {
-        router2.check_rag_need(&doc_clone, &focus_clone, &git_history_clone, &todos_clone, &transcript_clone).await
-    });
+    let stage_started = Instant::now();
+    let decision = intent_router
+        .route(&full_doc, &state.focus, &state.git_history, &todos_str, &transcript)
+        .await;
+    trace.stage("route", stage_started.elapsed());
+    trace.router(&decision);
+    info!("[Router 1: Planned Plan] {:?}", decision.plan);
+    info!("[Router 2: RAG Need] {} ({})", decision.rag.needed, decision.rag.reason);
+    info!("[Router 3: Tool Intent] {:?}", decision.tool);
     
-    // Router 3: Tool intent
-    let router3 = intent_router.clone();
-    let doc_clone = full_doc.clone();
-    let transcript_clone = transcript.clone();
-    let router3_task = tokio::spawn(async move {
-        router3.classify_tool_intent(&doc_clone, &transcript_clone).await
-    });
-    
-    // Wait for all routers to complete
-    let (router1_result, router2_result, router3_result) = tokio::join!(
-        router1_task,
-        router2_task,
-        router3_task
-    );
-    
-    let mut plan = match router1_result {
-        Ok(Ok(plan)) => {
-            info!("[Router 1: Planned Plan] {:?}", plan);
-            plan
-        },
-        Ok(Err(e)) => {
-            error!("[Router 1 Failed] {}", e);
-            vec![crate::modules::intent_router::PlanStep { 
-                intent: "APPEND".to_string(), 
-                instruction: "Process transcript".to_string() 
-            }]
-        },
-        Err(e) => {
-            error!("[Router 1 Task Failed] {:?}", e);
-            vec![crate::modules::intent_router::PlanStep { 
-                intent: "APPEND".to_string(), 
-                instruction: "Process transcript".to_string() 
-            }]
-        }
-    };
+    let mut plan = decision.plan;
     
     // Force APPEND for empty documents
     // "As long as the document is empty, the next or the first action will always be append. This is irrespective of intent router."
@@ -146,42 +114,32 @@ pub async fn process_transcript(
         }];
     }
     
-    let need_rag = match router2_result {
-        Ok(Ok(need)) => {
-            info!("[Router 2: RAG Need] {}", need);
-            need
-        },
-        _ => false // Default safe fallback
-    };
-    
-    let tool_intent = match router3_result {
-        Ok(Ok(intent)) => {
-             info!("[Router 3: Tool Intent] {:?}", intent);
-             intent
-        },
-        _ => crate::modules::intent_router::ToolIntent::None
-    };
+    let need_rag = decision.rag.needed;
+    let tool_intent = decision.tool;
 
     // ===================================================================
     // 2. Parallel Information Gathering (Stage 1)
     // ===================================================================
     
+    let stage_started = Instant::now();
     let rag_task = {
         let rec_id = recording_id.cloned();
         let transcript = transcript.clone();
         let doc_content = full_doc.clone();
-        let llm_flash = llm_flash.clone();
+        let llm_query = usage.meter(llms.get(ModelRole::RagQuery), UsageCaller::RagQuery);
         let rag_service = rag_service.clone();
-        let app_handle = app_handle.clone();
+        let events = events.clone();
+        let trace = trace.clone();
         tokio::spawn(async move {
             RagAgent::gather(
                 need_rag,
                 rec_id.as_deref(),
                 &transcript,
                 &doc_content,
-                &llm_flash,
+                llm_query.as_ref(),
                 &rag_service,
-                &app_handle
+                &events,
+                &trace,
             ).await
         })
     };
@@ -194,6 +152,7 @@ pub async fn process_transcript(
     };
 
     let (rag_result, search_result) = tokio::join!(rag_task, search_task);
+    trace.stage("gather", stage_started.elapsed());
     
     let retrieved_context = rag_result.unwrap_or_else(|_| Ok(String::new())).unwrap_or_default();
     let search_results = search_result.unwrap_or_else(|_| Ok(String::new())).unwrap_or_default();
@@ -208,9 +167,9 @@ pub async fn process_transcript(
     let mut ctx = AgentContext::new(
         transcript.clone(),
         doc_service.clone(),
-        llm_coder.clone(),
-        llm_flash.clone(),
-        app_handle.clone(),
+        llms.clone(),
+        usage.clone(),
+        events.clone(),
         state_manager.clone(),
         git_manager.clone(),
         todo_agent.clone(),
@@ -224,6 +183,7 @@ pub async fn process_transcript(
     );
     
     ctx.plan = plan.clone();
+    ctx.trace = trace.clone();
     ctx.retrieved_context = retrieved_context;
     ctx.search_results = search_results;
     
@@ -236,33 +196,42 @@ pub async fn process_transcript(
         ctx.current_step = step;
         let doc_intent = plan_step.to_doc_intent().unwrap_or(DocIntent::NoOp);
         ctx.doc_intent = doc_intent.clone();
+        let agent: Option<&dyn Agent> = match doc_intent {
+            DocIntent::Append => Some(&AppendAgent),
+            DocIntent::Edit => Some(&EditAgent),
+            DocIntent::Grep => Some(&GrepAgent),
+            DocIntent::Undo => Some(&UndoAgent),
+            DocIntent::Clear => Some(&ClearAgent),
+            DocIntent::NoOp => None,
+        };
+        trace.start_step(plan_step, agent.map_or("NoOp", |a| a.name()));
+        let step_started = Instant::now();
         
-        let execution_result = match doc_intent {
-             DocIntent::Append => AppendAgent.execute(&mut ctx).await,
-             // ...
-            DocIntent::Edit => EditAgent.execute(&mut ctx).await,
-            DocIntent::Grep => GrepAgent.execute(&mut ctx).await,
-            DocIntent::Undo => UndoAgent.execute(&mut ctx).await,
-            DocIntent::Clear => ClearAgent.execute(&mut ctx).await,
-            DocIntent::NoOp => {
+        let execution_result = match agent {
+            Some(agent) => agent.execute(&mut ctx)
+                .instrument(tracing::info_span!("agent_step", agent = agent.name()))
+                .await,
+            None => {
                  info!("[Action: NO-OP] Skipping document update");
                  // Only add history/emit idle if distinct from previous steps or is single step
                  if plan.len() == 1 {
                      ctx.chat_history.push(ChatMessage { role: "user".to_string(), content: ctx.transcript.clone() });
                      ctx.chat_history.push(ChatMessage { role: "assistant".to_string(), content: "ACTION: NOOP".to_string() });
-                     let _ = app_handle.emit("agent-status", crate::models::event::AgentStatusPayload { status: "idle".to_string() });
+                     events.emit(CreekEvent::agent_status("idle"));
                  }
                  Ok(())
             },
         };
+        trace.end_step(step_started.elapsed(), execution_result.as_ref().err().map(|e| format!("{:#}", e)));
 
         if let Err(e) = execution_result {
             error!("[Execution Error at Step {}] {}", step, e);
-            emit_warning_toast(app_handle, &format!("Agent Error: {}", e));
-            // Decide if we should continue subsequent steps? 
-            // For safety, maybe break? Or continue if independent?
-            // "Edit then Append" -> if Edit fails, Append might be confused. Break.
-            break; 
+            emit_warning_toast(events, &format!("Agent Error: {}", e));
+            // "Edit then Append" -> if Edit fails, Append might be confused.
+            // Stop here; the turn queue rolls the whole turn back.
+            let e = e.context(format!("Step {} ({}) failed", step + 1, plan_step.intent));
+            save_trace(trace_store, trace.finish(Some(format!("{:#}", e))));
+            return Err(e);
         }
     }
 
@@ -282,6 +251,57 @@ pub async fn process_transcript(
         }
     }
 
+    // Commit the turn's edits as one version
+    if let Some(rec_path) = recording_path {
+        let stage_started = Instant::now();
+        commit_turn(doc_service, state_manager, git_manager, llms, &usage, &trace, rec_path).await;
+        trace.stage("commit", stage_started.elapsed());
+    }
+    save_trace(trace_store, trace.finish(None));
+
+    // Only a committed turn reaches focus, todos and RAG memory (background)
+    let content = doc_service.get_snapshot().content;
+    let changed = content != full_doc;
+    if changed {
+        refresh_after_turn(state_manager, todo_agent, llms, &usage, events, &transcript, &content);
+    }
+    if let (Some(rec_id), Some(turn)) = (recording_id, rag_turn) {
+        let rag_clone = rag_service.clone();
+        let rec_id_clone = rec_id.clone();
+        let events = events.clone();
+        tokio::spawn(async move {
+            if let Err(e) = rag_clone.store_turn(&rec_id_clone, &turn).await {
+                let error_msg = format!("Failed to store conversation in RAG: {:?}", e);
+                warn!("{}", error_msg);
+                emit_warning_toast(&events, &error_msg);
+            }
+        });
+    }
+
+    // Question what this turn wrote and close what it answered (background).
+    // Turns that leave the document alone only get the answer check.
+    if let Some(rec_id) = recording_id {
+        if socratic_agent.enabled() {
+            let socratic_agent = socratic_agent.clone();
+            let llm = usage.meter(llms.get(ModelRole::Socratic), UsageCaller::Socratic);
+            let rag_service = rag_service.clone();
+            let events = events.clone();
+            let rec_id = rec_id.clone();
+            let turn_id = usage.turn_id().to_string();
+            let transcript = transcript.clone();
+            tokio::spawn(async move {
+                match socratic_agent
+                    .review_turn(llm.as_ref(), &rag_service, &rec_id, &turn_id, &transcript, &content, changed)
+                    .await
+                {
+                    Ok(Some(update)) => events.emit(CreekEvent::SocraticQuestion(update)),
+                    Ok(None) => {}
+                    Err(e) => warn!("[Socratic Agent] Review failed: {:?}", e),
+                }
+            });
+        }
+    }
+
     // ===================================================================
     // 5. Auto-Naming (Optional)
     // ===================================================================
@@ -297,9 +317,9 @@ pub async fn process_transcript(
                     info!("[Auto-Naming] Triggering for: {}. Content length: {}", rec_id, content.len());
                     let rec_id_clone = rec_id.clone();
                     let content_clone = content.clone();
-                    let llm_clone = llm_flash.clone();
+                    let llm_clone = usage.meter(llms.get(ModelRole::Flash), UsageCaller::AutoNaming);
                     let rec_path_clone = rec_path.to_path_buf();
-                    let app_clone = app_handle.clone();
+                    let events = events.clone();
                     
                     tokio::spawn(async move {
                         match generate_recording_name(&content_clone, &llm_clone).await {
@@ -309,11 +329,11 @@ pub async fn process_transcript(
                                 } else {
                                     info!("[Auto-Naming Success] Renamed to: {}", new_name);
                                     // Notify frontend with specific renaming event for immediate refresh
-                                    let _ = app_clone.emit("recording-renamed", serde_json::json!({
-                                        "id": rec_id_clone,
-                                        "new_name": new_name
+                                    events.emit(CreekEvent::RecordingRenamed(RecordingRenamed {
+                                        id: rec_id_clone,
+                                        new_name,
                                     }));
-                                    let _ = app_clone.emit("recordings-updated", ());
+                                    events.emit(CreekEvent::RecordingsUpdated);
                                 }
                             }
                             Err(e) => {
@@ -329,4 +349,11 @@ pub async fn process_transcript(
     }
 
     info!("==================================================");
+    Ok(())
+}
+
+fn save_trace(store: &TraceStore, trace: crate::modules::TurnTrace) {
+    if let Err(e) = store.save(&trace) {
+        warn!("[Turn Trace] Failed to save trace of turn {}: {:?}", trace.turn_id, e);
+    }
 }
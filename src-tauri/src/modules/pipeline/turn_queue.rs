@@ -0,0 +1,323 @@
+// Turn Queue
+//
+// Turns of the current recording run one at a time against the document.
+// What happens when a turn fires while another is running is the `[turns]
+// policy`:
+//   - serial:          queue it behind the running turn
+//   - coalesce-pending: merge it into the turn still waiting (if any), so a
+//                      burst of speech becomes one turn after the running one
+//   - cancel-previous: abort the running turn (and anything waiting), run this
+//
+// The queue only decides; the pipeline runs the turns and reports back. Each
+// started turn remembers the document it began from, so an aborted or failed
+// turn is rolled back whole (its git commit only happens when it completes).
+// The rollback first stops the turn's task, so nothing it still had in flight
+// can write over the restored document. Focus, todos and RAG memory are only
+// updated after a turn commits, so there is nothing to undo there.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::models::event::{CreekEvent, TurnEvent};
+use crate::services::asr::AudioSpan;
+use crate::services::llm_provider::TurnPolicy;
+
+#[derive(Debug, Clone)]
+pub struct QueuedTurn {
+    pub id: String,
+    pub text: String,
+    pub audio: Option<AudioSpan>,
+    pub recording_id: Option<String>,
+    pub recording_path: Option<PathBuf>,
+}
+
+impl QueuedTurn {
+    pub fn new(text: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            text,
+            audio: None,
+            recording_id: None,
+            recording_path: None,
+        }
+    }
+
+    pub fn with_audio(mut self, audio: Option<AudioSpan>) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    pub fn with_recording(mut self, recording_id: Option<String>, recording_path: Option<PathBuf>) -> Self {
+        self.recording_id = recording_id;
+        self.recording_path = recording_path;
+        self
+    }
+
+    /// Fold a later turn of the same recording into this one
+    fn absorb(&mut self, later: QueuedTurn) {
+        self.text.push(' ');
+        self.text.push_str(&later.text);
+        self.audio = match (self.audio.take(), later.audio) {
+            (Some(first), Some(last)) if first.session == last.session => {
+                Some(AudioSpan { end_ms: last.end_ms, ..first })
+            }
+            (first, last) => first.or(last),
+        };
+    }
+}
+
+fn queued_event(turn: &QueuedTurn, position: usize) -> TurnEvent {
+    TurnEvent {
+        text: Some(turn.text.clone()),
+        position: Some(position),
+        ..TurnEvent::new(&turn.id)
+    }
+}
+
+/// Document as it was when the running turn started
+struct RunningTurn {
+    id: String,
+    cancel: CancellationToken,
+    snapshot: String,
+    recording_path: Option<PathBuf>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl RunningTurn {
+    fn into_rollback(self) -> Rollback {
+        Rollback { snapshot: self.snapshot, recording_path: self.recording_path, task: self.task }
+    }
+}
+
+/// What undoing an aborted or failed turn takes
+pub struct Rollback {
+    /// Document as it was when the turn started
+    pub snapshot: String,
+    /// Recording whose document file the turn may have saved
+    pub recording_path: Option<PathBuf>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Rollback {
+    /// Abort the turn's task and wait until it is gone; only then is it safe
+    /// to restore the document
+    pub async fn stopped(mut self) -> Self {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            let _ = task.await;
+        }
+        self
+    }
+}
+
+/// How a turn task ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum TurnOutcome {
+    Completed,
+    Failed(String),
+}
+
+pub struct TurnQueue {
+    policy: TurnPolicy,
+    pending: VecDeque<QueuedTurn>,
+    running: Option<RunningTurn>,
+}
+
+impl TurnQueue {
+    pub fn new(policy: TurnPolicy) -> Self {
+        Self { policy, pending: VecDeque::new(), running: None }
+    }
+
+    pub fn set_policy(&mut self, policy: TurnPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.running.is_none() && self.pending.is_empty()
+    }
+
+    /// Add a turn. With cancel-previous, the returned rollback undoes the
+    /// aborted turn before the new one starts.
+    pub fn enqueue(&mut self, turn: QueuedTurn) -> (Vec<CreekEvent>, Option<Rollback>) {
+        let mut events = Vec::new();
+        let mut restore = None;
+
+        match self.policy {
+            TurnPolicy::Serial => {}
+            TurnPolicy::CoalescePending => {
+                let position = self.pending.len();
+                if let Some(waiting) = self.pending.back_mut() {
+                    waiting.absorb(turn);
+                    events.push(CreekEvent::TurnQueued(queued_event(waiting, position)));
+                    return (events, None);
+                }
+            }
+            TurnPolicy::CancelPrevious => {
+                let (aborted, rollback) = self.abort_all("superseded");
+                events.extend(aborted);
+                restore = rollback;
+            }
+        }
+
+        self.pending.push_back(turn);
+        let queued = self.pending.back().expect("just pushed");
+        events.push(CreekEvent::TurnQueued(queued_event(queued, self.pending.len())));
+        (events, restore)
+    }
+
+    /// Next turn to run, if nothing is running. `snapshot` is the document now.
+    pub fn start_next(&mut self, snapshot: impl FnOnce() -> String) -> Option<(QueuedTurn, CancellationToken, CreekEvent)> {
+        if self.running.is_some() {
+            return None;
+        }
+        let turn = self.pending.pop_front()?;
+        let cancel = CancellationToken::new();
+        self.running = Some(RunningTurn {
+            id: turn.id.clone(),
+            cancel: cancel.clone(),
+            snapshot: snapshot(),
+            recording_path: turn.recording_path.clone(),
+            task: None,
+        });
+        let event = CreekEvent::TurnStarted(TurnEvent::new(&turn.id));
+        Some((turn, cancel, event))
+    }
+
+    /// The task running the turn `start_next` handed out
+    pub fn attach(&mut self, id: &str, task: JoinHandle<()>) {
+        match self.running.as_mut() {
+            Some(running) if running.id == id => running.task = Some(task),
+            // Already aborted: make sure it stops
+            _ => task.abort(),
+        }
+    }
+
+    /// A turn task ended. Returns the event, plus the rollback if it failed;
+    /// None for turns that were already aborted.
+    pub fn finish(&mut self, id: &str, outcome: TurnOutcome) -> Option<(CreekEvent, Option<Rollback>)> {
+        if self.running.as_ref().map(|r| r.id.as_str()) != Some(id) {
+            return None;
+        }
+        let running = self.running.take().expect("checked above");
+        Some(match outcome {
+            TurnOutcome::Completed => (CreekEvent::TurnFinished(TurnEvent::new(id)), None),
+            TurnOutcome::Failed(error) => (CreekEvent::TurnAborted(TurnEvent::new(id).with_reason(error)), Some(running.into_rollback())),
+        })
+    }
+
+    /// Abort the running turn and drop waiting ones (pause, stop, supersede).
+    /// Returns the events and the rollback of the running turn.
+    pub fn abort_all(&mut self, reason: &str) -> (Vec<CreekEvent>, Option<Rollback>) {
+        let mut events = Vec::new();
+        let restore = self.running.take().map(|running| {
+            running.cancel.cancel();
+            events.push(CreekEvent::TurnAborted(TurnEvent::new(&running.id).with_reason(reason)));
+            running.into_rollback()
+        });
+        for turn in self.pending.drain(..) {
+            events.push(CreekEvent::TurnAborted(TurnEvent::new(&turn.id).with_reason(reason)));
+        }
+        (events, restore)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn names(events: &[CreekEvent]) -> Vec<&'static str> {
+        events.iter().map(CreekEvent::name).collect()
+    }
+
+    #[test]
+    fn test_serial_runs_in_order_one_at_a_time() {
+        let mut queue = TurnQueue::new(TurnPolicy::Serial);
+        let first = QueuedTurn::new("one".into());
+        let second = QueuedTurn::new("two".into());
+        let (first_id, second_id) = (first.id.clone(), second.id.clone());
+        queue.enqueue(first);
+        queue.enqueue(second);
+
+        let (running, _, _) = queue.start_next(|| "doc v1".into()).unwrap();
+        assert_eq!(running.id, first_id);
+        assert!(queue.start_next(|| unreachable!()).is_none());
+
+        let (event, restore) = queue.finish(&first_id, TurnOutcome::Completed).unwrap();
+        assert_eq!(event.name(), "turn-finished");
+        assert!(restore.is_none());
+        let (running, _, _) = queue.start_next(|| "doc v2".into()).unwrap();
+        assert_eq!(running.id, second_id);
+
+        // A failed turn hands back the document it started from
+        let (event, restore) = queue.finish(&second_id, TurnOutcome::Failed("boom".into())).unwrap();
+        assert_eq!(event.name(), "turn-aborted");
+        assert_eq!(restore.unwrap().snapshot, "doc v2");
+        assert!(queue.is_idle());
+    }
+
+    #[test]
+    fn test_coalesce_merges_waiting_turns_only() {
+        let mut queue = TurnQueue::new(TurnPolicy::CoalescePending);
+        queue.enqueue(QueuedTurn::new("running".into()));
+        queue.start_next(String::new).unwrap();
+
+        let span = |start_ms, end_ms| Some(AudioSpan { session: "s".into(), start_ms, end_ms });
+        let waiting = QueuedTurn::new("add a heading".into()).with_audio(span(1_000, 2_000));
+        let waiting_id = waiting.id.clone();
+        queue.enqueue(waiting);
+        let (events, _) = queue.enqueue(QueuedTurn::new("called Risks".into()).with_audio(span(2_500, 3_000)));
+        assert!(matches!(&events[0], CreekEvent::TurnQueued(e) if e.turn_id == waiting_id));
+
+        let running_id = queue.running.as_ref().unwrap().id.clone();
+        queue.finish(&running_id, TurnOutcome::Completed);
+        let (merged, _, _) = queue.start_next(String::new).unwrap();
+        assert_eq!(merged.text, "add a heading called Risks");
+        assert_eq!(merged.audio, span(1_000, 3_000));
+        assert!(queue.pending.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_previous_aborts_and_restores() {
+        let mut queue = TurnQueue::new(TurnPolicy::CancelPrevious);
+        queue.enqueue(QueuedTurn::new("first".into()));
+        let (first, cancel, _) = queue.start_next(|| "before first".into()).unwrap();
+
+        let (events, restore) = queue.enqueue(QueuedTurn::new("second".into()));
+        assert!(cancel.is_cancelled());
+        assert_eq!(names(&events), vec!["turn-aborted", "turn-queued"]);
+        assert_eq!(restore.unwrap().snapshot, "before first");
+
+        // The aborted task reporting in late changes nothing
+        assert!(queue.finish(&first.id, TurnOutcome::Completed).is_none());
+        assert_eq!(queue.start_next(String::new).unwrap().0.text, "second");
+    }
+
+    #[tokio::test]
+    async fn test_rollback_waits_for_a_turn_that_ignores_cancellation() {
+        let doc = Arc::new(Mutex::new("before".to_string()));
+        let mut queue = TurnQueue::new(TurnPolicy::Serial);
+        queue.enqueue(QueuedTurn::new("slow".into()));
+        let (turn, _cancel, _) = queue.start_next(|| doc.lock().unwrap().clone()).unwrap();
+
+        // Keeps editing the document after it was cancelled
+        let task_doc = doc.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                task_doc.lock().unwrap().push_str(" edit");
+            }
+        });
+        queue.attach(&turn.id, task);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let (_, rollback) = queue.abort_all("stopped");
+        let rollback = rollback.unwrap().stopped().await;
+        *doc.lock().unwrap() = rollback.snapshot;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(*doc.lock().unwrap(), "before");
+    }
+}
@@ -1,12 +1,10 @@
 use serde::Serialize;
 
-pub const FLUSH_TIMEOUT_MS: u64 = 2000;
+use crate::services::llm_provider::ChunkingConfig;
+
 pub const MAX_HISTORY: usize = 6; // 3 turns (User+Assistant pairs)
 pub const MAX_EDIT_RETRIES: usize = 3;
-
-// ASR chunking (character count)
-pub const MIN_SPEECH_CHARS: usize = 40;
-pub const MAX_SPEECH_CHARS: usize = 500;
+pub const CONFIG_POLL_SECS: u64 = 2; // creek.toml hot-reload check interval
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize)]
@@ -20,12 +18,12 @@ pub fn char_len(s: &str) -> usize {
     s.chars().count()
 }
 
-pub fn split_long_speech(s: &str) -> Vec<String> {
+pub fn split_long_speech(s: &str, max_chars: usize) -> Vec<String> {
     let t = s.trim();
     if t.is_empty() {
         return vec![];
     }
-    if char_len(t) <= MAX_SPEECH_CHARS {
+    if char_len(t) <= max_chars {
         return vec![t.to_string()];
     }
 
@@ -35,7 +33,7 @@ pub fn split_long_speech(s: &str) -> Vec<String> {
 
     for ch in t.chars() {
         buf.push(ch);
-        if matches!(ch, '。' | '！' | '？' | '.' | '!' | '?' | ';' | '；' | '\n') || char_len(&buf) >= MAX_SPEECH_CHARS {
+        if matches!(ch, '。' | '！' | '？' | '.' | '!' | '?' | ';' | '；' | '\n') || char_len(&buf) >= max_chars {
             let chunk = buf.trim().to_string();
             if !chunk.is_empty() {
                 out.push(chunk);
@@ -50,25 +48,92 @@ pub fn split_long_speech(s: &str) -> Vec<String> {
     out
 }
 
-#[derive(Default)]
+/// Batches final transcripts into turns. Sentences are released from `buf`
+/// once they are long enough and collect in the current turn until it fires
+/// (holdback/flush timers) or would grow past `max_chars`.
 pub struct SpeechAggregator {
+    /// Unfinished (or still too short) speech
     pub buf: String,
+    /// Released sentences of the turn being collected
+    turn: String,
+    min_chars: usize,
+    max_chars: usize,
+}
+
+impl Default for SpeechAggregator {
+    fn default() -> Self {
+        Self::new(&ChunkingConfig::default())
+    }
 }
 
 impl SpeechAggregator {
+    pub fn new(config: &ChunkingConfig) -> Self {
+        Self {
+            buf: String::new(),
+            turn: String::new(),
+            min_chars: config.min_chars,
+            max_chars: config.max_chars,
+        }
+    }
+
+    /// Apply reloaded thresholds; buffered speech is kept
+    pub fn configure(&mut self, config: &ChunkingConfig) {
+        self.min_chars = config.min_chars;
+        self.max_chars = config.max_chars;
+    }
+
+    /// Add a final transcript to the current turn. Returns turns that are
+    /// already full and should run now.
+    pub fn feed(&mut self, text: &str) -> Vec<String> {
+        let mut full = Vec::new();
+        for sentence in self.push(text) {
+            if !self.turn.is_empty() && char_len(&self.turn) + 1 + char_len(&sentence) > self.max_chars {
+                full.push(std::mem::take(&mut self.turn));
+            }
+            if !self.turn.is_empty() {
+                self.turn.push(' ');
+            }
+            self.turn.push_str(&sentence);
+        }
+        full
+    }
+
+    /// Speech is buffered that has not ended a sentence yet
+    pub fn has_partial(&self) -> bool {
+        !self.buf.trim().is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turn.is_empty() && !self.has_partial()
+    }
+
+    /// The whole current turn, unfinished tail included
+    pub fn take_turn(&mut self) -> String {
+        let tail = self.flush();
+        let mut turn = std::mem::take(&mut self.turn);
+        if !tail.is_empty() {
+            if !turn.is_empty() {
+                turn.push(' ');
+            }
+            turn.push_str(&tail);
+        }
+        turn
+    }
+
     pub fn push(&mut self, chunk: &str) -> Vec<String> {
         let chunk = chunk.trim();
         if chunk.is_empty() {
             return vec![];
         }
 
-        // If it's very long, flush buffer first then split the long chunk.
-        if char_len(chunk) > MAX_SPEECH_CHARS {
+        // If it's very long, flush buffer first (however short, so speech
+        // stays in order) then split the long chunk.
+        if char_len(chunk) > self.max_chars {
             let mut out = Vec::new();
-            if char_len(&self.buf) >= MIN_SPEECH_CHARS {
+            if self.has_partial() {
                 out.push(self.flush());
             }
-            out.extend(split_long_speech(chunk));
+            out.extend(split_long_speech(chunk, self.max_chars));
             return out;
         }
 
@@ -86,13 +151,13 @@ impl SpeechAggregator {
             || self.buf.ends_with('!')
             || self.buf.ends_with('?');
 
-        let should_flush = (char_len(&self.buf) >= MIN_SPEECH_CHARS && ends_with_punct)
-            || char_len(&self.buf) >= MAX_SPEECH_CHARS;
+        let should_flush = (char_len(&self.buf) >= self.min_chars && ends_with_punct)
+            || char_len(&self.buf) >= self.max_chars;
 
         if should_flush {
             let text = self.flush();
             // split again if it becomes too long
-            return split_long_speech(&text);
+            return split_long_speech(&text, self.max_chars);
         }
 
         vec![]
@@ -111,6 +176,10 @@ pub enum PipelineCommand {
     PauseRecording,
     ResumeRecording,
     StopRecording,
+    /// Drop and re-establish the ASR connection of the running recording
+    ReconnectAsr,
+    /// Re-apply the workspace's ASR settings to the running session
+    RefreshAsrSettings,
     ResetDocument,
     UpdateDocument(String),
     IngestDocument { filename: String, content: String },
@@ -124,3 +193,52 @@ pub enum PipelineCommand {
     ToggleTodo(String), // id
     DeleteTodo(String), // id
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregator(min_chars: usize, max_chars: usize) -> SpeechAggregator {
+        SpeechAggregator::new(&ChunkingConfig { min_chars, max_chars, ..ChunkingConfig::default() })
+    }
+
+    #[test]
+    fn test_short_sentences_wait_for_the_turn_to_fire() {
+        let mut agg = aggregator(20, 100);
+        assert!(agg.feed("Add a heading.").is_empty());
+        assert!(agg.has_partial());
+        assert!(agg.feed("Call it Risks and list three of them.").is_empty());
+        assert!(!agg.has_partial());
+        assert!(agg.feed("and then").is_empty());
+
+        assert_eq!(agg.take_turn(), "Add a heading. Call it Risks and list three of them. and then");
+        assert!(agg.is_empty());
+    }
+
+    #[test]
+    fn test_monologue_is_split_at_sentences_into_bounded_turns() {
+        let mut agg = aggregator(10, 60);
+        let sentence = "This sentence is about thirty chars.";
+        let mut turns = Vec::new();
+        for _ in 0..5 {
+            turns.extend(agg.feed(sentence));
+        }
+        turns.push(agg.take_turn());
+
+        assert!(turns.len() >= 3);
+        assert!(turns.iter().all(|turn| char_len(turn) <= 60 && turn.ends_with('.')));
+        assert_eq!(turns.join(" "), [sentence; 5].join(" "));
+    }
+
+    #[test]
+    fn test_long_chunk_keeps_short_buffered_speech_in_order() {
+        let mut agg = aggregator(20, 40);
+        assert!(agg.feed("First,").is_empty());
+        let long = "then a sentence that is long enough. And another one that also is.";
+        let mut all = agg.feed(long);
+        all.push(agg.take_turn());
+
+        assert_eq!(all.join(" "), format!("First, {}", long));
+        assert!(!agg.has_partial());
+    }
+}
@@ -20,6 +20,7 @@ use std::sync::Arc as StdArc;
 use futures_util::TryStreamExt;
 use sha2::{Sha256, Digest};
 use log::{info, warn};
+use crate::services::asr::AudioSpan;
 
 const SIMILARITY_THRESHOLD: f32 = 0.7;
 
@@ -49,6 +50,16 @@ fn get_table_name(recording_id: &str, table_type: &str) -> String {
     }
 }
 
+/// Similarity of a nearest-neighbour candidate, and whether it cleared the threshold
+fn record_candidate(similarity: f32) {
+    let outcome = if similarity >= SIMILARITY_THRESHOLD { "hit" } else { "miss" };
+    tracing::info!(
+        monotonic_counter.rag_candidates = 1,
+        histogram.rag_similarity = similarity as f64,
+        outcome,
+    );
+}
+
 /// Search Result - unified result from RAG
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -64,6 +75,10 @@ pub struct ConversationTurn {
     pub id: String,           // UUID
     pub timestamp: i64,       // Unix timestamp in milliseconds
     pub asr_input: String,    // User ASR chunk raw text
+    /// Where the turn's audio is archived (audio archive enabled); kept in
+    /// the recording's `audio/turns.jsonl`, not in LanceDB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioSpan>,
 }
 
 impl ConversationTurn {
@@ -72,8 +87,14 @@ impl ConversationTurn {
             id: uuid::Uuid::new_v4().to_string(),
             timestamp: Utc::now().timestamp_millis(),
             asr_input,
+            audio: None,
         }
     }
+
+    pub fn with_audio(mut self, audio: Option<AudioSpan>) -> Self {
+        self.audio = audio;
+        self
+    }
 }
 
 /// Query Agent - generates retrieval queries from user input
@@ -85,7 +106,7 @@ impl QueryAgent {
     }
     
     /// Generate retrieval query from user input using LLM
-    pub async fn generate_query<T: crate::services::llm_client::LLMClient>(
+    pub async fn generate_query<T: crate::services::llm_client::LLMClient + ?Sized>(
         &self,
         llm: &T,
         user_input: &str,
@@ -102,12 +123,9 @@ impl QueryAgent {
             }
         ];
         
-        // Call LLM with timeout
-        let query = match tokio::time::timeout(
-            std::time::Duration::from_secs(10),
-            self.collect_llm_response(llm, messages)
-        ).await {
-            Ok(Ok(resp)) => {
+        // Deadline comes from the client's role settings
+        let query = match self.collect_llm_response(llm, messages).await {
+            Ok(resp) => {
                 let trimmed = resp.trim().to_string();
                 if trimmed.is_empty() || trimmed.len() > 100 {
                     user_input.to_string() // Fallback to original input
@@ -122,7 +140,7 @@ impl QueryAgent {
     }
     
     /// Helper to collect full LLM response from stream
-    async fn collect_llm_response<T: crate::services::llm_client::LLMClient>(
+    async fn collect_llm_response<T: crate::services::llm_client::LLMClient + ?Sized>(
         &self,
         llm: &T,
         messages: Vec<crate::services::llm_client::ChatMessage>,
@@ -307,6 +325,7 @@ impl RagService {
                 
                 // Convert L2 distance to cosine similarity
                 let similarity = 1.0 - (distance * distance) / 2.0;
+                record_candidate(similarity);
                 
                 if similarity >= SIMILARITY_THRESHOLD {
                     results.push(SearchResult {
@@ -483,12 +502,14 @@ impl RagService {
                 
                 // Convert L2 distance to cosine similarity
                 let similarity = 1.0 - (distance * distance) / 2.0;
+                record_candidate(similarity);
                 
                 if similarity >= SIMILARITY_THRESHOLD {
                     let turn = ConversationTurn {
                         id: id_col.value(i).to_string(),
                         timestamp: timestamp_col.value(i),
                         asr_input: asr_input_col.value(i).to_string(),
+                        audio: None,
                     };
                     
                     // Safe string truncation for display
@@ -0,0 +1,344 @@
+// Socratic Questions Module
+//
+// Questions the Socratic agent (agents/socratic_agent.rs) put to the user
+// about their document. They live in the `socratic_questions` table in the
+// state DB, numbered per recording ("q-1", "q-2", ...), and stay open until a
+// later turn answers them or the user dismisses them, so the same gap is not
+// raised twice and the frontend can show what is still unresolved.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// What is wrong with the statement a question is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionKind {
+    /// Conflicts with another part of the document or with an earlier turn
+    Contradiction,
+    /// Relies on something that was never stated or checked
+    Assumption,
+    /// Lacks the numbers, names, dates or owners that would make it actionable
+    MissingSpecifics,
+    /// Too general to be wrong
+    Vague,
+}
+
+impl QuestionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuestionKind::Contradiction => "contradiction",
+            QuestionKind::Assumption => "assumption",
+            QuestionKind::MissingSpecifics => "missing_specifics",
+            QuestionKind::Vague => "vague",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "contradiction" => Some(QuestionKind::Contradiction),
+            "assumption" => Some(QuestionKind::Assumption),
+            "missing_specifics" => Some(QuestionKind::MissingSpecifics),
+            "vague" => Some(QuestionKind::Vague),
+            _ => None,
+        }
+    }
+
+    /// Tie-break between questions of equal priority: contradictions first
+    pub fn rank(&self) -> u8 {
+        match self {
+            QuestionKind::Contradiction => 0,
+            QuestionKind::Assumption => 1,
+            QuestionKind::MissingSpecifics => 2,
+            QuestionKind::Vague => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionStatus {
+    Open,
+    Answered,
+    Dismissed,
+}
+
+impl QuestionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuestionStatus::Open => "open",
+            QuestionStatus::Answered => "answered",
+            QuestionStatus::Dismissed => "dismissed",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(QuestionStatus::Open),
+            "answered" => Some(QuestionStatus::Answered),
+            "dismissed" => Some(QuestionStatus::Dismissed),
+            _ => None,
+        }
+    }
+}
+
+/// A question as proposed by the model, before it is stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewQuestion {
+    pub kind: QuestionKind,
+    pub question: String,
+    /// The statement being questioned, as written in the document or said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+    /// 1 (must be resolved) to 3 (nice to know)
+    #[serde(default = "default_priority")]
+    pub priority: u8,
+}
+
+fn default_priority() -> u8 {
+    2
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocraticQuestion {
+    pub id: String,
+    pub recording_id: String,
+    pub kind: QuestionKind,
+    pub question: String,
+    pub quote: Option<String>,
+    pub priority: u8,
+    pub status: QuestionStatus,
+    /// Turn whose edit raised the question
+    pub asked_turn: Option<String>,
+    /// Turn that answered it
+    pub answered_turn: Option<String>,
+    /// What the user said that answered it
+    pub answer: Option<String>,
+    pub created_at: i64,
+}
+
+/// Most important first: by priority, then by kind, then oldest first
+pub fn prioritize(questions: &mut [SocraticQuestion]) {
+    questions.sort_by_key(|q| (q.priority, q.kind.rank(), q.created_at));
+}
+
+pub struct QuestionStore {
+    db: Mutex<Connection>,
+}
+
+impl QuestionStore {
+    pub fn new(db_path: &str) -> Result<Self> {
+        let conn = Connection::open(db_path)
+            .context("Failed to open SQLite database")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS socratic_questions (
+                recording_id TEXT NOT NULL,
+                seq INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                question TEXT NOT NULL,
+                quote TEXT,
+                priority INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                asked_turn TEXT,
+                answered_turn TEXT,
+                answer TEXT,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (recording_id, seq)
+            )",
+            [],
+        )?;
+
+        Ok(Self { db: Mutex::new(conn) })
+    }
+
+    /// Store new questions for a recording and return them with their ids
+    pub fn ask(
+        &self,
+        recording_id: &str,
+        turn_id: Option<&str>,
+        questions: &[NewQuestion],
+    ) -> Result<Vec<SocraticQuestion>> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let last: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(seq), 0) FROM socratic_questions WHERE recording_id = ?1",
+            [recording_id],
+            |row| row.get(0),
+        )?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let mut stored = Vec::with_capacity(questions.len());
+        for (i, q) in questions.iter().enumerate() {
+            let seq = last + 1 + i as i64;
+            tx.execute(
+                "INSERT INTO socratic_questions
+                    (recording_id, seq, kind, question, quote, priority, status, asked_turn, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![
+                    recording_id,
+                    seq,
+                    q.kind.as_str(),
+                    q.question,
+                    q.quote,
+                    q.priority,
+                    QuestionStatus::Open.as_str(),
+                    turn_id,
+                    now,
+                ],
+            )?;
+            stored.push(SocraticQuestion {
+                id: format!("q-{}", seq),
+                recording_id: recording_id.to_string(),
+                kind: q.kind,
+                question: q.question.clone(),
+                quote: q.quote.clone(),
+                priority: q.priority,
+                status: QuestionStatus::Open,
+                asked_turn: turn_id.map(str::to_string),
+                answered_turn: None,
+                answer: None,
+                created_at: now,
+            });
+        }
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    /// Mark open questions as answered; returns how many were still open
+    pub fn answer(
+        &self,
+        recording_id: &str,
+        ids: &[String],
+        turn_id: Option<&str>,
+        answer: Option<&str>,
+    ) -> Result<usize> {
+        self.close(recording_id, ids, QuestionStatus::Answered, turn_id, answer)
+    }
+
+    pub fn dismiss(&self, recording_id: &str, id: &str) -> Result<bool> {
+        Ok(self.close(recording_id, &[id.to_string()], QuestionStatus::Dismissed, None, None)? > 0)
+    }
+
+    fn close(
+        &self,
+        recording_id: &str,
+        ids: &[String],
+        status: QuestionStatus,
+        turn_id: Option<&str>,
+        answer: Option<&str>,
+    ) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+        let mut closed = 0;
+        for seq in ids.iter().filter_map(|id| parse_id(id)) {
+            closed += db.execute(
+                "UPDATE socratic_questions SET status = ?1, answered_turn = ?2, answer = ?3
+                 WHERE recording_id = ?4 AND seq = ?5 AND status = ?6",
+                rusqlite::params![
+                    status.as_str(),
+                    turn_id,
+                    answer,
+                    recording_id,
+                    seq,
+                    QuestionStatus::Open.as_str(),
+                ],
+            )?;
+        }
+        Ok(closed)
+    }
+
+    /// Unanswered questions, most important first
+    pub fn open(&self, recording_id: &str) -> Result<Vec<SocraticQuestion>> {
+        let mut questions: Vec<_> = self.list(recording_id)?
+            .into_iter()
+            .filter(|q| q.status == QuestionStatus::Open)
+            .collect();
+        prioritize(&mut questions);
+        Ok(questions)
+    }
+
+    /// Every question of a recording, in the order they were asked
+    pub fn list(&self, recording_id: &str) -> Result<Vec<SocraticQuestion>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT seq, kind, question, quote, priority, status, asked_turn, answered_turn, answer, created_at
+             FROM socratic_questions WHERE recording_id = ?1 ORDER BY seq",
+        )?;
+        let rows = stmt.query_map([recording_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, u8>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+                row.get::<_, Option<String>>(8)?,
+                row.get::<_, i64>(9)?,
+            ))
+        })?;
+
+        let mut questions = Vec::new();
+        for row in rows {
+            let (seq, kind, question, quote, priority, status, asked_turn, answered_turn, answer, created_at) = row?;
+            questions.push(SocraticQuestion {
+                id: format!("q-{}", seq),
+                recording_id: recording_id.to_string(),
+                kind: QuestionKind::parse(&kind).with_context(|| format!("Unknown question kind {}", kind))?,
+                question,
+                quote,
+                priority,
+                status: QuestionStatus::parse(&status).with_context(|| format!("Unknown question status {}", status))?,
+                asked_turn,
+                answered_turn,
+                answer,
+                created_at,
+            });
+        }
+        Ok(questions)
+    }
+}
+
+fn parse_id(id: &str) -> Option<i64> {
+    id.trim().strip_prefix("q-")?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_question(kind: QuestionKind, question: &str, priority: u8) -> NewQuestion {
+        NewQuestion { kind, question: question.to_string(), quote: None, priority }
+    }
+
+    #[test]
+    fn test_questions_are_numbered_per_recording_and_closed_once() {
+        let store = QuestionStore::new(":memory:").unwrap();
+        let asked = store.ask("rec-1", Some("t1"), &[
+            new_question(QuestionKind::Vague, "Which users?", 2),
+            new_question(QuestionKind::Contradiction, "Q3 or Q4?", 2),
+            new_question(QuestionKind::MissingSpecifics, "Who owns the launch?", 1),
+        ]).unwrap();
+        assert_eq!(asked.iter().map(|q| q.id.as_str()).collect::<Vec<_>>(), vec!["q-1", "q-2", "q-3"]);
+        assert_eq!(store.ask("rec-2", None, &[new_question(QuestionKind::Vague, "Why?", 3)]).unwrap()[0].id, "q-1");
+
+        // Priority first, then contradictions before vague claims
+        let open = store.open("rec-1").unwrap();
+        assert_eq!(open.iter().map(|q| q.id.as_str()).collect::<Vec<_>>(), vec!["q-3", "q-2", "q-1"]);
+
+        let ids = vec!["q-3".to_string(), "q-9".to_string()];
+        assert_eq!(store.answer("rec-1", &ids, Some("t2"), Some("Dana owns it")).unwrap(), 1);
+        assert_eq!(store.answer("rec-1", &ids, Some("t3"), None).unwrap(), 0);
+        assert!(store.dismiss("rec-1", "q-1").unwrap());
+        assert!(!store.dismiss("rec-1", "q-1").unwrap());
+
+        assert_eq!(store.open("rec-1").unwrap().iter().map(|q| q.id.as_str()).collect::<Vec<_>>(), vec!["q-2"]);
+        let all = store.list("rec-1").unwrap();
+        assert_eq!(all[2].status, QuestionStatus::Answered);
+        assert_eq!(all[2].answered_turn.as_deref(), Some("t2"));
+        assert_eq!(all[2].answer.as_deref(), Some("Dana owns it"));
+        assert_eq!(all[0].status, QuestionStatus::Dismissed);
+        assert_eq!(store.ask("rec-1", None, &[new_question(QuestionKind::Vague, "How?", 2)]).unwrap()[0].id, "q-4");
+    }
+}
@@ -53,6 +53,7 @@
 
 use serde::{Deserialize, Serialize};
 use rusqlite::Connection;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use anyhow::{Result, Context};
 use crate::services::llm_client::{LLMClient, ChatMessage};
@@ -97,6 +98,8 @@ pub struct StateManager {
     db: Arc<Mutex<Connection>>,
     current_recording_id: Arc<Mutex<Option<String>>>,
     current_state: Arc<Mutex<DocumentState>>,
+    /// Highest `todo-N` handed out for the current recording; ids are never reused
+    todo_seq: AtomicU64,
 }
 
 impl StateManager {
@@ -109,6 +112,7 @@ impl StateManager {
             db: Arc::new(Mutex::new(conn)),
             current_recording_id: Arc::new(Mutex::new(None)),
             current_state: Arc::new(Mutex::new(DocumentState::default())),
+            todo_seq: AtomicU64::new(0),
         };
         
         manager.init_db()?;
@@ -147,6 +151,9 @@ impl StateManager {
         let mut current = self.current_recording_id.lock().unwrap();
         *current = Some(recording_id.clone());
         
+        // Todo ids continue from the loaded list
+        self.todo_seq.store(0, Ordering::Relaxed);
+
         // Try to load existing state
         if let Ok(state) = self.load_state(&recording_id) {
             let mut current_state = self.current_state.lock().unwrap();
@@ -190,6 +197,7 @@ impl StateManager {
             if current == recording_id {
                 let mut current_state = self.current_state.lock().unwrap();
                 *current_state = DocumentState::default();
+                self.todo_seq.store(0, Ordering::Relaxed);
             }
         }
         
@@ -228,7 +236,7 @@ impl StateManager {
     }
     
     /// Generate focus description using LLM and update state
-    pub async fn generate_and_update_focus<T: LLMClient>(&self, llm: &T, content: &str) -> Result<String> {
+    pub async fn generate_and_update_focus<T: LLMClient + ?Sized>(&self, llm: &T, content: &str) -> Result<String> {
         // Skip if content is empty
         if content.trim().is_empty() {
             return Ok("Empty document".to_string());
@@ -242,12 +250,9 @@ impl StateManager {
             }
         ];
         
-        // Call LLM with timeout and fallback
-        let focus = match tokio::time::timeout(
-            std::time::Duration::from_secs(10),
-            self.collect_llm_response(llm, messages)
-        ).await {
-            Ok(Ok(response)) => {
+        // Call LLM with fallback (deadline comes from the client's role settings)
+        let focus = match self.collect_llm_response(llm, messages).await {
+            Ok(response) => {
                 let cleaned = clean_concise_output(&response);
                 if cleaned.is_empty() || cleaned.len() > 150 {
                     "Document updated".to_string()
@@ -268,7 +273,7 @@ impl StateManager {
     }
     
     /// Helper to collect full LLM response from stream
-    async fn collect_llm_response<T: LLMClient>(&self, llm: &T, messages: Vec<ChatMessage>) -> Result<String> {
+    async fn collect_llm_response<T: LLMClient + ?Sized>(&self, llm: &T, messages: Vec<ChatMessage>) -> Result<String> {
         let mut stream = llm.stream_completion(messages).await
             .map_err(|e| anyhow::anyhow!("LLM error: {:?}", e))?;
         
@@ -306,9 +311,19 @@ impl StateManager {
         Ok(())
     }
     
-    /// Add a todo item
-    pub fn add_todo(&self, id: String, desc: String) {
+    /// Add a todo item and return its id, `todo-N`. Ids go into edit prompts,
+    /// so they stay short and reproducible across runs; N is allocated under
+    /// the same lock as the insert and only ever grows, so concurrent adds
+    /// and deletes never hand out an id twice.
+    pub fn add_todo(&self, desc: String) -> String {
         let mut state = self.current_state.lock().unwrap();
+        let highest = state.todo_list.iter()
+            .filter_map(|t| t.id.strip_prefix("todo-")?.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            .max(self.todo_seq.load(Ordering::Relaxed));
+        self.todo_seq.store(highest + 1, Ordering::Relaxed);
+        let id = format!("todo-{}", highest + 1);
         state.todo_list.push(TodoItem {
             id: id.clone(),
             desc: desc.clone(),
@@ -316,6 +331,7 @@ impl StateManager {
             completed_turns_ago: None,
         });
         info!("[State Manager] Todo Added: {} (id: {})", desc, id);
+        id
     }
     
     /// Update a todo item's description
@@ -504,3 +520,30 @@ impl StateManager {
         Ok(state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_todo_ids_are_never_reused() {
+        let manager = Arc::new(StateManager::new(":memory:").unwrap());
+        assert_eq!(manager.add_todo("Draft intro".into()), "todo-1");
+        assert_eq!(manager.add_todo("Check numbers".into()), "todo-2");
+        manager.delete_todo("todo-2").unwrap();
+        assert_eq!(manager.add_todo("Add budget".into()), "todo-3");
+
+        // Background todo agent and the user adding at the same time
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let manager = manager.clone();
+                std::thread::spawn(move || manager.add_todo(format!("todo {}", i)))
+            })
+            .collect();
+        let mut ids: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 8);
+        assert_eq!(manager.get_todos().len(), 10);
+    }
+}
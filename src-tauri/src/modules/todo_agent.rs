@@ -4,12 +4,16 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use crate::services::llm_client::{LLMClient, ChatMessage};
+use crate::services::llm_client::{LLMClient, LLMError, ChatMessage, UsageSink};
+use crate::services::llm_tools::{ToolChoice, ToolDefinition};
 use crate::modules::TodoItem;
 use crate::prompts::todo_agent::build_todo_maintenance_prompt;
 use futures_util::StreamExt;
+use serde_json::json;
 use log::{info, warn, error};
 
+const UPDATE_TODOS_TOOL: &str = "update_todos";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "action", rename_all = "lowercase")]
 pub enum TodoOperation {
@@ -34,9 +38,36 @@ impl TodoAgent {
     pub fn new() -> Self {
         Self
     }
+
+    /// Tool schema mirroring `TodoOperations`
+    fn update_todos_tool() -> ToolDefinition {
+        ToolDefinition::new(
+            UPDATE_TODOS_TOOL,
+            "Apply operations to the todo list. Pass an empty list when nothing needs to change.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "operations": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "action": { "type": "string", "enum": ["complete", "update", "delete", "add"] },
+                                "todo_id": { "type": "string", "description": "Existing todo id (complete/update/delete)" },
+                                "new_desc": { "type": "string", "description": "Replacement description (update)" },
+                                "desc": { "type": "string", "description": "Description of the new todo (add)" }
+                            },
+                            "required": ["action"]
+                        }
+                    }
+                },
+                "required": ["operations"]
+            }),
+        )
+    }
     
     /// Maintain todos using LLM
-    pub async fn maintain_todos<T: LLMClient>(
+    pub async fn maintain_todos<T: LLMClient + ?Sized>(
         &self,
         llm: &T,
         current_doc: &str,
@@ -65,20 +96,34 @@ impl TodoAgent {
             }
         ];
         
-        // Call LLM with timeout
-        let response = match tokio::time::timeout(
-            std::time::Duration::from_secs(15),
-            self.collect_llm_response(llm, messages)
-        ).await {
-            Ok(Ok(resp)) => {
+        // Prefer native tool calling: arguments are schema-checked by the provider
+        let result = llm.complete_with_tools(
+            messages.clone(),
+            vec![Self::update_todos_tool()],
+            ToolChoice::Tool(UPDATE_TODOS_TOOL.to_string()),
+            UsageSink::none(),
+        ).await;
+        match result.and_then(|completion| completion.arguments_for::<TodoOperations>(UPDATE_TODOS_TOOL)) {
+            Ok(ops) => {
+                if !ops.operations.is_empty() {
+                    info!("[Todo Agent] {} operations via tool call", ops.operations.len());
+                }
+                return Ok(ops.operations);
+            }
+            Err(LLMError::Unsupported(_)) => {}
+            // Provider rejected the tools field
+            Err(LLMError::InvalidRequest(e)) => warn!("[Todo Agent] Tool call rejected, falling back to text: {}", e),
+            // A struggling provider would only be asked twice
+            Err(e) => return Err(anyhow::anyhow!("Todo tool call failed: {}", e)),
+        }
+
+        // Call LLM (deadline comes from the client's role settings)
+        let response = match self.collect_llm_response(llm, messages).await {
+            Ok(resp) => {
                 info!("[Todo Agent] LLM response received");
                 resp
             },
-            Err(_) => {
-                warn!("[Todo Agent] LLM timeout");
-                return Ok(vec![]);
-            },
-            Ok(Err(e)) => {
+            Err(e) => {
                 error!("[Todo Agent] LLM error: {:?}", e);
                 return Ok(vec![]);
             }
@@ -118,7 +163,7 @@ impl TodoAgent {
     }
     
     /// Helper to collect full LLM response from stream
-    async fn collect_llm_response<T: LLMClient>(
+    async fn collect_llm_response<T: LLMClient + ?Sized>(
         &self,
         llm: &T,
         messages: Vec<ChatMessage>,
@@ -143,3 +188,96 @@ impl Default for TodoAgent {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::services::llm_cassette::testing::{reload, ScriptedClient};
+    use crate::services::llm_cassette::{Cassette, CassetteClient};
+    use std::pin::Pin;
+    use crate::services::llm_tools::ToolCompletion;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Times out on every call
+    #[derive(Default)]
+    struct TimingOut {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl LLMClient for TimingOut {
+        async fn stream_completion(
+            &self,
+            _messages: Vec<ChatMessage>,
+        ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(LLMError::Timeout)
+        }
+
+        async fn complete_with_tools(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Vec<ToolDefinition>,
+            _tool_choice: ToolChoice,
+            _usage: UsageSink,
+        ) -> Result<ToolCompletion, LLMError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(LLMError::Timeout)
+        }
+    }
+
+    fn todo(id: &str, desc: &str) -> TodoItem {
+        TodoItem { id: id.to_string(), desc: desc.to_string(), completed: false, completed_turns_ago: None }
+    }
+
+    async fn maintain(llm: &CassetteClient, todos: &[TodoItem]) -> Vec<TodoOperation> {
+        TodoAgent::new()
+            .maintain_todos(llm, "# Launch plan\n\n## Risks\n- Churn\n", todos, "Churn is covered now", "+## Risks")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_and_text_fallback_replay_from_cassette() {
+        let todos = vec![todo("todo-1", "List the risks")];
+
+        // Provider with native tool calling
+        let cassette = Arc::new(Cassette::new());
+        let provider = Arc::new(ScriptedClient::tool_call(UPDATE_TODOS_TOOL, json!({
+            "operations": [{ "action": "complete", "todo_id": "todo-1" }]
+        })));
+        maintain(&CassetteClient::record("todo", provider, cassette.clone()), &todos).await;
+        let llm = CassetteClient::replay("todo", reload(&cassette));
+        let ops = maintain(&llm, &todos).await;
+        assert!(matches!(ops.as_slice(), [TodoOperation::Complete { todo_id }] if todo_id == "todo-1"));
+
+        // Provider without it: the refusal is recorded, so replay takes the text path too
+        let cassette = Arc::new(Cassette::new());
+        let provider = Arc::new(ScriptedClient::text(r#"```json
+{"operations": [{"action": "add", "desc": "Price the tiers"}]}
+```"#));
+        maintain(&CassetteClient::record("todo", provider, cassette.clone()), &todos).await;
+        let cassette = reload(&cassette);
+        let llm = CassetteClient::replay("todo", cassette.clone());
+        let ops = maintain(&llm, &todos).await;
+        assert!(matches!(ops.as_slice(), [TodoOperation::Add { desc }] if desc == "Price the tiers"));
+        assert!(cassette.unmatched().is_empty());
+
+        // A request the cassette never saw fails instead of taking the text path
+        let miss = TodoAgent::new().maintain_todos(&llm, "# Other plan\n", &todos, "Something new", "").await;
+        assert!(miss.is_err());
+        assert_eq!(cassette.unmatched().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_timeout_is_not_retried_as_text() {
+        let llm = TimingOut::default();
+        let result = TodoAgent::new()
+            .maintain_todos(&llm, "# Launch plan\n", &[todo("todo-1", "List the risks")], "Add risks", "")
+            .await;
+        assert!(result.is_err());
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,361 @@
+// Turn Trace Module
+//
+// One structured record per processed turn: the transcript, what the three
+// routers decided, what RAG was asked and returned, every plan step with the
+// agent that ran it, its raw LLM responses and whether their patches applied,
+// the commit it ended in and how long each stage took. Records are written to
+// the `turn_traces` table in the state DB, keyed by the turn id that usage is
+// attributed to, so a bad edit can be traced back to the decision behind it.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::modules::intent_router::{PlanStep, RagNeed, RoutingDecision};
+use crate::modules::{SearchResult, ToolIntent};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouterTrace {
+    pub plan: Vec<PlanStep>,
+    pub rag: RagNeed,
+    pub tool: ToolIntent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RagTrace {
+    pub query: String,
+    pub hits: Vec<RagHit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RagHit {
+    pub source: String,
+    pub score: f32,
+    pub content: String,
+}
+
+/// What became of one LLM response when applied to the document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum PatchOutcome {
+    Applied,
+    /// Applied cleanly but left the document as it was
+    Unchanged,
+    Rejected { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttemptTrace {
+    /// Raw model output, before tag cleanup
+    pub response: String,
+    /// None until (or unless) the response was applied
+    pub patch: Option<PatchOutcome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepTrace {
+    pub intent: String,
+    pub instruction: String,
+    pub agent: String,
+    /// First attempt followed by its retries
+    pub attempts: Vec<AttemptTrace>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+impl StepTrace {
+    pub fn retries(&self) -> usize {
+        self.attempts.len().saturating_sub(1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitTrace {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnTrace {
+    pub turn_id: String,
+    pub recording_id: Option<String>,
+    /// Unix millis
+    pub started_at: i64,
+    pub transcript: String,
+    /// None if the turn ended before routing
+    pub router: Option<RouterTrace>,
+    /// None when Router 2 saw no need for retrieval
+    pub rag: Option<RagTrace>,
+    pub steps: Vec<StepTrace>,
+    /// None if the turn left the document unchanged (or outside a recording)
+    pub commit: Option<CommitTrace>,
+    /// Wall-clock time per stage, in order
+    pub timings: Vec<StageTiming>,
+    pub total_ms: u64,
+    pub error: Option<String>,
+}
+
+/// A list entry: enough to pick a turn without loading every trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnTraceSummary {
+    pub turn_id: String,
+    pub started_at: i64,
+    pub transcript: String,
+    pub intents: Vec<String>,
+    pub total_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Collects the trace of the running turn. Clones share the same record, so
+/// the pipeline and its agents can each add what they know.
+#[derive(Clone)]
+pub struct TraceRecorder {
+    trace: Arc<Mutex<TurnTrace>>,
+    started: std::time::Instant,
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new(String::new(), None, String::new())
+    }
+}
+
+impl TraceRecorder {
+    pub fn new(turn_id: String, recording_id: Option<String>, transcript: String) -> Self {
+        Self {
+            trace: Arc::new(Mutex::new(TurnTrace {
+                turn_id,
+                recording_id,
+                started_at: chrono::Utc::now().timestamp_millis(),
+                transcript,
+                router: None,
+                rag: None,
+                steps: Vec::new(),
+                commit: None,
+                timings: Vec::new(),
+                total_ms: 0,
+                error: None,
+            })),
+            started: std::time::Instant::now(),
+        }
+    }
+
+    pub fn router(&self, decision: &RoutingDecision) {
+        self.trace.lock().unwrap().router = Some(RouterTrace {
+            plan: decision.plan.clone(),
+            rag: decision.rag.clone(),
+            tool: decision.tool.clone(),
+        });
+    }
+
+    pub fn rag_query(&self, query: &str) {
+        self.trace.lock().unwrap().rag = Some(RagTrace { query: query.to_string(), hits: Vec::new() });
+    }
+
+    pub fn rag_hits(&self, results: &[SearchResult]) {
+        if let Some(rag) = self.trace.lock().unwrap().rag.as_mut() {
+            rag.hits = results.iter()
+                .map(|r| RagHit { source: r.source.clone(), score: r.score, content: r.content.clone() })
+                .collect();
+        }
+    }
+
+    pub fn stage(&self, stage: &str, elapsed: Duration) {
+        self.trace.lock().unwrap().timings.push(StageTiming {
+            stage: stage.to_string(),
+            ms: elapsed.as_millis() as u64,
+        });
+    }
+
+    pub fn start_step(&self, step: &PlanStep, agent: &str) {
+        self.trace.lock().unwrap().steps.push(StepTrace {
+            intent: step.intent.clone(),
+            instruction: step.instruction.clone(),
+            agent: agent.to_string(),
+            attempts: Vec::new(),
+            duration_ms: 0,
+            error: None,
+        });
+    }
+
+    /// A model response of the current step (one per attempt)
+    pub fn llm_response(&self, response: &str) {
+        if let Some(step) = self.trace.lock().unwrap().steps.last_mut() {
+            step.attempts.push(AttemptTrace { response: response.to_string(), patch: None });
+        }
+    }
+
+    /// How the current step's latest response applied
+    pub fn patch_result(&self, outcome: PatchOutcome) {
+        if let Some(attempt) = self.trace.lock().unwrap().steps.last_mut().and_then(|s| s.attempts.last_mut()) {
+            attempt.patch = Some(outcome);
+        }
+    }
+
+    pub fn end_step(&self, elapsed: Duration, error: Option<String>) {
+        if let Some(step) = self.trace.lock().unwrap().steps.last_mut() {
+            step.duration_ms = elapsed.as_millis() as u64;
+            step.error = error;
+        }
+    }
+
+    pub fn commit(&self, id: String, message: &str) {
+        self.trace.lock().unwrap().commit = Some(CommitTrace { id, message: message.to_string() });
+    }
+
+    /// Close the record and return it for storage
+    pub fn finish(&self, error: Option<String>) -> TurnTrace {
+        let mut trace = self.trace.lock().unwrap();
+        trace.total_ms = self.started.elapsed().as_millis() as u64;
+        trace.error = error;
+        trace.clone()
+    }
+}
+
+pub struct TraceStore {
+    db: Mutex<Connection>,
+}
+
+impl TraceStore {
+    pub fn new(db_path: &str) -> Result<Self> {
+        let conn = Connection::open(db_path)
+            .context("Failed to open SQLite database")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS turn_traces (
+                turn_id TEXT PRIMARY KEY,
+                recording_id TEXT,
+                started_at INTEGER NOT NULL,
+                trace TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_turn_traces_recording ON turn_traces (recording_id, started_at)",
+            [],
+        )?;
+
+        Ok(Self { db: Mutex::new(conn) })
+    }
+
+    pub fn save(&self, trace: &TurnTrace) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT OR REPLACE INTO turn_traces (turn_id, recording_id, started_at, trace)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                trace.turn_id,
+                trace.recording_id,
+                trace.started_at,
+                serde_json::to_string(trace)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, turn_id: &str) -> Result<Option<TurnTrace>> {
+        let db = self.db.lock().unwrap();
+        let json: Option<String> = db
+            .query_row("SELECT trace FROM turn_traces WHERE turn_id = ?1", [turn_id], |row| row.get(0))
+            .optional()?;
+        json.map(|json| serde_json::from_str(&json).context("Corrupt turn trace"))
+            .transpose()
+    }
+
+    /// Turns of a recording, oldest first
+    pub fn list(&self, recording_id: &str) -> Result<Vec<TurnTraceSummary>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT trace FROM turn_traces WHERE recording_id = ?1 ORDER BY started_at, rowid",
+        )?;
+        let rows = stmt.query_map([recording_id], |row| row.get::<_, String>(0))?;
+
+        let mut summaries = Vec::new();
+        for json in rows {
+            let trace: TurnTrace = serde_json::from_str(&json?).context("Corrupt turn trace")?;
+            summaries.push(TurnTraceSummary {
+                intents: trace.steps.iter().map(|s| s.intent.clone()).collect(),
+                turn_id: trace.turn_id,
+                started_at: trace.started_at,
+                transcript: trace.transcript,
+                total_ms: trace.total_ms,
+                error: trace.error,
+            });
+        }
+        Ok(summaries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(intent: &str) -> PlanStep {
+        PlanStep { intent: intent.to_string(), instruction: format!("do {}", intent) }
+    }
+
+    #[test]
+    fn test_recorder_tracks_attempts_per_step() {
+        let trace = TraceRecorder::new("t1".into(), Some("rec-1".into()), "Fix the title".into());
+        trace.router(&RoutingDecision {
+            plan: vec![step("EDIT"), step("APPEND")],
+            rag: RagNeed { needed: true, reason: "refers back".into() },
+            tool: ToolIntent::None,
+        });
+        trace.rag_query("title");
+        trace.rag_hits(&[SearchResult { content: "old title".into(), source: "conversation".into(), timestamp: 1, score: 0.8 }]);
+
+        trace.start_step(&step("EDIT"), "EditAgent");
+        trace.llm_response("<<<<<<< SEARCH\nmissing");
+        trace.patch_result(PatchOutcome::Rejected { reason: "SEARCH block not found".into() });
+        trace.llm_response("<<<<<<< SEARCH\n# Title");
+        trace.patch_result(PatchOutcome::Applied);
+        trace.end_step(Duration::from_millis(40), None);
+
+        // Clones write to the same record
+        let agent_side = trace.clone();
+        agent_side.start_step(&step("APPEND"), "AppendAgent");
+        agent_side.end_step(Duration::from_millis(5), Some("LLM failed".into()));
+        trace.stage("route", Duration::from_millis(12));
+
+        let done = trace.finish(Some("Step 2 failed".into()));
+        assert_eq!(done.router.as_ref().unwrap().plan.len(), 2);
+        assert_eq!(done.rag.as_ref().unwrap().hits[0].source, "conversation");
+        assert_eq!(done.steps.len(), 2);
+        assert_eq!(done.steps[0].retries(), 1);
+        assert_eq!(done.steps[0].attempts[1].patch, Some(PatchOutcome::Applied));
+        assert_eq!(done.steps[1].error.as_deref(), Some("LLM failed"));
+        assert_eq!(done.timings, vec![StageTiming { stage: "route".into(), ms: 12 }]);
+        assert_eq!(done.error.as_deref(), Some("Step 2 failed"));
+    }
+
+    #[test]
+    fn test_store_round_trips_and_lists_by_recording() {
+        let store = TraceStore::new(":memory:").unwrap();
+        for (turn_id, rec) in [("t1", "rec-1"), ("t2", "rec-1"), ("t3", "rec-2")] {
+            let trace = TraceRecorder::new(turn_id.into(), Some(rec.into()), format!("turn {}", turn_id));
+            trace.start_step(&step("APPEND"), "AppendAgent");
+            trace.llm_response("## Risks");
+            trace.patch_result(PatchOutcome::Applied);
+            trace.commit("abc123".into(), "Add risks");
+            store.save(&trace.finish(None)).unwrap();
+        }
+
+        let full = store.get("t2").unwrap().unwrap();
+        assert_eq!(full.transcript, "turn t2");
+        assert_eq!(full.steps[0].attempts[0].patch, Some(PatchOutcome::Applied));
+        assert_eq!(full.commit.unwrap().id, "abc123");
+        assert!(store.get("missing").unwrap().is_none());
+
+        let listed = store.list("rec-1").unwrap();
+        assert_eq!(listed.iter().map(|t| t.turn_id.as_str()).collect::<Vec<_>>(), vec!["t1", "t2"]);
+        assert_eq!(listed[0].intents, vec!["APPEND"]);
+    }
+}
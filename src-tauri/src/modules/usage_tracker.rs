@@ -0,0 +1,366 @@
+// Usage Tracker Module
+//
+// Token and cost accounting. Every LLM call made for a turn is attributed to
+// the caller that made it (router, an editor agent, todo, focus, ...) and
+// written to the `token_usage` table in the state DB, one row per call, so
+// totals can be rolled up per turn, recording or workspace and survive
+// restarts. Pricing and budgets come from creek.toml (see llm_usage.rs).
+
+use anyhow::{Context, Result};
+use log::warn;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::services::llm_client::{LLMClient, TokenUsage, UsageSink};
+use crate::services::llm_usage::{BudgetAction, BudgetConfig, MeteredClient, ModelPrice};
+
+/// Who made an LLM call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageCaller {
+    Router,
+    AppendAgent,
+    EditAgent,
+    GrepAgent,
+    UndoAgent,
+    RagQuery,
+    AutoNaming,
+    Todo,
+    Focus,
+    CommitMessage,
+    Socratic,
+}
+
+impl UsageCaller {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageCaller::Router => "router",
+            UsageCaller::AppendAgent => "append_agent",
+            UsageCaller::EditAgent => "edit_agent",
+            UsageCaller::GrepAgent => "grep_agent",
+            UsageCaller::UndoAgent => "undo_agent",
+            UsageCaller::RagQuery => "rag_query",
+            UsageCaller::AutoNaming => "auto_naming",
+            UsageCaller::Todo => "todo",
+            UsageCaller::Focus => "focus",
+            UsageCaller::CommitMessage => "commit_message",
+            UsageCaller::Socratic => "socratic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub calls: u64,
+    /// Calls to models without a `[pricing]` entry count as free
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageBreakdown {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub total: UsageTotals,
+    pub by_caller: Vec<UsageBreakdown>,
+    pub by_model: Vec<UsageBreakdown>,
+    /// In turn order
+    pub by_turn: Vec<UsageBreakdown>,
+    pub by_recording: Vec<UsageBreakdown>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetStatus {
+    Ok,
+    Warn(String),
+    Block(String),
+}
+
+pub struct UsageTracker {
+    db: Mutex<Connection>,
+    pricing: RwLock<HashMap<String, ModelPrice>>,
+    budget: RwLock<BudgetConfig>,
+    /// Limits already warned about (warn mode toasts once per limit)
+    warned: Mutex<HashSet<String>>,
+}
+
+impl UsageTracker {
+    pub fn new(db_path: &str) -> Result<Self> {
+        let conn = Connection::open(db_path)
+            .context("Failed to open SQLite database")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS token_usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recording_id TEXT,
+                turn_id TEXT NOT NULL,
+                caller TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                cost_usd REAL,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_token_usage_recording ON token_usage (recording_id)",
+            [],
+        )?;
+
+        Ok(Self {
+            db: Mutex::new(conn),
+            pricing: RwLock::new(HashMap::new()),
+            budget: RwLock::new(BudgetConfig::default()),
+            warned: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Apply pricing and budgets (startup and config hot reload)
+    pub fn configure(&self, pricing: HashMap<String, ModelPrice>, budget: BudgetConfig) {
+        *self.pricing.write().unwrap() = pricing;
+        *self.budget.write().unwrap() = budget;
+        self.warned.lock().unwrap().clear();
+    }
+
+    /// Cost of one completion, if its model has a `[pricing]` entry
+    pub fn cost_of(&self, usage: &TokenUsage) -> Option<f64> {
+        self.pricing.read().unwrap()
+            .get(&usage.model)
+            .map(|price| price.cost(usage))
+    }
+
+    pub fn record(&self, recording_id: Option<&str>, turn_id: &str, caller: UsageCaller, usage: &TokenUsage) -> Result<()> {
+        let cost = self.cost_of(usage);
+
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO token_usage
+                (recording_id, turn_id, caller, model, prompt_tokens, completion_tokens, cost_usd, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                recording_id,
+                turn_id,
+                caller.as_str(),
+                usage.model,
+                usage.prompt_tokens as i64,
+                usage.completion_tokens as i64,
+                cost,
+                chrono::Utc::now().timestamp_millis(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn recording_summary(&self, recording_id: &str) -> Result<UsageSummary> {
+        self.summary(&[recording_id.to_string()])
+    }
+
+    /// Roll up usage over a set of recordings (e.g. all recordings of a workspace)
+    pub fn summary(&self, recording_ids: &[String]) -> Result<UsageSummary> {
+        if recording_ids.is_empty() {
+            return Ok(UsageSummary::default());
+        }
+        let db = self.db.lock().unwrap();
+        let by_recording = Self::group_by(&db, "recording_id", recording_ids)?;
+
+        let mut total = UsageTotals::default();
+        for row in &by_recording {
+            total.prompt_tokens += row.totals.prompt_tokens;
+            total.completion_tokens += row.totals.completion_tokens;
+            total.calls += row.totals.calls;
+            total.cost_usd += row.totals.cost_usd;
+        }
+
+        Ok(UsageSummary {
+            total,
+            by_caller: Self::group_by(&db, "caller", recording_ids)?,
+            by_model: Self::group_by(&db, "model", recording_ids)?,
+            by_turn: Self::group_by(&db, "turn_id", recording_ids)?,
+            by_recording,
+        })
+    }
+
+    fn group_by(db: &Connection, column: &str, recording_ids: &[String]) -> Result<Vec<UsageBreakdown>> {
+        let placeholders = vec!["?"; recording_ids.len()].join(", ");
+        let sql = format!(
+            "SELECT {column}, SUM(prompt_tokens), SUM(completion_tokens), COUNT(*), SUM(COALESCE(cost_usd, 0))
+             FROM token_usage WHERE recording_id IN ({placeholders})
+             GROUP BY {column} ORDER BY MIN(created_at)"
+        );
+        let mut stmt = db.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(recording_ids), |row| {
+            Ok(UsageBreakdown {
+                key: row.get(0)?,
+                totals: UsageTotals {
+                    prompt_tokens: row.get::<_, i64>(1)? as u64,
+                    completion_tokens: row.get::<_, i64>(2)? as u64,
+                    calls: row.get::<_, i64>(3)? as u64,
+                    cost_usd: row.get(4)?,
+                },
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Check the configured limits for a recording and its workspace before a turn runs
+    pub fn check_budget(&self, recording_id: &str, workspace_recordings: &[String]) -> Result<BudgetStatus> {
+        let budget = self.budget.read().unwrap().clone();
+        let mut exceeded = Vec::new();
+
+        if budget.recording_tokens.is_some() || budget.recording_cost.is_some() {
+            let total = self.recording_summary(recording_id)?.total;
+            exceeded.extend(over_limit("Recording", &total, budget.recording_tokens, budget.recording_cost));
+        }
+        if budget.workspace_tokens.is_some() || budget.workspace_cost.is_some() {
+            let total = self.summary(workspace_recordings)?.total;
+            exceeded.extend(over_limit("Workspace", &total, budget.workspace_tokens, budget.workspace_cost));
+        }
+
+        let Some(message) = exceeded.into_iter().next() else {
+            return Ok(BudgetStatus::Ok);
+        };
+        Ok(match budget.action {
+            BudgetAction::Block => BudgetStatus::Block(message),
+            BudgetAction::Warn => {
+                let key = format!("{}:{}", recording_id, message.split(':').next().unwrap_or_default());
+                if self.warned.lock().unwrap().insert(key) {
+                    BudgetStatus::Warn(message)
+                } else {
+                    BudgetStatus::Ok
+                }
+            }
+        })
+    }
+}
+
+fn over_limit(scope: &str, total: &UsageTotals, max_tokens: Option<u64>, max_cost: Option<f64>) -> Option<String> {
+    if let Some(max) = max_tokens.filter(|max| total.total_tokens() >= *max) {
+        return Some(format!("{} token budget exceeded: {} / {}", scope, total.total_tokens(), max));
+    }
+    if let Some(max) = max_cost.filter(|max| total.cost_usd >= *max) {
+        return Some(format!("{} cost budget exceeded: ${:.2} / ${:.2}", scope, total.cost_usd, max));
+    }
+    None
+}
+
+/// Recording IDs under a workspace's recordings directory
+pub fn recording_ids_in(recordings_dir: &Path) -> Vec<String> {
+    std::fs::read_dir(recordings_dir)
+        .map(|entries| {
+            entries.flatten()
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| e.file_name().to_str().map(str::to_string))
+                .filter(|name| !name.starts_with('.'))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Attribution for the LLM calls of one turn
+#[derive(Clone)]
+pub struct UsageScope {
+    tracker: Arc<UsageTracker>,
+    recording_id: Option<String>,
+    turn_id: String,
+}
+
+impl UsageScope {
+    pub fn new(tracker: Arc<UsageTracker>, recording_id: Option<String>) -> Self {
+        Self {
+            tracker,
+            recording_id,
+            turn_id: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Attribute to an existing turn (the turn queue's id) instead of a fresh one
+    pub fn with_turn_id(mut self, turn_id: impl Into<String>) -> Self {
+        self.turn_id = turn_id.into();
+        self
+    }
+
+    pub fn turn_id(&self) -> &str {
+        &self.turn_id
+    }
+
+    pub fn tracker(&self) -> &Arc<UsageTracker> {
+        &self.tracker
+    }
+
+    /// Wrap `client` so its calls are recorded against this turn and `caller`
+    pub fn meter(&self, client: Arc<dyn LLMClient>, caller: UsageCaller) -> Arc<dyn LLMClient> {
+        let scope = self.clone();
+        Arc::new(MeteredClient::new(client, UsageSink::new(move |usage| {
+            if let Err(e) = scope.tracker.record(scope.recording_id.as_deref(), &scope.turn_id, caller, &usage) {
+                warn!("[Usage Tracker] Failed to record usage: {:?}", e);
+            }
+        })).with_caller(caller.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(model: &str, prompt: u64, completion: u64) -> TokenUsage {
+        TokenUsage { model: model.to_string(), prompt_tokens: prompt, completion_tokens: completion }
+    }
+
+    #[test]
+    fn test_summary_rolls_up_by_caller_and_turn() {
+        let tracker = UsageTracker::new(":memory:").unwrap();
+        let mut pricing = HashMap::new();
+        pricing.insert("qwen-flash".to_string(), ModelPrice { input_per_mtok: 1.0, output_per_mtok: 2.0 });
+        tracker.configure(pricing, BudgetConfig::default());
+
+        tracker.record(Some("rec-1"), "t1", UsageCaller::Router, &usage("qwen-flash", 1_000, 10)).unwrap();
+        tracker.record(Some("rec-1"), "t1", UsageCaller::EditAgent, &usage("qwen3-coder-flash", 2_000, 500)).unwrap();
+        tracker.record(Some("rec-1"), "t2", UsageCaller::Router, &usage("qwen-flash", 1_000, 10)).unwrap();
+        tracker.record(Some("rec-2"), "t3", UsageCaller::Todo, &usage("qwen-flash", 5, 5)).unwrap();
+
+        let summary = tracker.recording_summary("rec-1").unwrap();
+        assert_eq!(summary.total.calls, 3);
+        assert_eq!(summary.total.prompt_tokens, 4_000);
+        assert_eq!(summary.by_turn.iter().map(|t| t.key.as_str()).collect::<Vec<_>>(), vec!["t1", "t2"]);
+        let router = summary.by_caller.iter().find(|c| c.key == "router").unwrap();
+        assert_eq!(router.totals.calls, 2);
+        // Only the priced model contributes cost
+        assert!((summary.total.cost_usd - 2.0 * (1_000.0 + 20.0) / 1_000_000.0).abs() < 1e-12);
+
+        let workspace = tracker.summary(&["rec-1".to_string(), "rec-2".to_string()]).unwrap();
+        assert_eq!(workspace.by_recording.len(), 2);
+    }
+
+    #[test]
+    fn test_budget_warns_once_and_blocks() {
+        let tracker = UsageTracker::new(":memory:").unwrap();
+        tracker.record(Some("rec-1"), "t1", UsageCaller::EditAgent, &usage("m", 900, 200)).unwrap();
+
+        tracker.configure(HashMap::new(), BudgetConfig { recording_tokens: Some(1_000), ..Default::default() });
+        assert!(matches!(tracker.check_budget("rec-1", &[]).unwrap(), BudgetStatus::Warn(_)));
+        assert_eq!(tracker.check_budget("rec-1", &[]).unwrap(), BudgetStatus::Ok);
+
+        tracker.configure(HashMap::new(), BudgetConfig {
+            recording_tokens: Some(1_000),
+            action: BudgetAction::Block,
+            ..Default::default()
+        });
+        assert!(matches!(tracker.check_budget("rec-1", &[]).unwrap(), BudgetStatus::Block(_)));
+        assert_eq!(tracker.check_budget("rec-2", &[]).unwrap(), BudgetStatus::Ok);
+    }
+}
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use tauri::AppHandle;
 use tauri::Manager;
 use chrono::Utc;
+use crate::services::asr::{AsrSessionSettings, AudioArchiveSettings};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workspace {
@@ -11,6 +12,22 @@ pub struct Workspace {
     pub name: String,
     pub created_at: i64,
     pub path: PathBuf,
+    #[serde(default)]
+    pub settings: WorkspaceSettings,
+}
+
+/// Per-workspace preferences, persisted with the workspace list
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSettings {
+    /// Input device id (see `list_input_devices`); None = system default
+    #[serde(default)]
+    pub input_device: Option<String>,
+    /// Session audio archive and its retention limits
+    #[serde(default)]
+    pub audio_archive: AudioArchiveSettings,
+    /// Recognition language, server VAD and hotwords
+    #[serde(default)]
+    pub asr: AsrSessionSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,6 +115,7 @@ impl WorkspaceManager {
             name: if name.is_empty() { "New Workspace".to_string() } else { name },
             created_at: Utc::now().timestamp_millis(),
             path: workspace_dir,
+            settings: WorkspaceSettings::default(),
         };
         
         config.workspaces.push(workspace.clone());
@@ -183,6 +201,27 @@ impl WorkspaceManager {
         self.save_config(&config)
     }
 
+    /// Apply `update` to a workspace's settings and persist them
+    pub fn update_settings(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut WorkspaceSettings),
+    ) -> Result<WorkspaceSettings, String> {
+        let mut config = self.load_config()?;
+
+        let workspace = config
+            .workspaces
+            .iter_mut()
+            .find(|w| w.id == id)
+            .ok_or_else(|| "Workspace not found".to_string())?;
+
+        update(&mut workspace.settings);
+        let settings = workspace.settings.clone();
+
+        self.save_config(&config)?;
+        Ok(settings)
+    }
+
     pub fn initialize_default_workspace(&self) -> Result<(), String> {
         // Just ensure the config directory exists - don't auto-create workspaces
         let _ = self.get_config_dir()?;
@@ -1,6 +1,9 @@
 // Intent Router Prompts
 //
 // Prompts for the three parallel intent routers (0.6B models)
+//
+// Each router prompt is its rules plus an output format: the legacy text
+// format, or a JSON object matching the schema in modules::intent_router.
 
 // ============================================================================
 // Router 1: Document Layer Intent Classification
@@ -51,7 +54,9 @@ pub const DOC_INTENT_ROUTER_PROMPT: &str = r#"You are an intent classifier that
    - Batch replace (contains "all"/"every"/"unify"/"batch")? → GREP
    - Modify existing content? → EDIT
 4. Default → EDIT
+"#;
 
+pub const DOC_INTENT_TEXT_FORMAT: &str = r#"
 **Output Format**:
 Output a numbered list of planned steps.
 Each step must follow this format:
@@ -65,10 +70,30 @@ Example:
 Output ONLY the plan list, no markdown code blocks.
 "#;
 
+pub const DOC_INTENT_JSON_FORMAT: &str = r#"
+**Output Format**:
+Output a JSON object with the planned steps, in order:
+{"steps": [{"intent": "EDIT", "instruction": "Fix the typo in the first paragraph"}]}
+
+"intent" is one of NO-OP, EDIT, GREP, UNDO, CLEAR. "instruction" is an explicit natural language instruction.
+Output ONLY the JSON object.
+"#;
+
 pub fn build_doc_intent_query(current_doc: &str, user_input: &str) -> String {
     format!(
-        "{}\n\n## Current Document\n```md\n{}\n```\n\n## User Input\n{}\n\n## Intent Planning (Output single word or JSON array):",
+        "{}{}\n\n## Current Document\n```md\n{}\n```\n\n## User Input\n{}\n\n## Intent Planning (Output single word or JSON array):",
+        DOC_INTENT_ROUTER_PROMPT,
+        DOC_INTENT_TEXT_FORMAT,
+        if current_doc.is_empty() { "[Empty Document]" } else { current_doc },
+        user_input
+    )
+}
+
+pub fn build_doc_intent_json_query(current_doc: &str, user_input: &str) -> String {
+    format!(
+        "{}{}\n\n## Current Document\n```md\n{}\n```\n\n## User Input\n{}\n\n## Intent Planning (JSON):",
         DOC_INTENT_ROUTER_PROMPT,
+        DOC_INTENT_JSON_FORMAT,
         if current_doc.is_empty() { "[Empty Document]" } else { current_doc },
         user_input
     )
@@ -98,16 +123,52 @@ pub const RAG_NEED_ROUTER_PROMPT: &str = r#"You are a context missing detector t
 - User says "Add a new section" (Does not involve history)
 - Information mentioned by the user is already in the current document
 - Purely new content that doesn't depend on historical information
+"#;
 
+pub const RAG_NEED_TEXT_FORMAT: &str = r#"
 **Output Format**: Output ONLY true or false
 "#;
 
+pub const RAG_NEED_JSON_FORMAT: &str = r#"
+**Output Format**: Output ONLY a JSON object:
+{"needed": true, "reason": "User refers to the pricing discussion, which is not in the document"}
+
+"reason" is one short sentence explaining the decision.
+"#;
+
 pub fn build_rag_need_query(
     current_doc: &str,
     focus: &str,
     git_history: &[String],
     todo_list: &str,
     user_input: &str,
+) -> String {
+    format!(
+        "{}\n\n## Need to retrieve history (Output ONLY true or false):",
+        rag_need_context(RAG_NEED_TEXT_FORMAT, current_doc, focus, git_history, todo_list, user_input)
+    )
+}
+
+pub fn build_rag_need_json_query(
+    current_doc: &str,
+    focus: &str,
+    git_history: &[String],
+    todo_list: &str,
+    user_input: &str,
+) -> String {
+    format!(
+        "{}\n\n## Need to retrieve history (JSON):",
+        rag_need_context(RAG_NEED_JSON_FORMAT, current_doc, focus, git_history, todo_list, user_input)
+    )
+}
+
+fn rag_need_context(
+    output_format: &str,
+    current_doc: &str,
+    focus: &str,
+    git_history: &[String],
+    todo_list: &str,
+    user_input: &str,
 ) -> String {
     let git_history_str = if git_history.is_empty() {
         "[No history]".to_string()
@@ -116,8 +177,9 @@ pub fn build_rag_need_query(
     };
 
     format!(
-        "{}\n\n## Current Context\n\n### Document Content\n```md\n{}\n```\n\n### Current Focus\n{}\n\n### Git History\n{}\n\n### Todo List\n{}\n\n## User Input\n{}\n\n## Need to retrieve history (Output ONLY true or false):",
+        "{}{}\n\n## Current Context\n\n### Document Content\n```md\n{}\n```\n\n### Current Focus\n{}\n\n### Git History\n{}\n\n### Todo List\n{}\n\n## User Input\n{}",
         RAG_NEED_ROUTER_PROMPT,
+        output_format,
         if current_doc.is_empty() { "[Empty Document]" } else { current_doc },
         if focus.is_empty() { "[None]" } else { focus },
         git_history_str,
@@ -146,15 +208,83 @@ pub const TOOL_INTENT_ROUTER_PROMPT: &str = r#"You are a tool requirement detect
 - Information provided by the user themselves
 - Common knowledge
 - Purely creative content
+"#;
 
+pub const TOOL_INTENT_TEXT_FORMAT: &str = r#"
 **Output Format**: Output ONLY NONE or SEARCH
 "#;
 
+pub const TOOL_INTENT_JSON_FORMAT: &str = r#"
+**Output Format**: Output ONLY a JSON object:
+{"intent": "SEARCH", "query": "rust async runtime comparison 2024"}
+
+"intent" is NONE or SEARCH. For SEARCH, "query" is a concise web search query
+extracted from the user input (not the whole transcript); for NONE leave it empty.
+"#;
+
 pub fn build_tool_intent_query(current_doc: &str, user_input: &str) -> String {
     format!(
-        "{}\n\n## Current Document\n```md\n{}\n```\n\n## User Input\n{}\n\n## Tool Requirement (Output ONLY NONE or SEARCH):",
+        "{}{}\n\n## Current Document\n```md\n{}\n```\n\n## User Input\n{}\n\n## Tool Requirement (Output ONLY NONE or SEARCH):",
+        TOOL_INTENT_ROUTER_PROMPT,
+        TOOL_INTENT_TEXT_FORMAT,
+        if current_doc.is_empty() { "[Empty Document]" } else { current_doc },
+        user_input
+    )
+}
+
+pub fn build_tool_intent_json_query(current_doc: &str, user_input: &str) -> String {
+    format!(
+        "{}{}\n\n## Current Document\n```md\n{}\n```\n\n## User Input\n{}\n\n## Tool Requirement (JSON):",
+        TOOL_INTENT_ROUTER_PROMPT,
+        TOOL_INTENT_JSON_FORMAT,
+        if current_doc.is_empty() { "[Empty Document]" } else { current_doc },
+        user_input
+    )
+}
+
+// ============================================================================
+// Fused Router: all three decisions in one call
+// ============================================================================
+
+pub const FUSED_ROUTER_PROMPT: &str = r#"You are the intent router of a voice note tool. From one voice input, make three independent decisions and return them together.
+"#;
+
+pub const FUSED_ROUTER_JSON_FORMAT: &str = r#"
+**Output Format**: Output ONLY a JSON object with all three decisions:
+{"steps": [{"intent": "EDIT", "instruction": "Fix the typo in the first paragraph"}],
+ "rag": {"needed": false, "reason": "Everything referenced is in the document"},
+ "tool": {"intent": "NONE", "query": ""}}
+
+- "steps": Decision 1, the ordered document plan; "intent" is one of NO-OP, EDIT, GREP, UNDO, CLEAR
+- "rag": Decision 2; "reason" is one short sentence
+- "tool": Decision 3; for SEARCH, "query" is a concise web search query extracted from the user input
+"#;
+
+/// Shared context (document, focus, history, todos) is sent once for all three decisions
+pub fn build_fused_router_query(
+    current_doc: &str,
+    focus: &str,
+    git_history: &[String],
+    todo_list: &str,
+    user_input: &str,
+) -> String {
+    let git_history_str = if git_history.is_empty() {
+        "[No history]".to_string()
+    } else {
+        git_history.join("\n")
+    };
+
+    format!(
+        "{}\n# Decision 1: Document Plan\n\n{}\n# Decision 2: History Retrieval\n\n{}\n# Decision 3: External Tools\n\n{}{}\n\n## Current Context\n\n### Document Content\n```md\n{}\n```\n\n### Current Focus\n{}\n\n### Git History\n{}\n\n### Todo List\n{}\n\n## User Input\n{}\n\n## Routing Decisions (JSON):",
+        FUSED_ROUTER_PROMPT,
+        DOC_INTENT_ROUTER_PROMPT,
+        RAG_NEED_ROUTER_PROMPT,
         TOOL_INTENT_ROUTER_PROMPT,
+        FUSED_ROUTER_JSON_FORMAT,
         if current_doc.is_empty() { "[Empty Document]" } else { current_doc },
+        if focus.is_empty() { "[None]" } else { focus },
+        git_history_str,
+        if todo_list.is_empty() { "[None]" } else { todo_list },
         user_input
     )
 }
@@ -8,3 +8,4 @@ pub mod state_manager;
 pub mod todo_agent;
 pub mod rag;
 pub mod auto_naming;
+pub mod socratic;
@@ -0,0 +1,94 @@
+// Socratic Agent Prompts
+
+use crate::modules::{SearchResult, SocraticQuestion};
+use crate::utils::text::safe_truncate;
+
+/// Generate prompt for reviewing the document after a turn
+///
+/// IMPORTANT: Uses FIXED template structure to maximize KV cache hit rate.
+pub fn build_socratic_prompt(
+    current_doc: &str,
+    user_input: &str,
+    history: &[SearchResult],
+    open_questions: &[SocraticQuestion],
+    max_questions: usize,
+) -> String {
+    let doc_preview = safe_truncate(current_doc, 3000);
+
+    // FIXED STRUCTURE: Always show section, use [None] placeholder if empty
+    let history_text = if history.is_empty() {
+        "[None]".to_string()
+    } else {
+        history.iter()
+            .map(|h| format!("- ({}) {}", h.source, safe_truncate(&h.content, 300)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    // FIXED STRUCTURE: task 2 is always present, only its wording changes
+    let find_new = if max_questions == 0 {
+        "Do NOT ask new questions this time: the document did not change. Output an empty \"questions\" list.".to_string()
+    } else {
+        format!("Look for at most {} NEW problems in the document, most important first:", max_questions)
+    };
+
+    let open_text = if open_questions.is_empty() {
+        "[None]".to_string()
+    } else {
+        open_questions.iter()
+            .map(|q| format!("- [{}] ({}) {}", q.id, q.kind.as_str(), q.question))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    format!(
+        r#"You are a Socratic reviewer. The user is drafting a document by speaking; your job is to challenge it, not to praise or rewrite it.
+
+## Current Document
+```
+{}
+```
+
+## Earlier Statements (from previous turns and resources)
+{}
+
+## Open Questions (asked before, not yet answered)
+{}
+
+## Latest User Input
+{}
+
+## Your Task
+1. Decide which open questions the latest user input or the current document now answers. Only count a question as answered if the answer is explicit.
+2. {}
+   - "contradiction": conflicts with another part of the document or with an earlier statement
+   - "assumption": relies on something that was never stated or checked
+   - "missing_specifics": lacks the numbers, names, dates, owners or criteria needed to act on it
+   - "vague": too general to be wrong ("improve performance", "many users")
+3. For each, ask ONE short, pointed question the user can answer in a sentence.
+
+## Important Rules
+- Do not repeat or rephrase an open question
+- Quote the exact statement you are questioning
+- priority: 1 = blocks the plan, 2 = important, 3 = nice to know
+- Ask nothing when the document is specific and consistent; an empty list is a good answer
+- Write questions in the language of the document
+
+## Output Format
+Output ONLY a valid JSON object (no markdown, no explanation):
+{{
+  "answered": ["q-1"],
+  "questions": [
+    {{"kind": "missing_specifics", "quote": "launch soon", "question": "What date is the launch?", "priority": 1}}
+  ]
+}}
+
+If nothing changed, output: {{"answered": [], "questions": []}}
+"#,
+        doc_preview,
+        history_text,
+        open_text,
+        user_input,
+        find_new
+    )
+}
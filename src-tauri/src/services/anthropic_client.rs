@@ -0,0 +1,235 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use futures_util::StreamExt;
+use futures_util::future;
+use std::pin::Pin;
+
+use super::llm_json::ResponseFormat;
+use super::llm_client::{drain_sse_data, ChatMessage, LLMClient, LLMError, ModelSettings, TokenUsage, UsageSink};
+use super::llm_tools::{ToolCallAccumulator, ToolCallDelta, ToolChoice, ToolCompletion, ToolDefinition};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Native Anthropic Messages API client (`POST {base_url}/messages`)
+pub struct AnthropicClient {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    model: String,
+    settings: ModelSettings,
+}
+
+impl AnthropicClient {
+    pub fn new(base_url: String, api_key: String, model: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            base_url,
+            model,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Anthropic takes the system prompt as a top-level field, not as a message.
+    fn split_system(messages: Vec<ChatMessage>) -> (String, Vec<ChatMessage>) {
+        let mut system = Vec::new();
+        let mut rest = Vec::new();
+        for msg in messages {
+            if msg.role == "system" {
+                system.push(msg.content);
+            } else {
+                rest.push(msg);
+            }
+        }
+        (system.join("\n\n"), rest)
+    }
+
+    fn request_body(&self, messages: Vec<ChatMessage>) -> Value {
+        let (system, messages) = Self::split_system(messages);
+
+        let mut body = json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": self.settings.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "stream": true,
+        });
+        if !system.is_empty() {
+            body["system"] = json!(system);
+        }
+        if let Some(temperature) = self.settings.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        body
+    }
+
+    async fn post(&self, body: &Value) -> Result<reqwest::Response, LLMError> {
+        let url = format!("{}/messages", self.base_url);
+        let mut req = self.client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(body);
+        if let Some(timeout) = self.settings.timeout() {
+            req = req.timeout(timeout);
+        }
+
+        let resp = req
+            .send()
+            .await
+            .map_err(LLMError::from_reqwest)?;
+
+        if !resp.status().is_success() {
+            return Err(LLMError::from_response(resp).await);
+        }
+        Ok(resp)
+    }
+}
+
+#[async_trait]
+impl LLMClient for AnthropicClient {
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        self.stream_completion_with_usage(messages, UsageSink::none()).await
+    }
+
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        let resp = self.post(&self.request_body(messages)).await?;
+
+        // Events of interest: `content_block_delta` with a `text_delta` payload.
+        // Usage is split: input tokens arrive in `message_start`, output tokens in `message_delta`.
+        let model = self.model.clone();
+        let mut input_tokens = 0;
+        let stream_mapped = resp.bytes_stream().scan(String::new(), move |buf, chunk_result| {
+            future::ready(match chunk_result {
+                Ok(bytes) => {
+                    buf.push_str(&String::from_utf8_lossy(&bytes));
+
+                    let mut delta_content = String::new();
+                    for data in drain_sse_data(buf) {
+                        if let Ok(val) = serde_json::from_str::<Value>(&data) {
+                            match val["type"].as_str() {
+                                Some("content_block_delta") => {
+                                    if let Some(text) = val["delta"]["text"].as_str() {
+                                        delta_content.push_str(text);
+                                    }
+                                }
+                                Some("message_start") => {
+                                    input_tokens = val["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                                }
+                                Some("message_delta") => {
+                                    usage.report(TokenUsage {
+                                        model: model.clone(),
+                                        prompt_tokens: input_tokens,
+                                        completion_tokens: val["usage"]["output_tokens"].as_u64().unwrap_or(0),
+                                    });
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+
+                    Some(Ok(delta_content))
+                }
+                Err(e) => Some(Err(LLMError::from_stream(e))),
+            })
+        });
+
+        Ok(Box::pin(stream_mapped))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        let mut body = self.request_body(messages);
+        body["tools"] = Value::Array(tools.iter().map(ToolDefinition::to_anthropic).collect());
+        body["tool_choice"] = tool_choice.to_anthropic();
+
+        let resp = self.post(&body).await?;
+        let mut stream = resp.bytes_stream();
+
+        // A `tool_use` block opens with id/name in `content_block_start`; its input
+        // then streams as `input_json_delta` fragments on the same block index
+        let mut buf = String::new();
+        let mut content = String::new();
+        let mut calls = ToolCallAccumulator::default();
+        let mut input_tokens = 0;
+        while let Some(chunk) = stream.next().await {
+            let bytes = chunk.map_err(LLMError::from_stream)?;
+            buf.push_str(&String::from_utf8_lossy(&bytes));
+
+            for data in drain_sse_data(&mut buf) {
+                let Ok(val) = serde_json::from_str::<Value>(&data) else { continue };
+                let index = val["index"].as_u64().unwrap_or(0);
+                match val["type"].as_str() {
+                    Some("content_block_start") if val["content_block"]["type"] == "tool_use" => {
+                        calls.push(ToolCallDelta {
+                            index,
+                            id: val["content_block"]["id"].as_str().map(str::to_string),
+                            name: val["content_block"]["name"].as_str().map(str::to_string),
+                            arguments: None,
+                        });
+                    }
+                    Some("content_block_delta") => {
+                        if let Some(text) = val["delta"]["text"].as_str() {
+                            content.push_str(text);
+                        }
+                        if let Some(partial) = val["delta"]["partial_json"].as_str() {
+                            calls.push(ToolCallDelta {
+                                index,
+                                arguments: Some(partial.to_string()),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    Some("message_start") => {
+                        input_tokens = val["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                    }
+                    Some("message_delta") => {
+                        usage.report(TokenUsage {
+                            model: self.model.clone(),
+                            prompt_tokens: input_tokens,
+                            completion_tokens: val["usage"]["output_tokens"].as_u64().unwrap_or(0),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(ToolCompletion { content, tool_calls: calls.finish() })
+    }
+
+    /// No JSON mode on the Messages API: force a single tool whose input schema
+    /// is the response schema and return its arguments
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        let tool = ToolDefinition::new(&format.name, "Respond by calling this tool.", format.schema);
+        let completion = self.complete_with_tools(messages, vec![tool], ToolChoice::Tool(format.name.clone()), usage).await?;
+        completion.tool_calls.into_iter()
+            .find(|call| call.name == format.name)
+            .map(|call| call.arguments)
+            .ok_or_else(|| LLMError::ParseError(format!("Model did not call tool '{}'", format.name)))
+    }
+}
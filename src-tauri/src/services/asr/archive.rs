@@ -0,0 +1,418 @@
+// Session Audio Archive
+//
+// Opt-in per workspace. While recording, the captured PCM (before the VAD
+// drops anything) is written into the recording directory as WAV segments:
+//
+//   recordings/<id>/audio/session-20250101-093000/
+//     session.json        sample rate, segment length, start time
+//     segment-000.wav     first `segment_secs` of audio
+//     segment-001.wav     ...
+//   recordings/<id>/audio/turns.jsonl
+//     one `{"turn_id", "span"}` line per conversation turn
+//
+// Offsets are milliseconds of captured audio since the session started (the
+// same clock the VAD reports on), so a turn's audio is found by session and
+// offset. Retention limits (age, total size) are applied per workspace when
+// a recording stops, oldest sessions first.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+const SESSION_FILE: &str = "session.json";
+const TURN_INDEX_FILE: &str = "turns.jsonl";
+/// Update WAV headers this often, so a crash loses at most this much
+const FLUSH_EVERY_MS: u64 = 1_000;
+
+fn default_segment_secs() -> u32 {
+    300
+}
+
+/// Per-workspace archive settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioArchiveSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_segment_secs")]
+    pub segment_secs: u32,
+    /// Delete sessions older than this
+    #[serde(default)]
+    pub retention_days: Option<u32>,
+    /// Delete oldest sessions while the workspace's audio exceeds this
+    #[serde(default)]
+    pub max_total_mb: Option<u64>,
+}
+
+impl Default for AudioArchiveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            segment_secs: default_segment_secs(),
+            retention_days: None,
+            max_total_mb: None,
+        }
+    }
+}
+
+/// Where one recording session's audio goes
+#[derive(Debug, Clone)]
+pub struct ArchiveTarget {
+    /// `recordings/<id>/audio`
+    pub audio_dir: PathBuf,
+    /// Session directory name
+    pub session: String,
+    pub segment_secs: u32,
+}
+
+impl ArchiveTarget {
+    pub fn new(recording_path: &Path, segment_secs: u32) -> Self {
+        Self {
+            audio_dir: recording_path.join("audio"),
+            session: format!("session-{}", Utc::now().format("%Y%m%d-%H%M%S")),
+            segment_secs: segment_secs.max(1),
+        }
+    }
+}
+
+/// Audio captured so far in the current session; shared between the capture
+/// stage (which advances it) and the pipeline (which stamps turns with it)
+#[derive(Debug, Clone, Default)]
+pub struct AudioClock {
+    samples: Arc<AtomicU64>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl AudioClock {
+    pub fn advance(&self, samples: usize, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.samples.fetch_add(samples as u64, Ordering::Relaxed);
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        match self.sample_rate.load(Ordering::Relaxed) {
+            0 => 0,
+            rate => self.samples.load(Ordering::Relaxed) * 1000 / rate as u64,
+        }
+    }
+}
+
+/// A stretch of archived audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioSpan {
+    pub session: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Assigns each turn the audio it came from: from the first speech start
+/// after the previous turn (or the previous turn's end without a VAD) up to
+/// the audio captured when the turn fires
+#[derive(Debug, Clone)]
+pub struct TurnSpans {
+    session: String,
+    clock: AudioClock,
+    speech_start: Option<u64>,
+    last_end: u64,
+}
+
+impl TurnSpans {
+    pub fn new(session: String, clock: AudioClock) -> Self {
+        Self { session, clock, speech_start: None, last_end: 0 }
+    }
+
+    pub fn speech_started(&mut self, at_ms: u64) {
+        self.speech_start.get_or_insert(at_ms);
+    }
+
+    pub fn close_turn(&mut self) -> AudioSpan {
+        let end_ms = self.clock.now_ms();
+        let start_ms = self.speech_start.take().unwrap_or(self.last_end).min(end_ms);
+        self.last_end = end_ms;
+        AudioSpan { session: self.session.clone(), start_ms, end_ms }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionInfo {
+    sample_rate: u32,
+    segment_secs: u32,
+    /// Unix timestamp in milliseconds
+    started_at: i64,
+}
+
+/// Segmented WAV writer for one session
+pub struct AudioArchive {
+    dir: PathBuf,
+    sample_rate: u32,
+    segment_len: u64,
+    writer: Option<hound::WavWriter<BufWriter<File>>>,
+    segment: usize,
+    in_segment: u64,
+    since_flush: u64,
+}
+
+impl AudioArchive {
+    pub fn create(target: &ArchiveTarget, sample_rate: u32) -> Result<Self> {
+        let dir = target.audio_dir.join(&target.session);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let info = SessionInfo {
+            sample_rate,
+            segment_secs: target.segment_secs,
+            started_at: Utc::now().timestamp_millis(),
+        };
+        fs::write(dir.join(SESSION_FILE), serde_json::to_string_pretty(&info)?)?;
+        info!("[Audio Archive] Writing {}", dir.display());
+
+        Ok(Self {
+            dir,
+            sample_rate,
+            segment_len: sample_rate as u64 * target.segment_secs as u64,
+            writer: None,
+            segment: 0,
+            in_segment: 0,
+            since_flush: 0,
+        })
+    }
+
+    pub fn write(&mut self, chunk: &[i16]) -> Result<()> {
+        let mut rest = chunk;
+        while !rest.is_empty() {
+            if self.writer.is_none() || self.in_segment == self.segment_len {
+                self.next_segment()?;
+            }
+            let room = (self.segment_len - self.in_segment) as usize;
+            let (now, later) = rest.split_at(room.min(rest.len()));
+            let writer = self.writer.as_mut().expect("segment open");
+            for &sample in now {
+                writer.write_sample(sample)?;
+            }
+            self.in_segment += now.len() as u64;
+            self.since_flush += now.len() as u64;
+            rest = later;
+        }
+
+        if self.since_flush * 1000 >= FLUSH_EVERY_MS * self.sample_rate as u64 {
+            if let Some(writer) = self.writer.as_mut() {
+                writer.flush()?;
+            }
+            self.since_flush = 0;
+        }
+        Ok(())
+    }
+
+    fn next_segment(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finalize()?;
+            self.segment += 1;
+        }
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = self.dir.join(format!("segment-{:03}.wav", self.segment));
+        self.writer = Some(hound::WavWriter::create(&path, spec)
+            .with_context(|| format!("Failed to create {}", path.display()))?);
+        self.in_segment = 0;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finalize()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TurnIndexEntry {
+    turn_id: String,
+    span: AudioSpan,
+}
+
+/// Record where a turn's audio lives (`recordings/<id>/audio/turns.jsonl`)
+pub fn append_turn_index(recording_path: &Path, turn_id: &str, span: &AudioSpan) -> Result<()> {
+    let audio_dir = recording_path.join("audio");
+    fs::create_dir_all(&audio_dir)?;
+    let line = serde_json::to_string(&TurnIndexEntry {
+        turn_id: turn_id.to_string(),
+        span: span.clone(),
+    })?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audio_dir.join(TURN_INDEX_FILE))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Audio span of a turn, from the recording's turn index
+pub fn find_turn_span(recording_path: &Path, turn_id: &str) -> Option<AudioSpan> {
+    let content = fs::read_to_string(recording_path.join("audio").join(TURN_INDEX_FILE)).ok()?;
+    content.lines()
+        .filter_map(|line| serde_json::from_str::<TurnIndexEntry>(line).ok())
+        .find(|entry| entry.turn_id == turn_id)
+        .map(|entry| entry.span)
+}
+
+struct ArchivedSession {
+    dir: PathBuf,
+    started_at: i64,
+    bytes: u64,
+}
+
+fn archived_sessions(recordings_dir: &Path) -> Vec<ArchivedSession> {
+    let mut sessions = Vec::new();
+    let Ok(recordings) = fs::read_dir(recordings_dir) else {
+        return sessions;
+    };
+    for recording in recordings.flatten() {
+        let Ok(entries) = fs::read_dir(recording.path().join("audio")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let Some(info) = fs::read_to_string(dir.join(SESSION_FILE)).ok()
+                .and_then(|content| serde_json::from_str::<SessionInfo>(&content).ok())
+            else {
+                continue;
+            };
+            let bytes = fs::read_dir(&dir).into_iter().flatten().flatten()
+                .filter_map(|file| file.metadata().ok())
+                .map(|meta| meta.len())
+                .sum();
+            sessions.push(ArchivedSession { dir, started_at: info.started_at, bytes });
+        }
+    }
+    sessions.sort_by_key(|session| session.started_at);
+    sessions
+}
+
+/// Delete archived sessions in a workspace's recordings beyond the limits.
+/// Returns how many sessions were removed.
+pub fn enforce_retention(recordings_dir: &Path, settings: &AudioArchiveSettings) -> usize {
+    let mut sessions = archived_sessions(recordings_dir);
+    let mut doomed = Vec::new();
+
+    if let Some(days) = settings.retention_days {
+        let cutoff = Utc::now().timestamp_millis() - days as i64 * 24 * 60 * 60 * 1000;
+        let expired = sessions.iter().take_while(|s| s.started_at < cutoff).count();
+        doomed.extend(sessions.drain(..expired));
+    }
+    if let Some(max_mb) = settings.max_total_mb {
+        let budget = max_mb * 1024 * 1024;
+        let mut total: u64 = sessions.iter().map(|s| s.bytes).sum();
+        while total > budget && !sessions.is_empty() {
+            let oldest = sessions.remove(0);
+            total -= oldest.bytes;
+            doomed.push(oldest);
+        }
+    }
+
+    let mut removed = 0;
+    for session in doomed {
+        match fs::remove_dir_all(&session.dir) {
+            Ok(()) => {
+                info!("[Audio Archive] Retention removed {}", session.dir.display());
+                removed += 1;
+            }
+            Err(e) => warn!("[Audio Archive] Failed to remove {}: {}", session.dir.display(), e),
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments_rotate_and_turns_are_indexed() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = ArchiveTarget::new(dir.path(), 1);
+        let mut archive = AudioArchive::create(&target, 16_000).unwrap();
+        let clock = AudioClock::default();
+        for _ in 0..25 {
+            let chunk = vec![100i16; 1_600];
+            archive.write(&chunk).unwrap();
+            clock.advance(chunk.len(), 16_000);
+        }
+        archive.finish().unwrap();
+        assert_eq!(clock.now_ms(), 2_500);
+
+        let session_dir = target.audio_dir.join(&target.session);
+        let lengths: Vec<u32> = (0..3)
+            .map(|i| hound::WavReader::open(session_dir.join(format!("segment-{:03}.wav", i))).unwrap().duration())
+            .collect();
+        assert_eq!(lengths, vec![16_000, 16_000, 8_000]);
+
+        let span = AudioSpan { session: target.session.clone(), start_ms: 400, end_ms: 2_100 };
+        append_turn_index(dir.path(), "turn-a", &span).unwrap();
+        assert_eq!(find_turn_span(dir.path(), "turn-a"), Some(span));
+        assert_eq!(find_turn_span(dir.path(), "turn-b"), None);
+    }
+
+    #[test]
+    fn test_turn_spans_follow_speech_and_clock() {
+        let clock = AudioClock::default();
+        let mut spans = TurnSpans::new("session-x".to_string(), clock.clone());
+
+        clock.advance(16_000, 16_000);
+        spans.speech_started(300);
+        spans.speech_started(700);
+        clock.advance(16_000, 16_000);
+        let first = spans.close_turn();
+        assert_eq!((first.start_ms, first.end_ms), (300, 2_000));
+
+        // No speech boundary reported: everything since the previous turn
+        clock.advance(8_000, 16_000);
+        let second = spans.close_turn();
+        assert_eq!((second.start_ms, second.end_ms), (2_000, 2_500));
+        assert_eq!(second.session, "session-x");
+    }
+
+    fn fake_session(recordings: &Path, recording: &str, name: &str, age_days: i64, bytes: usize) -> PathBuf {
+        let dir = recordings.join(recording).join("audio").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        let info = SessionInfo {
+            sample_rate: 16_000,
+            segment_secs: 300,
+            started_at: Utc::now().timestamp_millis() - age_days * 24 * 60 * 60 * 1000,
+        };
+        fs::write(dir.join(SESSION_FILE), serde_json::to_string(&info).unwrap()).unwrap();
+        fs::write(dir.join("segment-000.wav"), vec![0u8; bytes]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_retention_by_age_then_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let recordings = dir.path();
+        let ancient = fake_session(recordings, "a", "session-1", 40, 1_000);
+        let old = fake_session(recordings, "a", "session-2", 10, 600_000);
+        let recent = fake_session(recordings, "b", "session-3", 1, 600_000);
+
+        let settings = AudioArchiveSettings {
+            enabled: true,
+            retention_days: Some(30),
+            max_total_mb: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(enforce_retention(recordings, &settings), 2);
+        assert!(!ancient.exists());
+        assert!(!old.exists());
+        assert!(recent.exists());
+
+        // Already within limits: nothing else goes
+        assert_eq!(enforce_retention(recordings, &settings), 0);
+    }
+}
@@ -0,0 +1,239 @@
+// Audio Front End
+//
+// Turns whatever the input device delivers (any cpal sample format, any
+// channel count, any rate) into the 16-bit mono PCM a backend expects:
+// convert to f32, average channels down to mono, then resample with a
+// windowed-sinc low-pass so content above the target Nyquist is filtered out
+// instead of aliasing into the speech band. The resampler is streaming:
+// history carries over between device callbacks, and the ratio is exact, so
+// 44.1 kHz input comes out at exactly 16 kHz.
+
+use cpal::{FromSample, Sample};
+use std::f64::consts::PI;
+
+use super::AudioChunk;
+
+/// Kernel zero crossings on each side of the center
+const HALF_TAPS: f64 = 24.0;
+/// Passband edge as a fraction of the lower Nyquist frequency
+const ROLLOFF: f64 = 0.9;
+
+/// Streaming band-limited resampler for mono f32 audio
+pub struct Resampler {
+    /// Input samples per output sample
+    step: f64,
+    /// Low-pass cutoff, relative to the input Nyquist frequency
+    cutoff: f64,
+    /// Kernel half-width in input samples
+    half_width: usize,
+    buffer: Vec<f32>,
+    /// Position of the next output sample, in input samples from `buffer[0]`
+    pos: f64,
+    input_len: u64,
+    output_len: u64,
+    output_rate: u32,
+    input_rate: u32,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        let cutoff = (output_rate as f64 / input_rate as f64).min(1.0) * ROLLOFF;
+        let half_width = (HALF_TAPS / cutoff).ceil() as usize;
+        Self {
+            step: input_rate as f64 / output_rate as f64,
+            cutoff,
+            half_width,
+            // Silence before the first sample, so output starts at t = 0
+            buffer: vec![0.0; half_width],
+            pos: half_width as f64,
+            input_len: 0,
+            output_len: 0,
+            output_rate,
+            input_rate,
+        }
+    }
+
+    fn is_passthrough(&self) -> bool {
+        self.input_rate == self.output_rate
+    }
+
+    /// Resample the next block of input
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        self.input_len += input.len() as u64;
+        if self.is_passthrough() {
+            self.output_len += input.len() as u64;
+            return input.to_vec();
+        }
+        self.buffer.extend_from_slice(input);
+        self.drain(u64::MAX)
+    }
+
+    /// Emit the tail still waiting on future input (end of stream)
+    pub fn flush(&mut self) -> Vec<f32> {
+        if self.is_passthrough() {
+            return Vec::new();
+        }
+        let expected = (self.input_len * self.output_rate as u64).div_ceil(self.input_rate as u64);
+        let padding = self.half_width + self.step.ceil() as usize + 1;
+        self.buffer.extend(std::iter::repeat_n(0.0, padding));
+        self.drain(expected)
+    }
+
+    fn drain(&mut self, limit: u64) -> Vec<f32> {
+        let mut out = Vec::with_capacity((self.buffer.len() as f64 / self.step) as usize + 1);
+        while self.output_len < limit {
+            let center = self.pos.floor() as usize;
+            if center + self.half_width >= self.buffer.len() {
+                break;
+            }
+            out.push(self.interpolate(center));
+            self.pos += self.step;
+            self.output_len += 1;
+        }
+
+        // Keep only the history the next kernel can still reach
+        let consumed = (self.pos.floor() as usize).saturating_sub(self.half_width);
+        self.buffer.drain(..consumed);
+        self.pos -= consumed as f64;
+        out
+    }
+
+    fn interpolate(&self, center: usize) -> f32 {
+        let first = center + 1 - self.half_width;
+        let last = center + self.half_width;
+        (first..=last)
+            .map(|k| self.buffer[k] as f64 * self.kernel(self.pos - k as f64))
+            .sum::<f64>() as f32
+    }
+
+    /// Blackman-windowed sinc at `x` input samples from the center
+    fn kernel(&self, x: f64) -> f64 {
+        let width = self.half_width as f64;
+        if x.abs() >= width {
+            return 0.0;
+        }
+        let arg = PI * self.cutoff * x;
+        let sinc = if arg.abs() < 1e-9 { 1.0 } else { arg.sin() / arg };
+        let u = x / width;
+        let window = 0.42 + 0.5 * (PI * u).cos() + 0.08 * (2.0 * PI * u).cos();
+        self.cutoff * sinc * window
+    }
+}
+
+/// Device samples in, backend PCM out
+pub struct AudioFrontend {
+    channels: usize,
+    resampler: Resampler,
+}
+
+impl AudioFrontend {
+    pub fn new(input_rate: u32, channels: u16, target_rate: u32) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            resampler: Resampler::new(input_rate, target_rate),
+        }
+    }
+
+    /// Interleaved device samples of any cpal sample format
+    pub fn process<T>(&mut self, interleaved: &[T]) -> AudioChunk
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let mono: Vec<f32> = interleaved.chunks(self.channels)
+            .map(|frame| frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / frame.len() as f32)
+            .collect();
+        to_pcm16(&self.resampler.process(&mono))
+    }
+
+    pub fn flush(&mut self) -> AudioChunk {
+        to_pcm16(&self.resampler.flush())
+    }
+}
+
+fn to_pcm16(samples: &[f32]) -> AudioChunk {
+    samples.iter()
+        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exponential sine sweep from `f0` to `f1` Hz, sampled at time `t` seconds
+    fn sweep_at(t: f64, f0: f64, f1: f64, duration: f64) -> f64 {
+        let k = (f1 / f0).ln() / duration;
+        (2.0 * PI * f0 * ((k * t).exp() - 1.0) / k).sin()
+    }
+
+    fn sweep(rate: u32, f0: f64, f1: f64, duration: f64) -> Vec<f32> {
+        let n = (rate as f64 * duration) as usize;
+        (0..n)
+            .map(|i| (0.5 * sweep_at(i as f64 / rate as f64, f0, f1, duration)) as f32)
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// Resample in uneven blocks, like device callbacks
+    fn resample_blocks(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+        let mut resampler = Resampler::new(from, to);
+        let mut out = Vec::new();
+        for block in input.chunks(733) {
+            out.extend(resampler.process(block));
+        }
+        out.extend(resampler.flush());
+        out
+    }
+
+    #[test]
+    fn test_sweep_survives_common_device_rates() {
+        let duration = 1.0;
+        for from in [44_100, 48_000, 96_000, 8_000] {
+            let input = sweep(from, 100.0, 3_500.0, duration);
+            let output = resample_blocks(&input, from, 16_000);
+            assert_eq!(output.len(), 16_000, "length from {} Hz", from);
+
+            let expected = sweep(16_000, 100.0, 3_500.0, duration);
+            // Skip the kernel's edge transients
+            let error: Vec<f32> = output.iter().zip(&expected)
+                .skip(200).take(15_600)
+                .map(|(a, b)| a - b)
+                .collect();
+            assert!(rms(&error) < 0.01, "error {} from {} Hz", rms(&error), from);
+        }
+    }
+
+    #[test]
+    fn test_content_above_nyquist_is_filtered_not_aliased() {
+        // 12 kHz would fold to 4 kHz under plain decimation
+        let tone: Vec<f32> = (0..48_000)
+            .map(|i| (0.5 * (2.0 * PI * 12_000.0 * i as f64 / 48_000.0).sin()) as f32)
+            .collect();
+        let output = resample_blocks(&tone, 48_000, 16_000);
+        assert!(rms(&output[200..15_800]) < 0.001);
+
+        // A sweep through the stopband stays quiet end to end
+        let high = sweep(48_000, 9_000.0, 20_000.0, 1.0);
+        let output = resample_blocks(&high, 48_000, 16_000);
+        assert!(rms(&output[200..15_800]) < 0.005);
+    }
+
+    #[test]
+    fn test_frontend_downmixes_and_converts_formats() {
+        // Opposite-phase stereo cancels; identical channels survive
+        let mut frontend = AudioFrontend::new(16_000, 2, 16_000);
+        let cancelled = frontend.process(&[0.5f32, -0.5, 0.25, -0.25]);
+        assert_eq!(cancelled, vec![0, 0]);
+        let mono = frontend.process(&[0.5f32, 0.5]);
+        assert_eq!(mono, vec![16_384]);
+
+        let mut frontend = AudioFrontend::new(16_000, 1, 16_000);
+        assert_eq!(frontend.process(&[i16::MIN, 0, 16_384]), vec![-32_767, 0, 16_384]);
+        assert_eq!(frontend.process(&[0u8, 128, 255]), vec![-32_767, 0, 32_511]);
+        assert_eq!(frontend.process(&[0.5f64]), vec![16_384]);
+    }
+}
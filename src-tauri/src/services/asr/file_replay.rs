@@ -0,0 +1,227 @@
+// File Replay ASR
+//
+// Feeds a captured WAV (or raw 16-bit little-endian PCM) file into another
+// backend in place of the microphone, resampled to the backend's rate and
+// paced in real time so server-side VAD segments it the way it would live
+// audio. Recording ends when the file has been played and the inner backend
+// has flushed.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::info;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::audio_frontend::AudioFrontend;
+use super::{AsrBackend, AudioChunk, SessionControl, Transcript};
+
+const CHUNK_MS: u64 = 100;
+const DEFAULT_PCM_SAMPLE_RATE: u32 = 16_000;
+
+/// Mono 16-bit PCM decoded from a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcmAudio {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+}
+
+/// Decode a `.wav` file (any channel count, int or float samples, downmixed
+/// to mono) or raw mono 16-bit little-endian PCM at `pcm_sample_rate`
+pub fn read_audio_file(path: &Path, pcm_sample_rate: u32) -> Result<PcmAudio> {
+    let is_wav = path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+    if !is_wav {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let samples = bytes.chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        return Ok(PcmAudio { samples, sample_rate: pcm_sample_rate });
+    }
+
+    let mut reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let samples = interleaved.chunks(channels)
+        .map(|frame| {
+            let mono = frame.iter().sum::<f32>() / frame.len() as f32;
+            (mono.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+        })
+        .collect();
+    Ok(PcmAudio { samples, sample_rate: spec.sample_rate })
+}
+
+pub struct FileReplayBackend {
+    path: PathBuf,
+    inner: Arc<dyn AsrBackend>,
+    pcm_sample_rate: u32,
+}
+
+impl FileReplayBackend {
+    pub fn new(path: PathBuf, inner: Arc<dyn AsrBackend>) -> Self {
+        Self {
+            path,
+            inner,
+            pcm_sample_rate: DEFAULT_PCM_SAMPLE_RATE,
+        }
+    }
+
+    pub fn with_pcm_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.pcm_sample_rate = sample_rate;
+        self
+    }
+}
+
+#[async_trait]
+impl AsrBackend for FileReplayBackend {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn wants_microphone(&self) -> bool {
+        false
+    }
+
+    async fn run(
+        &self,
+        _audio: mpsc::UnboundedReceiver<AudioChunk>,
+        transcripts: mpsc::UnboundedSender<Transcript>,
+        control: SessionControl,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let audio = read_audio_file(&self.path, self.pcm_sample_rate)?;
+        info!(
+            "[ASR Replay] Playing {} ({:.1}s at {} Hz) into {}",
+            self.path.display(),
+            audio.samples.len() as f32 / audio.sample_rate as f32,
+            audio.sample_rate,
+            self.inner.name()
+        );
+        let target_rate = self.inner.sample_rate();
+        let mut frontend = AudioFrontend::new(audio.sample_rate, 1, target_rate);
+        let mut samples = frontend.process(&audio.samples);
+        samples.extend(frontend.flush());
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let feed_token = cancel.clone();
+        let chunk_len = (target_rate as u64 * CHUNK_MS / 1000) as usize;
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(Duration::from_millis(CHUNK_MS));
+            for chunk in samples.chunks(chunk_len.max(1)) {
+                tokio::select! {
+                    _ = feed_token.cancelled() => break,
+                    _ = tick.tick() => {}
+                }
+                if audio_tx.send(chunk.to_vec()).is_err() {
+                    break;
+                }
+            }
+            // Dropping the sender tells the inner backend the input is over
+        });
+
+        self.inner.run(audio_rx, transcripts, control, cancel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records what it hears and reports the sample count as its transcript
+    struct CountingBackend;
+
+    #[async_trait]
+    impl AsrBackend for CountingBackend {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn run(
+            &self,
+            mut audio: mpsc::UnboundedReceiver<AudioChunk>,
+            transcripts: mpsc::UnboundedSender<Transcript>,
+            _control: SessionControl,
+            _cancel: CancellationToken,
+        ) -> Result<()> {
+            let mut total = 0;
+            while let Some(chunk) = audio.recv().await {
+                total += chunk.len();
+            }
+            transcripts.send(Transcript::complete("count", total.to_string()))?;
+            Ok(())
+        }
+    }
+
+    fn write_wav(path: &Path, channels: u16, sample_rate: u32, frames: &[[i16; 2]]) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for frame in frames {
+            for &s in &frame[..channels as usize] {
+                writer.write_sample(s).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_read_wav_downmixes_and_raw_pcm() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("stereo.wav");
+        write_wav(&wav, 2, 16_000, &[[1000, 3000], [-2000, 2000]]);
+        let audio = read_audio_file(&wav, DEFAULT_PCM_SAMPLE_RATE).unwrap();
+        assert_eq!(audio.sample_rate, 16_000);
+        assert_eq!(audio.samples.len(), 2);
+        assert!((audio.samples[0] - 2000).abs() <= 1);
+        assert!(audio.samples[1].abs() <= 1);
+
+        let raw = dir.path().join("take.pcm");
+        std::fs::write(&raw, [0x01, 0x00, 0xff, 0xff]).unwrap();
+        let audio = read_audio_file(&raw, 8_000).unwrap();
+        assert_eq!(audio, PcmAudio { samples: vec![1, -1], sample_rate: 8_000 });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_feeds_whole_file_then_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("mono.wav");
+        write_wav(&wav, 1, 16_000, &vec![[7, 0]; 4_000]);
+
+        let backend = FileReplayBackend::new(wav, Arc::new(CountingBackend));
+        let (_audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        backend.run(audio_rx, tx, SessionControl::default(), CancellationToken::new()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().text, "4000");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_resamples_to_backend_rate() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("cd.wav");
+        write_wav(&wav, 1, 44_100, &vec![[0, 0]; 44_100]);
+
+        let backend = FileReplayBackend::new(wav, Arc::new(CountingBackend));
+        let (_audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        backend.run(audio_rx, tx, SessionControl::default(), CancellationToken::new()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().text, "16000");
+    }
+}
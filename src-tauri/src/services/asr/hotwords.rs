@@ -0,0 +1,87 @@
+// Hotword Seeding
+//
+// Recognition bias terms taken from the recording itself: the document's
+// headings, and the terms of any glossary section (a heading named
+// "Glossary"/"Terms"/"术语"/"词汇表" followed by `- Term: definition` items).
+// Merged after the workspace's own list, which always wins the limit.
+
+/// Most terms sent to the server
+pub const MAX_HOTWORDS: usize = 100;
+/// Longer headings are sentences, not terms
+const MAX_TERM_CHARS: usize = 40;
+
+const GLOSSARY_HEADINGS: &[&str] = &["glossary", "terms", "terminology", "术语", "术语表", "词汇表"];
+
+fn clean(term: &str) -> Option<String> {
+    let term = term.trim().trim_matches(|c| matches!(c, '*' | '_' | '`')).trim();
+    let len = term.chars().count();
+    (len > 0 && len <= MAX_TERM_CHARS).then(|| term.to_string())
+}
+
+/// Candidate hotwords from a markdown document, in document order
+pub fn seed_from_document(markdown: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut in_glossary = false;
+    for line in markdown.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            let heading = line.trim_start_matches('#').trim();
+            in_glossary = GLOSSARY_HEADINGS.contains(&heading.to_lowercase().as_str());
+            if !in_glossary {
+                terms.extend(clean(heading));
+            }
+        } else if in_glossary {
+            if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+                let term = item.split([':', '：']).next().unwrap_or(item);
+                let term = term.split(" - ").next().unwrap_or(term);
+                terms.extend(clean(term));
+            }
+        }
+    }
+    terms
+}
+
+/// Configured terms first, then seeded ones; case-insensitive dedupe, capped
+pub fn merge(configured: &[String], seeded: &[String]) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    configured.iter()
+        .chain(seeded)
+        .filter_map(|term| clean(term))
+        .filter(|term| seen.insert(term.to_lowercase()))
+        .take(MAX_HOTWORDS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeds_headings_and_glossary_terms() {
+        let doc = "# Creek Roadmap\n\
+                   Some prose mentioning things.\n\
+                   ## Q3 LanceDB migration\n\
+                   ## This heading is far too long to be a useful recognition hint at all\n\
+                   ## Glossary\n\
+                   - **VAD**: voice activity detection\n\
+                   - 流式识别：streaming recognition\n\
+                   - DashScope - Alibaba's model service\n\
+                   Not a list item\n\
+                   ## Next steps\n\
+                   - not a glossary term\n";
+        assert_eq!(
+            seed_from_document(doc),
+            vec!["Creek Roadmap", "Q3 LanceDB migration", "VAD", "流式识别", "DashScope", "Next steps"]
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_configured_first_without_duplicates() {
+        let configured = vec!["Creek".to_string(), "  ".to_string()];
+        let seeded = vec!["creek".to_string(), "LanceDB".to_string()];
+        assert_eq!(merge(&configured, &seeded), vec!["Creek", "LanceDB"]);
+
+        let many: Vec<String> = (0..150).map(|i| format!("term{}", i)).collect();
+        assert_eq!(merge(&[], &many).len(), MAX_HOTWORDS);
+    }
+}
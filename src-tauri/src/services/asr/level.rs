@@ -0,0 +1,110 @@
+// Input Level Meter
+//
+// Summarizes microphone audio into ~20 readings per second for the UI meter:
+// RMS and peak in dBFS, plus flags for input that looks muted (digital
+// silence for the whole window) or clipped (samples pinned at full scale).
+
+use serde::Serialize;
+
+const WINDOW_MS: u32 = 50;
+const FLOOR_DB: f32 = -96.0;
+/// Anything below this for a whole window is a muted or dead input
+const SILENT_DB: f32 = -70.0;
+/// Samples this close to full scale count as clipped
+const CLIP_LEVEL: i32 = 32_700;
+
+/// `audio-level` event payload
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AudioLevel {
+    pub rms_db: f32,
+    pub peak_db: f32,
+    pub silent: bool,
+    pub clipping: bool,
+}
+
+pub struct LevelMeter {
+    window_len: usize,
+    count: usize,
+    sum_squares: f64,
+    peak: i32,
+    clipped: usize,
+}
+
+impl LevelMeter {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            window_len: (sample_rate * WINDOW_MS / 1000).max(1) as usize,
+            count: 0,
+            sum_squares: 0.0,
+            peak: 0,
+            clipped: 0,
+        }
+    }
+
+    /// Readings for every window completed by this chunk
+    pub fn process(&mut self, chunk: &[i16]) -> Vec<AudioLevel> {
+        let mut readings = Vec::new();
+        for &sample in chunk {
+            let magnitude = (sample as i32).abs();
+            self.sum_squares += (sample as f64).powi(2);
+            self.peak = self.peak.max(magnitude);
+            if magnitude >= CLIP_LEVEL {
+                self.clipped += 1;
+            }
+            self.count += 1;
+
+            if self.count == self.window_len {
+                readings.push(self.reading());
+                self.count = 0;
+                self.sum_squares = 0.0;
+                self.peak = 0;
+                self.clipped = 0;
+            }
+        }
+        readings
+    }
+
+    fn reading(&self) -> AudioLevel {
+        let to_db = |amplitude: f64| {
+            if amplitude > 0.0 {
+                ((20.0 * (amplitude / 32_768.0).log10()) as f32).max(FLOOR_DB)
+            } else {
+                FLOOR_DB
+            }
+        };
+        let rms_db = to_db((self.sum_squares / self.count as f64).sqrt());
+        AudioLevel {
+            rms_db,
+            peak_db: to_db(self.peak as f64),
+            silent: rms_db < SILENT_DB,
+            // A lone full-scale sample is a transient; a run of them is clipping
+            clipping: self.clipped >= 3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::asr::AudioChunk;
+
+    #[test]
+    fn test_readings_at_twenty_hertz_with_flags() {
+        let mut meter = LevelMeter::new(16_000);
+        let half_scale: AudioChunk = (0..16_000)
+            .map(|i| if i % 2 == 0 { 16_384 } else { -16_384 })
+            .collect();
+        let readings = meter.process(&half_scale);
+        assert_eq!(readings.len(), 20);
+        assert!((readings[0].rms_db + 6.02).abs() < 0.1);
+        assert!(!readings[0].silent && !readings[0].clipping);
+
+        let muted = meter.process(&[0; 800]);
+        assert_eq!(muted.len(), 1);
+        assert!(muted[0].silent);
+        assert_eq!(muted[0].peak_db, FLOOR_DB);
+
+        let clipped = meter.process(&[i16::MAX; 800]);
+        assert!(clipped[0].clipping);
+    }
+}
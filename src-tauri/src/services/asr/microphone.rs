@@ -0,0 +1,203 @@
+// Microphone Capture
+//
+// Opens the selected (or default) cpal input device on a dedicated thread
+// (cpal streams aren't `Send` on every platform) and forwards 16-bit mono PCM
+// at the backend's sample rate until the recording is cancelled. Every cpal
+// sample format is accepted; see audio_frontend.rs for the conversion.
+
+use anyhow::{anyhow, bail, Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{DeviceId, FromSample, SampleFormat, SizedSample, StreamConfig, I24, U24};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use super::audio_frontend::AudioFrontend;
+use super::AudioChunk;
+
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// An input device and what it can capture
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDeviceInfo {
+    /// Stable identifier to persist (falls back to the name where the host has none)
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    pub default_config: Option<InputConfigInfo>,
+    pub supported_configs: Vec<InputConfigInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InputConfigInfo {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// Enumerate input devices on the default host (blocking)
+pub fn list_input_devices() -> Result<Vec<InputDeviceInfo>> {
+    let host = cpal::default_host();
+    let default_id = host.default_input_device().map(|device| device_id(&device));
+
+    let devices = host.input_devices()?
+        .map(|device| {
+            let id = device_id(&device);
+            let default_config = device.default_input_config().ok().map(|config| InputConfigInfo {
+                channels: config.channels(),
+                min_sample_rate: config.sample_rate(),
+                max_sample_rate: config.sample_rate(),
+                sample_format: config.sample_format().to_string(),
+            });
+            let supported_configs = device.supported_input_configs()
+                .map(|configs| {
+                    configs
+                        .map(|range| InputConfigInfo {
+                            channels: range.channels(),
+                            min_sample_rate: range.min_sample_rate(),
+                            max_sample_rate: range.max_sample_rate(),
+                            sample_format: range.sample_format().to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            InputDeviceInfo {
+                is_default: default_id.as_ref() == Some(&id),
+                name: device_name(&device),
+                id,
+                default_config,
+                supported_configs,
+            }
+        })
+        .collect();
+    Ok(devices)
+}
+
+fn device_name(device: &cpal::Device) -> String {
+    device.description()
+        .map(|desc| desc.name().to_string())
+        .unwrap_or_else(|_| "unknown".into())
+}
+
+fn device_id(device: &cpal::Device) -> String {
+    device.id()
+        .map(|id| id.to_string())
+        .unwrap_or_else(|_| device_name(device))
+}
+
+/// The requested device by id (or name), else the default one
+fn select_device(host: &cpal::Host, requested: Option<&str>) -> Result<cpal::Device> {
+    if let Some(requested) = requested {
+        let by_id = requested.parse::<DeviceId>().ok()
+            .and_then(|id| host.device_by_id(&id));
+        let found = by_id.or_else(|| {
+            host.input_devices().ok()?
+                .find(|device| device_id(device) == requested || device_name(device) == requested)
+        });
+        match found {
+            Some(device) => return Ok(device),
+            None => warn!("[Microphone] Input device '{}' not found, using default", requested),
+        }
+    }
+    host.default_input_device()
+        .context("No input device available")
+}
+
+/// Start capturing from `device` (id or name; None = system default).
+/// Returns once the input stream is playing.
+pub async fn start_capture(
+    device: Option<String>,
+    target_rate: u32,
+    audio_tx: mpsc::UnboundedSender<AudioChunk>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let (ready_tx, ready_rx) = oneshot::channel();
+    std::thread::spawn(move || {
+        let stream = match open_stream(device.as_deref(), target_rate, audio_tx) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+
+        // Keep the stream alive for the duration of the recording
+        while !cancel.is_cancelled() {
+            std::thread::sleep(CANCEL_POLL);
+        }
+        drop(stream);
+        info!("[Microphone] Capture stopped");
+    });
+
+    ready_rx.await.map_err(|_| anyhow!("Capture thread exited"))?
+}
+
+fn open_stream(
+    device: Option<&str>,
+    target_rate: u32,
+    audio_tx: mpsc::UnboundedSender<AudioChunk>,
+) -> Result<cpal::Stream> {
+    let host = cpal::default_host();
+    let input_device = select_device(&host, device)?;
+    info!("Input device: {} ({})", device_name(&input_device), device_id(&input_device));
+
+    let config = input_device.default_input_config()?;
+    info!("Default config: {:?}", config);
+
+    let sample_rate = config.sample_rate();
+    let channels = config.channels();
+    info!("Input: {} Hz, {} channel(s), {:?} -> {} Hz mono", sample_rate, channels, config.sample_format(), target_rate);
+    let frontend = AudioFrontend::new(sample_rate, channels, target_rate);
+
+    let stream_config = config.config();
+    let stream = match config.sample_format() {
+        SampleFormat::I8 => build_stream::<i8>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::I16 => build_stream::<i16>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::I24 => build_stream::<I24>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::I32 => build_stream::<i32>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::I64 => build_stream::<i64>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::U8 => build_stream::<u8>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::U16 => build_stream::<u16>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::U24 => build_stream::<U24>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::U32 => build_stream::<u32>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::U64 => build_stream::<u64>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::F32 => build_stream::<f32>(&input_device, &stream_config, frontend, audio_tx),
+        SampleFormat::F64 => build_stream::<f64>(&input_device, &stream_config, frontend, audio_tx),
+        other => bail!("Unsupported input sample format {:?}", other),
+    }?;
+
+    stream.play()?;
+    Ok(stream)
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut frontend: AudioFrontend,
+    audio_tx: mpsc::UnboundedSender<AudioChunk>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let err_fn = move |err| {
+        error!("an error occurred on stream: {}", err);
+    };
+
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| {
+            let pcm = frontend.process(data);
+            if !pcm.is_empty() {
+                let _ = audio_tx.send(pcm);
+            }
+        },
+        err_fn,
+        None // None = blocking, use default timeout
+    )?;
+    Ok(stream)
+}
@@ -0,0 +1,112 @@
+// Scripted ASR
+//
+// Emits a fixed list of transcripts at offsets from the start of recording.
+// No microphone, no network: used to drive the pipeline end to end in CI.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use super::{AsrBackend, AudioChunk, SessionControl, Transcript};
+
+/// One transcript, `at_ms` after recording starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedTranscript {
+    pub at_ms: u64,
+    pub text: String,
+}
+
+/// Read a JSON array of `{"at_ms": .., "text": ..}`
+pub fn load_script(path: &Path) -> Result<Vec<ScriptedTranscript>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ASR script {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Invalid ASR script {}", path.display()))
+}
+
+pub struct ScriptedBackend {
+    script: Vec<ScriptedTranscript>,
+}
+
+impl ScriptedBackend {
+    pub fn new(mut script: Vec<ScriptedTranscript>) -> Self {
+        script.sort_by_key(|entry| entry.at_ms);
+        Self { script }
+    }
+}
+
+#[async_trait]
+impl AsrBackend for ScriptedBackend {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn wants_microphone(&self) -> bool {
+        false
+    }
+
+    async fn run(
+        &self,
+        _audio: mpsc::UnboundedReceiver<AudioChunk>,
+        transcripts: mpsc::UnboundedSender<Transcript>,
+        _control: SessionControl,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let start = Instant::now();
+        for (index, entry) in self.script.iter().enumerate() {
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                _ = tokio::time::sleep_until(start + Duration::from_millis(entry.at_ms)) => {}
+            }
+            info!("[ASR Mock] Transcript: {}", entry.text);
+            if transcripts.send(Transcript::complete(format!("mock-{}", index), entry.text.clone())).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at_ms: u64, text: &str) -> ScriptedTranscript {
+        ScriptedTranscript { at_ms, text: text.to_string() }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_script_is_emitted_in_time_order() {
+        let backend = ScriptedBackend::new(vec![entry(900, "second"), entry(300, "first")]);
+        let (_audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let start = Instant::now();
+        tokio::spawn(async move { backend.run(audio_rx, tx, SessionControl::default(), CancellationToken::new()).await });
+
+        assert_eq!(rx.recv().await.unwrap(), Transcript::complete("mock-0", "first"));
+        assert_eq!(start.elapsed(), Duration::from_millis(300));
+        assert_eq!(rx.recv().await.unwrap().text, "second");
+        assert_eq!(start.elapsed(), Duration::from_millis(900));
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_stops_the_script() {
+        let backend = ScriptedBackend::new(vec![entry(100, "kept"), entry(5_000, "dropped")]);
+        let (_audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        tokio::spawn(async move { backend.run(audio_rx, tx, SessionControl::default(), token).await });
+
+        assert_eq!(rx.recv().await.unwrap().text, "kept");
+        cancel.cancel();
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,365 @@
+// ASR Backends
+//
+// Speech recognition behind one trait, so the pipeline doesn't care where
+// transcripts come from. A backend consumes 16-bit mono PCM at its own
+// `sample_rate()` and sends transcripts to the pipeline: interim text while
+// an item is being recognised (shown live), then the item's final (acted on):
+//
+// - `qwen`:   DashScope realtime WebSocket (default)
+// - `openai`: OpenAI-realtime-compatible transcription WebSocket
+// - `file`:   replays a WAV / raw PCM file into another backend instead of
+//             the microphone (reproducing captured sessions)
+// - `mock`:   emits scripted transcripts at fixed offsets, no audio or
+//             network at all (CI)
+//
+// Selected by the `[asr]` section of creek.toml:
+//   [asr]
+//   backend = "file"
+//   file = "/tmp/session.wav"
+//   transcriber = "qwen"
+//
+//   [asr]
+//   backend = "mock"
+//   script = [{ at_ms = 500, text = "Hello" }, { at_ms = 2000, text = "撤销" }]
+//
+// Language, server VAD and hotwords are per workspace (`AsrSessionSettings`)
+// and are pushed to a live session whenever they change.
+//
+// Realtime backends reconnect with backoff when the WebSocket drops
+// (`[asr.reconnect]`), replaying audio the server hadn't committed yet.
+//
+// Microphone audio passes through a local VAD first (`[asr.vad]`), and can
+// be archived into the recording directory (per-workspace, see `archive`).
+
+pub mod archive;
+pub mod audio_frontend;
+pub mod file_replay;
+pub mod hotwords;
+pub mod level;
+pub mod microphone;
+pub mod mock;
+pub mod realtime;
+pub mod vad;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch, Notify};
+use tokio_util::sync::CancellationToken;
+
+/// Server VAD silence when the workspace sets none
+pub const DEFAULT_SILENCE_MS: u32 = 800;
+
+pub use archive::{ArchiveTarget, AudioArchiveSettings, AudioClock, AudioSpan, TurnSpans};
+pub use file_replay::FileReplayBackend;
+pub use level::AudioLevel;
+pub use mock::{ScriptedBackend, ScriptedTranscript};
+pub use realtime::{RealtimeBackend, RealtimeFlavor, ReconnectConfig};
+pub use vad::{VadConfig, VadEvent, VadMode};
+
+/// 16-bit mono PCM chunks at the backend's sample rate
+pub type AudioChunk = Vec<i16>;
+
+/// Text of one recognised speech item. Partials carry the text so far and
+/// are superseded by the final with the same `item_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    pub item_id: String,
+    pub text: String,
+    pub is_final: bool,
+}
+
+impl Transcript {
+    pub fn partial(item_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self { item_id: item_id.into(), text: text.into(), is_final: false }
+    }
+
+    pub fn complete(item_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self { item_id: item_id.into(), text: text.into(), is_final: true }
+    }
+}
+
+/// Connection state of a streaming backend (`asr-status` event)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum AsrStatus {
+    Connecting,
+    Live,
+    Reconnecting { attempt: u32, error: String },
+    Failed { error: String },
+}
+
+fn default_seed_hotwords() -> bool {
+    true
+}
+
+/// Per-workspace recognition settings, applied when a session is created
+/// and again whenever they change mid-session. Unset fields fall back to
+/// the `[asr]` section and the backend's defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AsrSessionSettings {
+    /// Language code, or "auto" to let the server detect it (mixed sessions)
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Server VAD speech threshold (0.0-1.0)
+    #[serde(default)]
+    pub vad_threshold: Option<f32>,
+    /// Server VAD: silence that ends a segment
+    #[serde(default)]
+    pub silence_duration_ms: Option<u32>,
+    /// Custom vocabulary to bias recognition towards
+    #[serde(default)]
+    pub hotwords: Vec<String>,
+    /// Add the recording's headings and glossary terms to `hotwords`
+    #[serde(default = "default_seed_hotwords")]
+    pub seed_hotwords: bool,
+}
+
+impl AsrSessionSettings {
+    /// Silence the server waits for before it ends a segment
+    pub fn silence_ms(&self) -> u32 {
+        self.silence_duration_ms.unwrap_or(DEFAULT_SILENCE_MS)
+    }
+}
+
+impl Default for AsrSessionSettings {
+    fn default() -> Self {
+        Self {
+            language: None,
+            vad_threshold: None,
+            silence_duration_ms: None,
+            hotwords: Vec::new(),
+            seed_hotwords: default_seed_hotwords(),
+        }
+    }
+}
+
+/// Side channels of a recording: connection status out, reconnect requests
+/// and session settings in. Clones share the reconnect signal and settings.
+#[derive(Clone)]
+pub struct SessionControl {
+    status: Option<mpsc::UnboundedSender<AsrStatus>>,
+    reconnect: Arc<Notify>,
+    settings: Arc<watch::Sender<AsrSessionSettings>>,
+}
+
+impl Default for SessionControl {
+    fn default() -> Self {
+        Self {
+            status: None,
+            reconnect: Arc::new(Notify::new()),
+            settings: Arc::new(watch::channel(AsrSessionSettings::default()).0),
+        }
+    }
+}
+
+impl SessionControl {
+    /// Update the session settings; a live session is only told if they changed
+    pub fn set_settings(&self, settings: AsrSessionSettings) {
+        self.settings.send_if_modified(|current| {
+            let changed = *current != settings;
+            *current = settings;
+            changed
+        });
+    }
+
+    pub fn watch_settings(&self) -> watch::Receiver<AsrSessionSettings> {
+        self.settings.subscribe()
+    }
+
+    pub fn with_status(mut self, tx: mpsc::UnboundedSender<AsrStatus>) -> Self {
+        self.status = Some(tx);
+        self
+    }
+
+    pub fn report(&self, status: AsrStatus) {
+        if let Some(tx) = &self.status {
+            let _ = tx.send(status);
+        }
+    }
+
+    /// Ask the running backend to drop its connection and reconnect now
+    pub fn request_reconnect(&self) {
+        self.reconnect.notify_one();
+    }
+
+    pub async fn reconnect_requested(&self) {
+        self.reconnect.notified().await;
+    }
+}
+
+#[async_trait]
+pub trait AsrBackend: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &str;
+
+    /// Sample rate of the audio passed to `run`
+    fn sample_rate(&self) -> u32 {
+        16_000
+    }
+
+    /// Whether the service should capture the microphone and feed it to `run`.
+    /// Backends with their own audio source (or none) return false.
+    fn wants_microphone(&self) -> bool {
+        true
+    }
+
+    /// Transcribe until `cancel` fires or the audio ends, sending partial
+    /// (if the backend has them) and final transcripts to `transcripts`.
+    /// Network backends report their connection state through `control`.
+    async fn run(
+        &self,
+        audio: mpsc::UnboundedReceiver<AudioChunk>,
+        transcripts: mpsc::UnboundedSender<Transcript>,
+        control: SessionControl,
+        cancel: CancellationToken,
+    ) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsrBackendKind {
+    #[default]
+    Qwen,
+    OpenAI,
+    File,
+    Mock,
+}
+
+fn default_language() -> String {
+    "zh".to_string()
+}
+
+/// `[asr]` section of creek.toml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AsrConfig {
+    #[serde(default)]
+    pub backend: AsrBackendKind,
+    /// Realtime model (defaults per backend)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// WebSocket endpoint (defaults per backend)
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// `file`: WAV or raw 16-bit little-endian PCM to replay
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// `file`: sample rate of a raw PCM file (WAV files carry their own)
+    #[serde(default)]
+    pub pcm_sample_rate: Option<u32>,
+    /// `file`: backend that transcribes the replayed audio
+    #[serde(default)]
+    pub transcriber: AsrBackendKind,
+    /// `mock`: inline script
+    #[serde(default)]
+    pub script: Vec<ScriptedTranscript>,
+    /// `mock`: JSON file with a script, appended to the inline one
+    #[serde(default)]
+    pub script_file: Option<PathBuf>,
+    /// Local VAD on microphone audio (see vad.rs)
+    #[serde(default)]
+    pub vad: VadConfig,
+    /// Realtime backends: reconnect backoff and audio buffered meanwhile
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+}
+
+impl Default for AsrConfig {
+    fn default() -> Self {
+        Self {
+            backend: AsrBackendKind::default(),
+            model: None,
+            url: None,
+            api_key: None,
+            api_key_env: None,
+            language: default_language(),
+            file: None,
+            pcm_sample_rate: None,
+            transcriber: AsrBackendKind::default(),
+            script: Vec::new(),
+            script_file: None,
+            vad: VadConfig::default(),
+            reconnect: ReconnectConfig::default(),
+        }
+    }
+}
+
+impl AsrConfig {
+    /// Explicit key, then `api_key_env`, then the pipeline's key
+    fn resolve_api_key(&self, default_api_key: &str) -> String {
+        self.api_key.clone()
+            .or_else(|| self.api_key_env.as_ref().and_then(|var| std::env::var(var).ok()))
+            .unwrap_or_else(|| default_api_key.to_string())
+    }
+}
+
+/// Build the configured backend
+pub fn build_backend(config: &AsrConfig, default_api_key: &str) -> Result<Arc<dyn AsrBackend>> {
+    let backend: Arc<dyn AsrBackend> = match config.backend {
+        AsrBackendKind::Qwen => Arc::new(RealtimeBackend::from_config(
+            RealtimeFlavor::Qwen,
+            config,
+            config.resolve_api_key(default_api_key),
+        )),
+        AsrBackendKind::OpenAI => Arc::new(RealtimeBackend::from_config(
+            RealtimeFlavor::OpenAI,
+            config,
+            config.resolve_api_key(default_api_key),
+        )),
+        AsrBackendKind::File => {
+            let path = config.file.clone()
+                .context("[asr] backend = \"file\" requires `file`")?;
+            if config.transcriber == AsrBackendKind::File {
+                bail!("[asr] transcriber cannot be \"file\"");
+            }
+            let inner = build_backend(
+                &AsrConfig { backend: config.transcriber, ..config.clone() },
+                default_api_key,
+            )?;
+            let mut replay = FileReplayBackend::new(path, inner);
+            if let Some(rate) = config.pcm_sample_rate {
+                replay = replay.with_pcm_sample_rate(rate);
+            }
+            Arc::new(replay)
+        }
+        AsrBackendKind::Mock => {
+            let mut script = config.script.clone();
+            if let Some(path) = &config.script_file {
+                script.extend(mock::load_script(path)?);
+            }
+            Arc::new(ScriptedBackend::new(script))
+        }
+    };
+    Ok(backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_backend_from_toml() {
+        let config: AsrConfig = toml::from_str(
+            "backend = \"mock\"\nscript = [{ at_ms = 10, text = \"hi\" }]",
+        ).unwrap();
+        let backend = build_backend(&config, "sk").unwrap();
+        assert_eq!(backend.name(), "mock");
+        assert!(!backend.wants_microphone());
+
+        let config: AsrConfig = toml::from_str("backend = \"openai\"").unwrap();
+        let backend = build_backend(&config, "sk").unwrap();
+        assert_eq!(backend.name(), "openai");
+        assert_eq!(backend.sample_rate(), 24_000);
+
+        let config: AsrConfig = toml::from_str("backend = \"file\"").unwrap();
+        assert!(build_backend(&config, "sk").is_err());
+    }
+}
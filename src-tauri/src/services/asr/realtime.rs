@@ -0,0 +1,896 @@
+// Realtime WebSocket ASR
+//
+// DashScope's Qwen realtime ASR speaks the OpenAI realtime protocol, so both
+// share one client: PCM goes up as base64 `input_audio_buffer.append`
+// events, server VAD segments it, and each
+// `conversation.item.input_audio_transcription.completed` event carries a
+// final transcript. Interim text arrives before that as `.delta` events
+// (OpenAI, incremental) or `.text` events (Qwen, confirmed text + `stash`).
+// The flavors differ in endpoint, session payload and sample rate.
+//
+// A dropped connection is retried with exponential backoff. Audio the server
+// hasn't committed (sent since its last `input_audio_buffer.committed`, or
+// captured while disconnected) is kept in a bounded buffer and replayed on
+// the new connection, so an unstable uplink costs latency rather than words.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+use uuid::Uuid;
+
+use super::{AsrBackend, AsrConfig, AsrSessionSettings, AsrStatus, AudioChunk, SessionControl, Transcript};
+
+const QWEN_MODEL: &str = "qwen3-asr-flash-realtime";
+const QWEN_WS_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/realtime";
+const OPENAI_MODEL: &str = "gpt-4o-transcribe";
+const OPENAI_WS_URL: &str = "wss://api.openai.com/v1/realtime";
+
+/// How long to keep listening for transcripts after the audio ends
+const END_OF_INPUT_GRACE: Duration = Duration::from_secs(3);
+/// Server VAD threshold when the workspace sets none
+const DEFAULT_VAD_THRESHOLD: f32 = 0.5;
+/// Language value that leaves detection to the server
+const AUTO_LANGUAGE: &str = "auto";
+
+/// Give up on a connection attempt after this long
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// A connection this old counts as healthy even if the server said nothing
+const HEALTHY_AFTER: Duration = Duration::from_secs(10);
+
+type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+fn default_max_attempts() -> u32 {
+    20
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    10_000
+}
+
+fn default_buffer_secs() -> u32 {
+    30
+}
+
+/// `[asr.reconnect]` section of creek.toml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconnectConfig {
+    /// Consecutive failed attempts before the recording's ASR gives up
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Uncommitted audio kept for replay; older audio is dropped
+    #[serde(default = "default_buffer_secs")]
+    pub buffer_secs: u32,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            buffer_secs: default_buffer_secs(),
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Delay before the given (1-based) attempt: doubling, capped
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RealtimeFlavor {
+    /// DashScope realtime (`session.update`, 16 kHz)
+    Qwen,
+    /// OpenAI realtime transcription (`transcription_session.update`, 24 kHz)
+    OpenAI,
+}
+
+pub struct RealtimeBackend {
+    flavor: RealtimeFlavor,
+    url: String,
+    model: String,
+    api_key: String,
+    language: String,
+    reconnect: ReconnectConfig,
+}
+
+impl RealtimeBackend {
+    pub fn new(flavor: RealtimeFlavor, api_key: String) -> Self {
+        let (url, model) = match flavor {
+            RealtimeFlavor::Qwen => (QWEN_WS_URL, QWEN_MODEL),
+            RealtimeFlavor::OpenAI => (OPENAI_WS_URL, OPENAI_MODEL),
+        };
+        Self {
+            flavor,
+            url: url.to_string(),
+            model: model.to_string(),
+            api_key,
+            language: "zh".to_string(),
+            reconnect: ReconnectConfig::default(),
+        }
+    }
+
+    pub fn from_config(flavor: RealtimeFlavor, config: &AsrConfig, api_key: String) -> Self {
+        let mut backend = Self::new(flavor, api_key);
+        if let Some(url) = &config.url {
+            backend.url = url.clone();
+        }
+        if let Some(model) = &config.model {
+            backend.model = model.clone();
+        }
+        backend.language = config.language.clone();
+        backend.reconnect = config.reconnect.clone();
+        backend
+    }
+
+    fn endpoint(&self) -> String {
+        match self.flavor {
+            RealtimeFlavor::Qwen => format!("{}?model={}", self.url, self.model),
+            RealtimeFlavor::OpenAI => format!("{}?intent=transcription", self.url),
+        }
+    }
+
+    /// Session payload for the given workspace settings. Hotwords go in as
+    /// Qwen's `corpus` text / OpenAI's transcription `prompt`.
+    fn session_update(&self, settings: &AsrSessionSettings) -> Value {
+        let turn_detection = json!({
+            "type": "server_vad",
+            "threshold": settings.vad_threshold.unwrap_or(DEFAULT_VAD_THRESHOLD),
+            "silence_duration_ms": settings.silence_ms()
+        });
+        let mut transcription = match self.flavor {
+            RealtimeFlavor::Qwen => json!({}),
+            RealtimeFlavor::OpenAI => json!({ "model": self.model }),
+        };
+        let language = settings.language.as_deref().unwrap_or(&self.language);
+        if language != AUTO_LANGUAGE {
+            transcription["language"] = json!(language);
+        }
+        if !settings.hotwords.is_empty() {
+            let vocabulary = settings.hotwords.join(", ");
+            match self.flavor {
+                RealtimeFlavor::Qwen => transcription["corpus"] = json!({ "text": vocabulary }),
+                RealtimeFlavor::OpenAI => transcription["prompt"] = json!(vocabulary),
+            }
+        }
+
+        match self.flavor {
+            RealtimeFlavor::Qwen => json!({
+                "event_id": Uuid::new_v4().to_string(),
+                "type": "session.update",
+                "session": {
+                    "modalities": ["text"],
+                    "input_audio_format": "pcm",
+                    "sample_rate": self.sample_rate(),
+                    "input_audio_transcription": transcription,
+                    "turn_detection": turn_detection
+                }
+            }),
+            RealtimeFlavor::OpenAI => json!({
+                "type": "transcription_session.update",
+                "session": {
+                    "input_audio_format": "pcm16",
+                    "input_audio_transcription": transcription,
+                    "turn_detection": turn_detection
+                }
+            }),
+        }
+    }
+}
+
+/// Turns server events into partial and final transcripts per item
+#[derive(Default)]
+struct TranscriptAssembler {
+    /// OpenAI deltas accumulated so far, by item
+    partials: HashMap<String, String>,
+}
+
+impl TranscriptAssembler {
+    fn handle(&mut self, event: &Value) -> Option<Transcript> {
+        let item_id = event["item_id"].as_str().unwrap_or_default().to_string();
+        match event["type"].as_str()? {
+            "conversation.item.input_audio_transcription.delta" => {
+                let text = self.partials.entry(item_id.clone()).or_default();
+                text.push_str(event["delta"].as_str()?);
+                Some(Transcript::partial(item_id, text.clone()))
+            }
+            "conversation.item.input_audio_transcription.text" => {
+                let text = format!(
+                    "{}{}",
+                    event["text"].as_str().unwrap_or_default(),
+                    event["stash"].as_str().unwrap_or_default()
+                );
+                Some(Transcript::partial(item_id, text))
+            }
+            "conversation.item.input_audio_transcription.completed" => {
+                self.partials.remove(&item_id);
+                Some(Transcript::complete(item_id, event["transcript"].as_str()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Audio the server hasn't committed yet, oldest first
+struct AudioBacklog {
+    chunks: VecDeque<AudioChunk>,
+    samples: usize,
+    capacity: usize,
+    /// Where the oldest chunk starts in this connection's audio, in samples
+    start: usize,
+}
+
+impl AudioBacklog {
+    fn new(capacity: usize) -> Self {
+        Self { chunks: VecDeque::new(), samples: 0, capacity, start: 0 }
+    }
+
+    /// Returns how many samples were dropped to stay within capacity
+    fn push(&mut self, chunk: AudioChunk) -> usize {
+        self.samples += chunk.len();
+        self.chunks.push_back(chunk);
+        let mut dropped = 0;
+        while self.samples > self.capacity {
+            let Some(oldest) = self.chunks.pop_front() else { break };
+            self.samples -= oldest.len();
+            self.start += oldest.len();
+            dropped += oldest.len();
+        }
+        dropped
+    }
+
+    /// Drop what the server committed: the first `offset` samples of this
+    /// connection's audio. Audio sent after the commit point is kept.
+    fn commit(&mut self, offset: usize) {
+        while let Some(oldest) = self.chunks.front_mut() {
+            let end = self.start + oldest.len();
+            if end <= offset {
+                self.samples -= oldest.len();
+                self.start = end;
+                self.chunks.pop_front();
+            } else {
+                let committed = offset.saturating_sub(self.start);
+                oldest.drain(..committed);
+                self.samples -= committed;
+                self.start += committed;
+                break;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.chunks.clear();
+        self.samples = 0;
+    }
+
+    /// A new connection starts its audio with the replayed backlog
+    fn rebase(&mut self) {
+        self.start = 0;
+    }
+}
+
+enum SessionEnd {
+    /// Cancelled, or the input ended and its transcripts had time to arrive
+    Finished,
+    Dropped(String),
+    ReconnectRequested,
+}
+
+/// What the read loop tells the write loop
+enum ServerSignal {
+    /// The server sent something other than an error or session setup, so
+    /// the session works (key, quota and model were accepted)
+    Alive,
+    /// Audio committed up to the server VAD's last speech end, in ms of this
+    /// connection's audio, when known
+    Committed(Option<u64>),
+}
+
+/// What outlives a single connection
+struct StreamState {
+    audio: mpsc::UnboundedReceiver<AudioChunk>,
+    input_open: bool,
+    /// The last connection proved healthy; consecutive failures start over
+    healthy: bool,
+    backlog: AudioBacklog,
+    transcripts: mpsc::UnboundedSender<Transcript>,
+    control: SessionControl,
+    cancel: CancellationToken,
+}
+
+impl StreamState {
+    fn buffer(&mut self, chunk: AudioChunk) {
+        let dropped = self.backlog.push(chunk);
+        if dropped > 0 {
+            debug!("[ASR] Reconnect buffer full, dropped {} samples", dropped);
+        }
+    }
+
+    /// Sit out a backoff delay, buffering audio. False if cancelled.
+    async fn wait(&mut self, delay: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + delay;
+        loop {
+            tokio::select! {
+                _ = self.cancel.cancelled() => return false,
+                _ = tokio::time::sleep_until(deadline) => return true,
+                chunk = self.audio.recv(), if self.input_open => match chunk {
+                    Some(chunk) => self.buffer(chunk),
+                    None => self.input_open = false,
+                },
+            }
+        }
+    }
+}
+
+impl RealtimeBackend {
+    async fn connect(&self) -> Result<WsStream> {
+        let url = Url::parse(&self.endpoint())?;
+
+        // Generate WebSocket Key
+        let key_bytes = Uuid::new_v4().into_bytes();
+        let key_b64 = BASE64.encode(key_bytes);
+
+        let request = http::Request::builder()
+            .uri(url.as_str())
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("OpenAI-Beta", "realtime=v1")
+            .header("Host", url.host_str().unwrap_or_default())
+            .header("Connection", "Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", key_b64)
+            .body(())?;
+
+        let (ws_stream, _) = tokio::time::timeout(CONNECT_TIMEOUT, connect_async(request))
+            .await
+            .context("Connection timed out")??;
+        Ok(ws_stream)
+    }
+
+    fn append_event(samples: &[i16]) -> Message {
+        let pcm_bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let audio_event = json!({
+            "event_id": Uuid::new_v4().to_string(),
+            "type": "input_audio_buffer.append",
+            "audio": BASE64.encode(&pcm_bytes)
+        });
+        Message::Text(audio_event.to_string().into())
+    }
+
+    /// One connection's lifetime: replay the backlog, then stream live audio
+    async fn stream(&self, ws_stream: WsStream, state: &mut StreamState) -> SessionEnd {
+        let (mut write, mut read) = ws_stream.split();
+
+        // Send Init Event (Server VAD, language, hotwords)
+        let mut settings = state.control.watch_settings();
+        let update = self.session_update(&settings.borrow_and_update());
+        if let Err(e) = write.send(Message::Text(update.to_string().into())).await {
+            return SessionEnd::Dropped(e.to_string());
+        }
+
+        // Handle Incoming Messages (Transcript), until this connection ends.
+        // `signals` closes when the server goes away.
+        let read_token = state.cancel.child_token();
+        let reader_stop = read_token.clone();
+        let (signal_tx, mut signals) = mpsc::unbounded_channel::<ServerSignal>();
+        let transcripts = state.transcripts.clone();
+        let connected_at = tokio::time::Instant::now();
+        tokio::spawn(async move {
+            let mut assembler = TranscriptAssembler::default();
+            let mut speech_end_ms = None;
+            let mut alive = false;
+            loop {
+                tokio::select! {
+                    _ = read_token.cancelled() => {
+                        info!("ASR Read Loop cancelled");
+                        break;
+                    }
+                    msg = read.next() => {
+                        match msg {
+                            Some(Ok(Message::Text(text))) => {
+                                debug!("ASR Message: {}", text);
+                                if let Ok(data) = serde_json::from_str::<Value>(&text) {
+                                    let kind = data["type"].as_str().unwrap_or_default();
+                                    if !alive && kind != "error" && !kind.contains("session.") {
+                                        alive = true;
+                                        let _ = signal_tx.send(ServerSignal::Alive);
+                                    }
+                                    if data["type"] == "error" {
+                                        error!("ASR server error: {}", data["error"]);
+                                    } else if data["type"] == "input_audio_buffer.speech_stopped" {
+                                        speech_end_ms = data["audio_end_ms"].as_u64();
+                                    } else if data["type"] == "input_audio_buffer.committed" {
+                                        let _ = signal_tx.send(ServerSignal::Committed(speech_end_ms.take()));
+                                    } else if let Some(transcript) = assembler.handle(&data) {
+                                        if transcript.is_final {
+                                            info!("ASR Transcript: {}", transcript.text);
+                                        }
+                                        let _ = transcripts.send(transcript);
+                                    }
+                                }
+                            }
+                            Some(Ok(Message::Close(_))) => break,
+                            Some(Err(e)) => {
+                                error!("WebSocket read error: {}", e);
+                                break;
+                            },
+                            None => break,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        });
+
+        let end = 'session: {
+            state.backlog.rebase();
+            if !state.backlog.chunks.is_empty() {
+                info!(
+                    "[ASR] Replaying {:.1}s of uncommitted audio",
+                    state.backlog.samples as f32 / self.sample_rate() as f32
+                );
+                for chunk in &state.backlog.chunks {
+                    if let Err(e) = write.send(Self::append_event(chunk)).await {
+                        break 'session SessionEnd::Dropped(e.to_string());
+                    }
+                }
+            }
+            state.control.report(AsrStatus::Live);
+
+            // Stream Audio to WebSocket
+            loop {
+                if !state.input_open {
+                    // Input ended (file replay): let the server flush the last segment
+                    tokio::select! {
+                        _ = state.cancel.cancelled() => {}
+                        _ = tokio::time::sleep(END_OF_INPUT_GRACE) => {}
+                    }
+                    break SessionEnd::Finished;
+                }
+                tokio::select! {
+                    _ = state.cancel.cancelled() => {
+                        info!("Recording cancelled");
+                        break SessionEnd::Finished;
+                    }
+                    _ = state.control.reconnect_requested() => break SessionEnd::ReconnectRequested,
+                    Ok(()) = settings.changed() => {
+                        let update = self.session_update(&settings.borrow_and_update());
+                        info!("[ASR] Session settings changed, updating live session");
+                        if let Err(e) = write.send(Message::Text(update.to_string().into())).await {
+                            break SessionEnd::Dropped(e.to_string());
+                        }
+                    }
+                    signal = signals.recv() => match signal {
+                        Some(ServerSignal::Alive) => state.healthy = true,
+                        Some(ServerSignal::Committed(Some(end_ms))) => {
+                            let offset = end_ms as usize * self.sample_rate() as usize / 1000;
+                            state.backlog.commit(offset);
+                        }
+                        // No position: everything sent so far is committed
+                        Some(ServerSignal::Committed(None)) => state.backlog.clear(),
+                        None => break SessionEnd::Dropped("connection closed by server".to_string()),
+                    },
+                    chunk = state.audio.recv() => match chunk {
+                        Some(samples) => {
+                            let sent = write.send(Self::append_event(&samples)).await;
+                            // Kept until the server commits it
+                            state.buffer(samples);
+                            if let Err(e) = sent {
+                                error!("Failed to send audio to WS: {}", e);
+                                break SessionEnd::Dropped(e.to_string());
+                            }
+                        }
+                        None => state.input_open = false,
+                    },
+                }
+            }
+        };
+
+        reader_stop.cancel();
+        if connected_at.elapsed() >= HEALTHY_AFTER {
+            state.healthy = true;
+        }
+        // Close WS cleanly if possible
+        let _ = write.close().await;
+        end
+    }
+}
+
+#[async_trait]
+impl AsrBackend for RealtimeBackend {
+    fn name(&self) -> &str {
+        match self.flavor {
+            RealtimeFlavor::Qwen => "qwen",
+            RealtimeFlavor::OpenAI => "openai",
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self.flavor {
+            RealtimeFlavor::Qwen => 16_000,
+            RealtimeFlavor::OpenAI => 24_000,
+        }
+    }
+
+    async fn run(
+        &self,
+        audio: mpsc::UnboundedReceiver<AudioChunk>,
+        transcripts: mpsc::UnboundedSender<Transcript>,
+        control: SessionControl,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let capacity = self.sample_rate() as usize * self.reconnect.buffer_secs as usize;
+        let mut state = StreamState {
+            audio,
+            input_open: true,
+            healthy: false,
+            backlog: AudioBacklog::new(capacity),
+            transcripts,
+            control,
+            cancel,
+        };
+        let mut failures = 0;
+        state.control.report(AsrStatus::Connecting);
+
+        loop {
+            let connected = tokio::select! {
+                _ = state.cancel.cancelled() => return Ok(()),
+                connected = self.connect() => connected,
+            };
+            let end = match connected {
+                Ok(ws_stream) => {
+                    info!("[ASR] Connected to {} realtime WebSocket", self.name());
+                    self.stream(ws_stream, &mut state).await
+                }
+                Err(e) => SessionEnd::Dropped(format!("{:#}", e)),
+            };
+            // Only a session that worked ends a run of failures; a server that
+            // accepts the connection and then rejects the session doesn't
+            if std::mem::take(&mut state.healthy) {
+                failures = 0;
+            }
+
+            let (error, delay, cause) = match end {
+                SessionEnd::Finished => return Ok(()),
+                SessionEnd::ReconnectRequested => {
+                    info!("[ASR] Reconnect requested");
+                    ("reconnect requested".to_string(), Duration::ZERO, "requested")
+                }
+                SessionEnd::Dropped(error) => {
+                    failures += 1;
+                    if failures > self.reconnect.max_attempts {
+                        error!("[ASR] Giving up after {} failed attempts: {}", failures - 1, error);
+                        state.control.report(AsrStatus::Failed { error: error.clone() });
+                        bail!("ASR connection lost: {}", error);
+                    }
+                    (error, self.reconnect.backoff(failures), "dropped")
+                }
+            };
+            tracing::info!(monotonic_counter.asr_reconnects = 1, cause);
+            warn!(
+                "[ASR] Connection lost ({}), reconnecting in {}ms (attempt {}/{})",
+                error,
+                delay.as_millis(),
+                failures.max(1),
+                self.reconnect.max_attempts
+            );
+            state.control.report(AsrStatus::Reconnecting { attempt: failures.max(1), error });
+            if !state.wait(delay).await {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::asr::DEFAULT_SILENCE_MS;
+
+    #[test]
+    fn test_flavor_session_payloads() {
+        let config = AsrConfig {
+            language: "en".to_string(),
+            ..Default::default()
+        };
+        let qwen = RealtimeBackend::from_config(RealtimeFlavor::Qwen, &config, "sk".into());
+        assert_eq!(qwen.endpoint(), format!("{}?model={}", QWEN_WS_URL, QWEN_MODEL));
+        let update = qwen.session_update(&AsrSessionSettings::default());
+        assert_eq!(update["type"], "session.update");
+        assert_eq!(update["session"]["sample_rate"], 16_000);
+        assert_eq!(update["session"]["input_audio_transcription"]["language"], "en");
+        assert_eq!(update["session"]["turn_detection"]["silence_duration_ms"], DEFAULT_SILENCE_MS);
+
+        let openai = RealtimeBackend::from_config(RealtimeFlavor::OpenAI, &config, "sk".into());
+        assert!(openai.endpoint().ends_with("?intent=transcription"));
+        let update = openai.session_update(&AsrSessionSettings::default());
+        assert_eq!(update["type"], "transcription_session.update");
+        assert_eq!(update["session"]["input_audio_transcription"]["model"], OPENAI_MODEL);
+    }
+
+    #[test]
+    fn test_workspace_settings_shape_the_session() {
+        let settings = AsrSessionSettings {
+            language: Some("auto".to_string()),
+            vad_threshold: Some(0.3),
+            silence_duration_ms: Some(1_200),
+            hotwords: vec!["Creek".to_string(), "LanceDB".to_string()],
+            ..Default::default()
+        };
+        let qwen = RealtimeBackend::new(RealtimeFlavor::Qwen, "sk".into());
+        let update = qwen.session_update(&settings);
+        let transcription = &update["session"]["input_audio_transcription"];
+        assert!(transcription.get("language").is_none());
+        assert_eq!(transcription["corpus"]["text"], "Creek, LanceDB");
+        assert_eq!(update["session"]["turn_detection"]["threshold"].as_f64().unwrap() as f32, 0.3);
+        assert_eq!(update["session"]["turn_detection"]["silence_duration_ms"], 1_200);
+
+        let openai = RealtimeBackend::new(RealtimeFlavor::OpenAI, "sk".into());
+        let update = openai.session_update(&AsrSessionSettings { language: Some("en".to_string()), ..settings });
+        let transcription = &update["session"]["input_audio_transcription"];
+        assert_eq!(transcription["language"], "en");
+        assert_eq!(transcription["prompt"], "Creek, LanceDB");
+    }
+
+    #[test]
+    fn test_partials_accumulate_and_final_replaces_them() {
+        let mut assembler = TranscriptAssembler::default();
+        let delta = |text: &str| json!({
+            "type": "conversation.item.input_audio_transcription.delta",
+            "item_id": "item_1",
+            "delta": text
+        });
+        assert_eq!(assembler.handle(&delta("Hel")), Some(Transcript::partial("item_1", "Hel")));
+        assert_eq!(assembler.handle(&delta("lo")), Some(Transcript::partial("item_1", "Hello")));
+
+        let completed = json!({
+            "type": "conversation.item.input_audio_transcription.completed",
+            "item_id": "item_1",
+            "transcript": "Hello."
+        });
+        assert_eq!(assembler.handle(&completed), Some(Transcript::complete("item_1", "Hello.")));
+        assert!(assembler.partials.is_empty());
+
+        // Qwen resends the whole interim text: confirmed part plus unconfirmed stash
+        let qwen = json!({
+            "type": "conversation.item.input_audio_transcription.text",
+            "item_id": "item_2",
+            "text": "今天",
+            "stash": "天气"
+        });
+        assert_eq!(assembler.handle(&qwen), Some(Transcript::partial("item_2", "今天天气")));
+        assert_eq!(assembler.handle(&json!({ "type": "session.updated" })), None);
+    }
+
+    #[test]
+    fn test_backoff_doubles_to_cap_and_backlog_is_bounded() {
+        let config = ReconnectConfig { initial_backoff_ms: 500, max_backoff_ms: 3_000, ..Default::default() };
+        let delays: Vec<u128> = (1..=5).map(|attempt| config.backoff(attempt).as_millis()).collect();
+        assert_eq!(delays, vec![500, 1_000, 2_000, 3_000, 3_000]);
+
+        let mut backlog = AudioBacklog::new(300);
+        assert_eq!(backlog.push(vec![1; 100]), 0);
+        assert_eq!(backlog.push(vec![2; 100]), 0);
+        assert_eq!(backlog.push(vec![3; 150]), 100);
+        assert_eq!(backlog.samples, 250);
+        assert_eq!(backlog.chunks.front().unwrap()[0], 2);
+
+        // Samples 0..100 were dropped; commit up to 150 cuts into the 2s
+        backlog.commit(150);
+        assert_eq!(backlog.samples, 200);
+        assert_eq!(backlog.chunks.front().unwrap().len(), 50);
+        backlog.commit(200);
+        assert_eq!(backlog.chunks.len(), 1);
+        assert_eq!(backlog.samples, 150);
+        backlog.commit(100);
+        assert_eq!(backlog.samples, 150);
+    }
+
+    /// First sample of each `input_audio_buffer.append` until `count` arrive
+    async fn read_appends(ws: &mut WebSocketStream<tokio::net::TcpStream>, count: usize) -> Vec<i16> {
+        let mut firsts = Vec::new();
+        while firsts.len() < count {
+            let Some(Ok(Message::Text(text))) = ws.next().await else { break };
+            let event: Value = serde_json::from_str(&text).unwrap();
+            if event["type"] == "input_audio_buffer.append" {
+                let bytes = BASE64.decode(event["audio"].as_str().unwrap()).unwrap();
+                firsts.push(i16::from_le_bytes([bytes[0], bytes[1]]));
+            }
+        }
+        firsts
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_replays_uncommitted_audio_and_updates_live_session() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = AsrConfig {
+            url: Some(format!("ws://{}", listener.local_addr().unwrap())),
+            reconnect: ReconnectConfig { initial_backoff_ms: 10, ..Default::default() },
+            ..Default::default()
+        };
+        let backend = RealtimeBackend::from_config(RealtimeFlavor::Qwen, &config, "sk".into());
+
+        let server = tokio::spawn(async move {
+            // First connection drops after two chunks, before committing them
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            assert_eq!(read_appends(&mut ws, 2).await, vec![1, 2]);
+            drop(ws);
+
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let replayed = read_appends(&mut ws, 3).await;
+            for event in [
+                json!({ "type": "input_audio_buffer.committed" }),
+                json!({
+                    "type": "conversation.item.input_audio_transcription.completed",
+                    "item_id": "item_1",
+                    "transcript": "still here"
+                }),
+            ] {
+                ws.send(Message::Text(event.to_string().into())).await.unwrap();
+            }
+            // Settings changed mid-session: pushed without reconnecting
+            let Some(Ok(Message::Text(text))) = ws.next().await else { panic!("expected session.update") };
+            let update: Value = serde_json::from_str(&text).unwrap();
+            // Hold the connection until the client goes
+            while let Some(Ok(_)) = ws.next().await {}
+            (replayed, update)
+        });
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (transcript_tx, mut transcript_rx) = mpsc::unbounded_channel();
+        let (status_tx, mut status_rx) = mpsc::unbounded_channel();
+        let cancel = CancellationToken::new();
+        let control = SessionControl::default().with_status(status_tx);
+        let settings = control.clone();
+        let token = cancel.clone();
+        let client = tokio::spawn(async move { backend.run(audio_rx, transcript_tx, control, token).await });
+
+        audio_tx.send(vec![1i16; 160]).unwrap();
+        audio_tx.send(vec![2i16; 160]).unwrap();
+        assert_eq!(status_rx.recv().await, Some(AsrStatus::Connecting));
+        assert_eq!(status_rx.recv().await, Some(AsrStatus::Live));
+        assert!(matches!(status_rx.recv().await, Some(AsrStatus::Reconnecting { attempt: 1, .. })));
+        audio_tx.send(vec![3i16; 160]).unwrap();
+        assert_eq!(status_rx.recv().await, Some(AsrStatus::Live));
+
+        assert_eq!(transcript_rx.recv().await, Some(Transcript::complete("item_1", "still here")));
+        settings.set_settings(AsrSessionSettings { hotwords: vec!["Creek".to_string()], ..Default::default() });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancel.cancel();
+        client.await.unwrap().unwrap();
+        let (replayed, update) = server.await.unwrap();
+        assert_eq!(replayed, vec![1, 2, 3]);
+        assert_eq!(update["session"]["input_audio_transcription"]["corpus"]["text"], "Creek");
+    }
+
+    #[tokio::test]
+    async fn test_commit_keeps_audio_sent_after_the_commit_point() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = AsrConfig {
+            url: Some(format!("ws://{}", listener.local_addr().unwrap())),
+            reconnect: ReconnectConfig { initial_backoff_ms: 10, ..Default::default() },
+            ..Default::default()
+        };
+        let backend = RealtimeBackend::from_config(RealtimeFlavor::Qwen, &config, "sk".into());
+        let (drop_tx, drop_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let server = tokio::spawn(async move {
+            // Speech ended 15 ms in (240 samples), but 480 had been sent by the commit
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            assert_eq!(read_appends(&mut ws, 3).await, vec![0, 160, 320]);
+            for event in [
+                json!({ "type": "input_audio_buffer.speech_stopped", "audio_end_ms": 15 }),
+                json!({ "type": "input_audio_buffer.committed" }),
+                json!({
+                    "type": "conversation.item.input_audio_transcription.completed",
+                    "item_id": "item_1",
+                    "transcript": "first"
+                }),
+            ] {
+                ws.send(Message::Text(event.to_string().into())).await.unwrap();
+            }
+            drop_rx.await.unwrap();
+            drop(ws);
+
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let replayed = read_appends(&mut ws, 2).await;
+            while let Some(Ok(_)) = ws.next().await {}
+            replayed
+        });
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (transcript_tx, mut transcript_rx) = mpsc::unbounded_channel();
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let client = tokio::spawn(async move {
+            backend.run(audio_rx, transcript_tx, SessionControl::default(), token).await
+        });
+
+        // Ramp audio: each sample is its own offset
+        for chunk in 0..3i16 {
+            audio_tx.send((chunk * 160..(chunk + 1) * 160).collect()).unwrap();
+        }
+        assert_eq!(transcript_rx.recv().await, Some(Transcript::complete("item_1", "first")));
+        // The commit came first; give the client a moment to apply it
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop_tx.send(()).unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        cancel.cancel();
+        client.await.unwrap().unwrap();
+        // Only the uncommitted part of the second chunk onwards is replayed
+        assert_eq!(server.await.unwrap(), vec![240, 320]);
+    }
+
+    #[tokio::test]
+    async fn test_server_rejecting_every_session_exhausts_the_attempts() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = AsrConfig {
+            url: Some(format!("ws://{}", listener.local_addr().unwrap())),
+            reconnect: ReconnectConfig { max_attempts: 2, initial_backoff_ms: 10, ..Default::default() },
+            ..Default::default()
+        };
+        let backend = RealtimeBackend::from_config(RealtimeFlavor::Qwen, &config, "sk".into());
+
+        // Accepts the upgrade, then refuses the session (bad key, quota, model)
+        tokio::spawn(async move {
+            loop {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                for event in [
+                    json!({ "type": "session.created" }),
+                    json!({ "type": "error", "error": { "message": "invalid api key" } }),
+                ] {
+                    ws.send(Message::Text(event.to_string().into())).await.unwrap();
+                }
+                let _ = ws.close(None).await;
+            }
+        });
+
+        let (_audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (transcript_tx, _transcript_rx) = mpsc::unbounded_channel();
+        let (status_tx, mut status_rx) = mpsc::unbounded_channel();
+        let control = SessionControl::default().with_status(status_tx);
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            backend.run(audio_rx, transcript_tx, control, CancellationToken::new()),
+        ).await.expect("kept reconnecting");
+        assert!(result.is_err());
+
+        let mut statuses = Vec::new();
+        while let Ok(status) = status_rx.try_recv() {
+            statuses.push(status);
+        }
+        assert!(matches!(statuses.last(), Some(AsrStatus::Failed { .. })));
+        let attempts: Vec<u32> = statuses.iter()
+            .filter_map(|s| match s { AsrStatus::Reconnecting { attempt, .. } => Some(*attempt), _ => None })
+            .collect();
+        assert_eq!(attempts, vec![1, 2]);
+    }
+}
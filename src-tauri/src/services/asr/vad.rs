@@ -0,0 +1,461 @@
+// Local Voice Activity Detection
+//
+// Sits between the microphone and the backend. Audio is split into 20 ms
+// frames and each frame is classified as voiced or not; only speech (plus a
+// short pre-roll, and the trailing silence the server needs to close its own
+// segment) goes upstream, so long silent stretches cost nothing. Boundaries
+// are reported as `SpeechStart` / `SpeechEnd` events, which the pipeline
+// uses to end turns.
+//
+// Two frame classifiers:
+// - `energy`:     level above an adaptive noise floor (default)
+// - `classifier`: energy plus zero-crossing rate and speech-band energy
+//                 ratio, in the spirit of WebRTC's VAD; rejects hiss, hum
+//                 and clicks that are loud but not speech-shaped
+//
+//   [asr.vad]
+//   mode = "classifier"
+//   threshold_db = 9.0
+//   end_ms = 900
+//
+// The gate never ends a segment before the server would: while a session
+// runs, `end_ms` is raised to its `silence_duration_ms` plus a margin, or
+// the server would stop receiving audio before it saw enough silence.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::AudioChunk;
+
+const FRAME_MS: u32 = 20;
+/// Level assumed before any silence has been measured
+const INITIAL_FLOOR_DB: f32 = -60.0;
+const SILENCE_DB: f32 = -96.0;
+/// Silence forwarded beyond the server's own end-of-segment wait
+const SERVER_SILENCE_MARGIN_MS: u32 = 200;
+
+fn frames(ms: u32) -> u32 {
+    ms.div_ceil(FRAME_MS).max(1)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VadMode {
+    #[default]
+    Energy,
+    Classifier,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_threshold_db() -> f32 {
+    9.0
+}
+
+fn default_min_level_db() -> f32 {
+    -50.0
+}
+
+fn default_start_ms() -> u32 {
+    60
+}
+
+fn default_end_ms() -> u32 {
+    900
+}
+
+fn default_pre_roll_ms() -> u32 {
+    300
+}
+
+/// `[asr.vad]` section of creek.toml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VadConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mode: VadMode,
+    /// dB above the noise floor that counts as speech
+    #[serde(default = "default_threshold_db")]
+    pub threshold_db: f32,
+    /// Frames quieter than this (dBFS) are never speech
+    #[serde(default = "default_min_level_db")]
+    pub min_level_db: f32,
+    /// Voiced time needed to start a segment
+    #[serde(default = "default_start_ms")]
+    pub start_ms: u32,
+    /// Silence needed to end a segment; raised to the server's silence
+    /// duration plus a margin when that is longer
+    #[serde(default = "default_end_ms")]
+    pub end_ms: u32,
+    /// Audio sent ahead of the detected start, so onsets aren't clipped
+    #[serde(default = "default_pre_roll_ms")]
+    pub pre_roll_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            mode: VadMode::default(),
+            threshold_db: default_threshold_db(),
+            min_level_db: default_min_level_db(),
+            start_ms: default_start_ms(),
+            end_ms: default_end_ms(),
+            pre_roll_ms: default_pre_roll_ms(),
+        }
+    }
+}
+
+/// Speech boundary, in milliseconds of audio since recording started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VadEvent {
+    SpeechStart { at_ms: u64 },
+    SpeechEnd { at_ms: u64, duration_ms: u64 },
+}
+
+/// Audio to forward and boundaries crossed, for one input chunk
+#[derive(Debug, Default)]
+pub struct VadOutput {
+    pub audio: AudioChunk,
+    pub events: Vec<VadEvent>,
+}
+
+/// Per-frame voiced / unvoiced decision
+struct FrameClassifier {
+    mode: VadMode,
+    threshold_db: f32,
+    min_level_db: f32,
+    noise_floor_db: f32,
+    /// One-pole filter states for the speech-band split
+    low_state: f32,
+    high_state: f32,
+    low_alpha: f32,
+    high_alpha: f32,
+}
+
+impl FrameClassifier {
+    fn new(config: &VadConfig, sample_rate: u32) -> Self {
+        // Speech band ~250 Hz .. 3.5 kHz
+        let alpha = |cutoff_hz: f32| {
+            let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff_hz);
+            let dt = 1.0 / sample_rate as f32;
+            dt / (rc + dt)
+        };
+        Self {
+            mode: config.mode,
+            threshold_db: config.threshold_db,
+            min_level_db: config.min_level_db,
+            noise_floor_db: INITIAL_FLOOR_DB,
+            low_state: 0.0,
+            high_state: 0.0,
+            low_alpha: alpha(250.0),
+            high_alpha: alpha(3_500.0),
+        }
+    }
+
+    fn is_voiced(&mut self, frame: &[i16]) -> bool {
+        let samples: Vec<f32> = frame.iter().map(|&s| s as f32 / 32_768.0).collect();
+        let energy = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        let level_db = if energy > 0.0 { 10.0 * energy.log10() } else { SILENCE_DB };
+
+        let loud = level_db > (self.noise_floor_db + self.threshold_db).max(self.min_level_db);
+        let voiced = loud && match self.mode {
+            VadMode::Energy => true,
+            VadMode::Classifier => self.is_speech_shaped(&samples, energy),
+        };
+
+        // Track the floor quickly through silence, slowly through speech
+        let rate = if voiced { 0.002 } else { 0.05 };
+        self.noise_floor_db += (level_db - self.noise_floor_db) * rate;
+        self.noise_floor_db = self.noise_floor_db.max(SILENCE_DB);
+        voiced
+    }
+
+    /// Voiced speech has most of its energy between ~250 Hz and 3.5 kHz and a
+    /// moderate zero-crossing rate; hiss crosses constantly, hum and clicks
+    /// sit outside the band
+    fn is_speech_shaped(&mut self, samples: &[f32], energy: f32) -> bool {
+        let crossings = samples.windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        let zcr = crossings as f32 / samples.len() as f32;
+
+        let mut band_energy = 0.0;
+        for &s in samples {
+            self.low_state += self.low_alpha * (s - self.low_state);
+            self.high_state += self.high_alpha * (s - self.high_state);
+            // Below 3.5 kHz minus below 250 Hz
+            let band = self.high_state - self.low_state;
+            band_energy += band * band;
+        }
+        let band_ratio = band_energy / samples.len() as f32 / energy;
+
+        // Weighted evidence, like a tiny linear classifier
+        let score = 2.0 * (band_ratio - 0.5) + 1.5 * (0.25 - (zcr - 0.12).abs()) * 4.0;
+        score > 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Silence { voiced_frames: u32, run_start_ms: u64 },
+    Speech { started_ms: u64, last_voiced_ms: u64, silent_frames: u32 },
+}
+
+/// Frames audio, classifies it and gates what reaches the backend
+pub struct VadGate {
+    enabled: bool,
+    classifier: FrameClassifier,
+    frame_len: usize,
+    start_frames: u32,
+    /// Configured `end_ms`, the floor for `end_frames`
+    end_ms: u32,
+    end_frames: u32,
+    pre_roll_frames: usize,
+    partial: AudioChunk,
+    pre_roll: VecDeque<AudioChunk>,
+    state: State,
+    /// Audio processed so far
+    elapsed_ms: u64,
+}
+
+impl VadGate {
+    pub fn new(config: &VadConfig, sample_rate: u32) -> Self {
+        Self {
+            enabled: config.enabled,
+            classifier: FrameClassifier::new(config, sample_rate),
+            frame_len: (sample_rate * FRAME_MS / 1000) as usize,
+            start_frames: frames(config.start_ms),
+            end_ms: config.end_ms,
+            end_frames: frames(config.end_ms),
+            pre_roll_frames: (config.pre_roll_ms / FRAME_MS) as usize,
+            partial: Vec::new(),
+            pre_roll: VecDeque::new(),
+            state: State::Silence { voiced_frames: 0, run_start_ms: 0 },
+            elapsed_ms: 0,
+        }
+    }
+
+    /// Keep forwarding silence until the server has ended its segment too
+    pub fn follow_server_silence(&mut self, silence_ms: u32) {
+        self.end_frames = frames(self.end_ms.max(silence_ms + SERVER_SILENCE_MARGIN_MS));
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        matches!(self.state, State::Speech { .. })
+    }
+
+    pub fn process(&mut self, chunk: &[i16]) -> VadOutput {
+        if !self.enabled {
+            return VadOutput { audio: chunk.to_vec(), events: Vec::new() };
+        }
+
+        let mut output = VadOutput::default();
+        self.partial.extend_from_slice(chunk);
+        let mut offset = 0;
+        while self.partial.len() - offset >= self.frame_len {
+            let frame = self.partial[offset..offset + self.frame_len].to_vec();
+            offset += self.frame_len;
+            self.process_frame(frame, &mut output);
+        }
+        self.partial.drain(..offset);
+        output
+    }
+
+    fn process_frame(&mut self, frame: AudioChunk, output: &mut VadOutput) {
+        let frame_start_ms = self.elapsed_ms;
+        self.elapsed_ms += FRAME_MS as u64;
+        let voiced = self.classifier.is_voiced(&frame);
+
+        match &mut self.state {
+            State::Silence { voiced_frames, run_start_ms } => {
+                if !voiced {
+                    *voiced_frames = 0;
+                } else {
+                    if *voiced_frames == 0 {
+                        *run_start_ms = frame_start_ms;
+                    }
+                    *voiced_frames += 1;
+                }
+
+                if *voiced_frames >= self.start_frames {
+                    let started_ms = *run_start_ms;
+                    output.events.push(VadEvent::SpeechStart { at_ms: started_ms });
+                    for buffered in self.pre_roll.drain(..) {
+                        output.audio.extend(buffered);
+                    }
+                    output.audio.extend(frame);
+                    self.state = State::Speech {
+                        started_ms,
+                        last_voiced_ms: self.elapsed_ms,
+                        silent_frames: 0,
+                    };
+                } else {
+                    // Held back; forwarded as pre-roll if speech starts
+                    self.pre_roll.push_back(frame);
+                    // The run so far plus `pre_roll_ms` of what came before it
+                    while self.pre_roll.len() > self.pre_roll_frames + *voiced_frames as usize {
+                        self.pre_roll.pop_front();
+                    }
+                }
+            }
+            State::Speech { started_ms, last_voiced_ms, silent_frames } => {
+                // Trailing silence still goes up, so server-side VAD closes the segment too
+                output.audio.extend(frame);
+                if voiced {
+                    *last_voiced_ms = self.elapsed_ms;
+                    *silent_frames = 0;
+                } else {
+                    *silent_frames += 1;
+                }
+
+                if *silent_frames >= self.end_frames {
+                    output.events.push(VadEvent::SpeechEnd {
+                        at_ms: self.elapsed_ms,
+                        duration_ms: *last_voiced_ms - *started_ms,
+                    });
+                    self.state = State::Silence { voiced_frames: 0, run_start_ms: 0 };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn tone(ms: u32, freq: f32, amplitude: f32) -> AudioChunk {
+        let n = (RATE * ms / 1000) as usize;
+        (0..n)
+            .map(|i| {
+                let t = i as f32 / RATE as f32;
+                (amplitude * (2.0 * std::f32::consts::PI * freq * t).sin() * 32_767.0) as i16
+            })
+            .collect()
+    }
+
+    fn silence(ms: u32) -> AudioChunk {
+        vec![0; (RATE * ms / 1000) as usize]
+    }
+
+    /// Deterministic white-ish noise
+    fn hiss(ms: u32, amplitude: f32) -> AudioChunk {
+        let mut seed: u32 = 0x1234_5678;
+        (0..(RATE * ms / 1000) as usize)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                ((seed as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude * 32_767.0) as i16
+            })
+            .collect()
+    }
+
+    fn run(gate: &mut VadGate, audio: &[i16]) -> (usize, Vec<VadEvent>) {
+        let mut forwarded = 0;
+        let mut events = Vec::new();
+        // Odd-sized chunks, like device callbacks
+        for chunk in audio.chunks(441) {
+            let out = gate.process(chunk);
+            forwarded += out.audio.len();
+            events.extend(out.events);
+        }
+        (forwarded, events)
+    }
+
+    #[test]
+    fn test_speech_is_bracketed_and_silence_suppressed() {
+        let mut gate = VadGate::new(&VadConfig::default(), RATE);
+        let mut audio = silence(2_000);
+        audio.extend(tone(1_000, 220.0, 0.3));
+        audio.extend(silence(3_000));
+
+        let (forwarded, events) = run(&mut gate, &audio);
+        assert_eq!(events.len(), 2);
+        let VadEvent::SpeechStart { at_ms } = events[0] else { panic!("{:?}", events) };
+        assert_eq!(at_ms, 2_000);
+        let VadEvent::SpeechEnd { duration_ms, .. } = events[1] else { panic!("{:?}", events) };
+        assert_eq!(duration_ms, 1_000);
+        assert!(!gate.is_speaking());
+
+        // Pre-roll + speech + end hangover, nowhere near the full 6 s
+        let forwarded_ms = forwarded as u32 * 1000 / RATE;
+        assert!((2_100..2_400).contains(&forwarded_ms), "forwarded {} ms", forwarded_ms);
+    }
+
+    #[test]
+    fn test_short_pauses_do_not_split_speech() {
+        let mut gate = VadGate::new(&VadConfig::default(), RATE);
+        let mut audio = tone(800, 200.0, 0.3);
+        audio.extend(silence(400));
+        audio.extend(tone(800, 200.0, 0.3));
+        audio.extend(silence(1_000));
+
+        let (_, events) = run(&mut gate, &audio);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], VadEvent::SpeechEnd { duration_ms: 2_000, .. }));
+    }
+
+    #[test]
+    fn test_classifier_rejects_hiss_that_energy_accepts() {
+        let mut audio = silence(500);
+        audio.extend(hiss(1_000, 0.3));
+        audio.extend(silence(1_000));
+
+        let mut energy = VadGate::new(&VadConfig::default(), RATE);
+        assert_eq!(run(&mut energy, &audio).1.len(), 2);
+
+        let config = VadConfig { mode: VadMode::Classifier, ..Default::default() };
+        let mut classifier = VadGate::new(&config, RATE);
+        let (forwarded, events) = run(&mut classifier, &audio);
+        assert!(events.is_empty());
+        assert_eq!(forwarded, 0);
+
+        // ...but still hears a voiced tone
+        let mut voiced = silence(500);
+        voiced.extend(tone(1_000, 180.0, 0.3));
+        voiced.extend(silence(1_000));
+        let mut classifier = VadGate::new(&config, RATE);
+        assert_eq!(run(&mut classifier, &voiced).1.len(), 2);
+    }
+
+    #[test]
+    fn test_gate_waits_for_longer_server_silence() {
+        let mut audio = tone(1_000, 220.0, 0.3);
+        audio.extend(silence(1_200));
+
+        let mut gate = VadGate::new(&VadConfig::default(), RATE);
+        assert_eq!(run(&mut gate, &audio).1.len(), 2);
+
+        // Server waits 1.5 s: still speaking after 1.2 s of silence
+        let mut gate = VadGate::new(&VadConfig::default(), RATE);
+        gate.follow_server_silence(1_500);
+        let (forwarded, events) = run(&mut gate, &audio);
+        assert_eq!(events.len(), 1);
+        assert!(gate.is_speaking());
+        assert_eq!(forwarded, audio.len());
+
+        let (_, events) = run(&mut gate, &silence(600));
+        assert!(matches!(events[..], [VadEvent::SpeechEnd { at_ms: 2_700, .. }]));
+
+        // A shorter server wait never shortens the configured end
+        let mut gate = VadGate::new(&VadConfig::default(), RATE);
+        gate.follow_server_silence(300);
+        assert_eq!(run(&mut gate, &audio).1.len(), 2);
+    }
+
+    #[test]
+    fn test_disabled_gate_passes_everything() {
+        let config = VadConfig { enabled: false, ..Default::default() };
+        let mut gate = VadGate::new(&config, RATE);
+        let (forwarded, events) = run(&mut gate, &silence(1_000));
+        assert_eq!(forwarded, 16_000);
+        assert!(events.is_empty());
+    }
+}
@@ -1,252 +1,263 @@
-use tokio::sync::mpsc;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use url::Url;
-use serde_json::json;
-use uuid::Uuid;
-use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use log::{info, error, debug};
+use anyhow::{Context, Result};
+use log::{debug, error, info, warn};
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
 use tokio_util::sync::CancellationToken;
 
-const QWEN_MODEL: &str = "qwen3-asr-flash-realtime";
-const WS_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/realtime";
+use super::asr::archive::AudioArchive;
+use super::asr::level::LevelMeter;
+use super::asr::vad::VadGate;
+use super::asr::{self, microphone, ArchiveTarget, AsrBackend, AsrConfig, AsrSessionSettings, AsrStatus, AudioChunk, AudioClock, AudioLevel, RealtimeBackend, RealtimeFlavor, SessionControl, Transcript, VadConfig, VadEvent};
 
+/// Runs one recording at a time against the configured `AsrBackend`,
+/// capturing the microphone for backends that want it. Microphone audio goes
+/// through the level meter, the optional archive and the local VAD, which
+/// drops silence and reports speech boundaries.
 #[derive(Clone)]
 pub struct AsrService {
-    api_key: String,
-    tx_sender: Option<mpsc::UnboundedSender<String>>, 
+    backend: Arc<dyn AsrBackend>,
+    vad: VadConfig,
+    /// Input device id or name; None = system default
+    input_device: Option<String>,
+    /// Where to archive captured audio; None = don't
+    archive: Option<ArchiveTarget>,
+    clock: AudioClock,
+    control: SessionControl,
+    tx_sender: Option<mpsc::UnboundedSender<Transcript>>,
+    vad_sender: Option<mpsc::UnboundedSender<VadEvent>>,
+    level_sender: Option<mpsc::UnboundedSender<AudioLevel>>,
 }
 
 impl AsrService {
+    /// Qwen realtime with default settings
     pub fn new(api_key: String) -> Self {
+        Self::with_backend(Arc::new(RealtimeBackend::new(RealtimeFlavor::Qwen, api_key)))
+    }
+
+    pub fn with_backend(backend: Arc<dyn AsrBackend>) -> Self {
         Self {
-            api_key,
+            backend,
+            vad: VadConfig::default(),
+            input_device: None,
+            archive: None,
+            clock: AudioClock::default(),
+            control: SessionControl::default(),
             tx_sender: None,
+            vad_sender: None,
+            level_sender: None,
         }
     }
 
-    pub fn set_callback(&mut self, tx: mpsc::UnboundedSender<String>) {
+    pub fn with_vad(mut self, vad: VadConfig) -> Self {
+        self.vad = vad;
+        self
+    }
+
+    pub fn with_input_device(mut self, device: Option<String>) -> Self {
+        self.input_device = device;
+        self
+    }
+
+    /// Archive this recording's microphone audio. Also restarts the audio
+    /// clock, so call it once per recording.
+    pub fn with_archive(mut self, archive: Option<ArchiveTarget>) -> Self {
+        self.archive = archive;
+        self.clock = AudioClock::default();
+        self
+    }
+
+    /// Milliseconds of microphone audio captured by the current recording
+    pub fn audio_clock(&self) -> AudioClock {
+        self.clock.clone()
+    }
+
+    /// Backend from the `[asr]` section; `api_key` is used when it names none
+    pub fn from_config(config: &AsrConfig, api_key: &str) -> Result<Self> {
+        Ok(Self::with_backend(asr::build_backend(config, api_key)?).with_vad(config.vad.clone()))
+    }
+
+    /// Receives partial and final transcripts
+    pub fn set_callback(&mut self, tx: mpsc::UnboundedSender<Transcript>) {
         self.tx_sender = Some(tx);
     }
 
-    pub async fn start_recording(&self, cancel_token: CancellationToken) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let api_key = self.api_key.clone();
-        let sender = self.tx_sender.clone();
-
-        // 1. Setup Audio Input (cpal)
-        let host = cpal::default_host();
-        
-        info!("Available hosts: {:?}", cpal::available_hosts());
-        
-        let input_device = host.default_input_device()
-            .ok_or("No input device available")?;
-            
-        #[allow(deprecated)]
-        let device_name = input_device.name().unwrap_or("unknown".into());
-        info!("Default Input device: {}", device_name);
-        
-        // List all input devices for debugging
-        if let Ok(devices) = host.input_devices() {
-            for (i, dev) in devices.enumerate() {
-                #[allow(deprecated)]
-                let name = dev.name().unwrap_or_else(|_| "unknown".into());
-                info!("Device {}: {}", i, name);
-            }
+    /// Receives local speech boundaries
+    pub fn set_vad_callback(&mut self, tx: mpsc::UnboundedSender<VadEvent>) {
+        self.vad_sender = Some(tx);
+    }
+
+    /// Receives input level readings (~20 Hz) while recording from the microphone
+    pub fn set_level_callback(&mut self, tx: mpsc::UnboundedSender<AudioLevel>) {
+        self.level_sender = Some(tx);
+    }
+
+    /// Receives connection status of network backends
+    pub fn set_status_callback(&mut self, tx: mpsc::UnboundedSender<AsrStatus>) {
+        self.control = std::mem::take(&mut self.control).with_status(tx);
+    }
+
+    /// Handle for forcing the running recording's backend to reconnect
+    pub fn session_control(&self) -> SessionControl {
+        self.control.clone()
+    }
+
+    /// Whether recordings report speech boundaries (local VAD on live audio)
+    pub fn has_local_vad(&self) -> bool {
+        self.vad.enabled && self.backend.wants_microphone()
+    }
+
+    pub async fn start_recording(&self, cancel_token: CancellationToken) -> Result<()> {
+        let sender = self.tx_sender.clone()
+            .context("ASR transcript callback not set")?;
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        if self.backend.wants_microphone() {
+            let sample_rate = self.backend.sample_rate();
+            let (mic_tx, mic_rx) = mpsc::unbounded_channel();
+            microphone::start_capture(self.input_device.clone(), sample_rate, mic_tx, cancel_token.clone()).await?;
+            let archive = match &self.archive {
+                Some(target) => match AudioArchive::create(target, sample_rate) {
+                    Ok(archive) => Some(archive),
+                    Err(e) => {
+                        warn!("[Audio Archive] Disabled for this recording: {:#}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            let stage = InputStage {
+                sample_rate,
+                meter: LevelMeter::new(sample_rate),
+                gate: VadGate::new(&self.vad, sample_rate),
+                archive,
+                clock: self.clock.clone(),
+                levels: self.level_sender.clone(),
+                events: self.vad_sender.clone(),
+                settings: self.control.watch_settings(),
+            };
+            tokio::spawn(stage.run(mic_rx, audio_tx));
         }
 
-        let config = input_device.default_input_config()?;
-        // Force sample rate if possible, or resample. 
-        // Aliyun usually expects 16000Hz PCM. 
-        // For simplicity here, we assume device supports it or we accept mismatch (might fail).
-        // A robust impl would do resampling.
-        
-        #[allow(deprecated)]
-        let device_name_2 = input_device.name().unwrap_or("unknown".into());
-        info!("Input device: {}", device_name_2);
-        info!("Default config: {:?}", config);
-        
-        // Spawn async setup to avoid blocking
+        info!("[ASR] Recording with {} backend", self.backend.name());
+        let backend = self.backend.clone();
+        let control = self.control.clone();
         tokio::spawn(async move {
-            if let Err(e) = Self::run_async_recording(api_key, sender, input_device, config, cancel_token).await {
+            if let Err(e) = backend.run(audio_rx, sender, control, cancel_token).await {
                 error!("Async recording error: {}", e);
             }
         });
-        
+
         Ok(())
     }
+}
 
-    async fn run_async_recording(
-        api_key: String, 
-        sender: Option<mpsc::UnboundedSender<String>>,
-        input_device: cpal::Device,
-        config: cpal::SupportedStreamConfig,
-        cancel_token: CancellationToken,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-
-        // 2. Connect WebSocket
-        let url_str = format!("{}?model={}", WS_URL, QWEN_MODEL);
-        let url = Url::parse(&url_str)?;
-        
-        // Generate WebSocket Key
-        let key_bytes = Uuid::new_v4().into_bytes();
-        let key_b64 = BASE64.encode(key_bytes);
-
-        let request = http::Request::builder()
-            .uri(url.as_str())
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("OpenAI-Beta", "realtime=v1")
-            .header("Host", url.host_str().unwrap_or("dashscope.aliyuncs.com"))
-            .header("Connection", "Upgrade")
-            .header("Upgrade", "websocket")
-            .header("Sec-WebSocket-Version", "13")
-            .header("Sec-WebSocket-Key", key_b64)
-            .body(())?;
-
-        let (ws_stream, _) = connect_async(request).await?;
-        info!("Connected to Aliyun ASR WebSocket");
-
-        let (mut write, mut read) = ws_stream.split();
-
-        // 3. Send Init Event (Server VAD)
-        let session_id = Uuid::new_v4().to_string();
-        let init_event = json!({
-            "event_id": session_id,
-            "type": "session.update",
-            "session": {
-                "modalities": ["text"],
-                "input_audio_format": "pcm",
-                "sample_rate": 16000, // We must ensure we send 16k
-                "input_audio_transcription": {
-                    "language": "zh"
-                },
-                "turn_detection": {
-                    "type": "server_vad",
-                    "threshold": 0.5,
-                    "silence_duration_ms": 800
+/// Microphone audio on its way to the backend
+struct InputStage {
+    sample_rate: u32,
+    meter: LevelMeter,
+    gate: VadGate,
+    archive: Option<AudioArchive>,
+    clock: AudioClock,
+    levels: Option<mpsc::UnboundedSender<AudioLevel>>,
+    events: Option<mpsc::UnboundedSender<VadEvent>>,
+    /// Session settings; the gate follows their server silence duration
+    settings: watch::Receiver<AsrSessionSettings>,
+}
+
+impl InputStage {
+    /// Meter and archive every chunk, forward only what the gate lets through;
+    /// ends with the microphone
+    async fn run(
+        mut self,
+        mut mic_rx: mpsc::UnboundedReceiver<AudioChunk>,
+        audio_tx: mpsc::UnboundedSender<AudioChunk>,
+    ) {
+        self.gate.follow_server_silence(self.settings.borrow_and_update().silence_ms());
+        while let Some(chunk) = mic_rx.recv().await {
+            if self.settings.has_changed().unwrap_or(false) {
+                let silence_ms = self.settings.borrow_and_update().silence_ms();
+                debug!("[VAD] Following server silence of {} ms", silence_ms);
+                self.gate.follow_server_silence(silence_ms);
+            }
+            if let Some(tx) = &self.levels {
+                for level in self.meter.process(&chunk) {
+                    let _ = tx.send(level);
                 }
             }
-        });
-        
-        write.send(Message::Text(init_event.to_string().into())).await?;
-
-        // 4. Handle Incoming Messages (Transcript)
-        let sender_clone = sender.clone();
-        
-        // We need a way to stop reading when cancelled.
-        // We can use select! on read.next() and cancel_token.cancelled()
-        
-        // BUT `read` and `write` are split.
-        // We should run the read loop in a separate task, but linked to cancellation?
-        // Or just run it here.
-        
-        // Let's spawn the read loop, but we need to abort it when token cancels.
-        let read_token = cancel_token.clone();
-        tokio::spawn(async move {
-            loop {
-                tokio::select! {
-                    _ = read_token.cancelled() => {
-                        info!("ASR Read Loop cancelled");
-                        break;
-                    }
-                    msg = read.next() => {
-                        match msg {
-                            Some(Ok(Message::Text(text))) => {
-                                debug!("ASR Message: {}", text);
-                                if let Ok(data) = serde_json::from_str::<serde_json::Value>(&text) {
-                                    // Check for final transcript
-                                     if data["type"] == "conversation.item.input_audio_transcription.completed" {
-                                        if let Some(transcript) = data["transcript"].as_str() {
-                                            info!("ASR Transcript: {}", transcript);
-                                            if let Some(s) = &sender_clone {
-                                                let _ = s.send(transcript.to_string());
-                                            }
-                                        }
-                                     }
-                                }
-                            }
-                            Some(Ok(Message::Close(_))) => break,
-                            Some(Err(e)) => {
-                                error!("WebSocket read error: {}", e);
-                                break;
-                            },
-                            None => break,
-                            _ => {}
-                        }
-                    }
+            if let Some(archive) = &mut self.archive {
+                if let Err(e) = archive.write(&chunk) {
+                    warn!("[Audio Archive] Write failed, stopping archive: {:#}", e);
+                    self.archive = None;
                 }
             }
-        });
+            self.clock.advance(chunk.len(), self.sample_rate);
 
-        // 5. Stream Audio to WebSocket
-        let err_fn = move |err| {
-            error!("an error occurred on stream: {}", err);
-        };
-        
-        // Channel to pass audio data from cpal thread to ws writer
-        let (audio_tx, mut audio_rx) = mpsc::unbounded_channel::<Vec<u8>>();
-        
-        let sample_rate = config.sample_rate();
-        info!("Input Sample Rate: {}", sample_rate);
-        
-        // Simple decimation factor (downsampling)
-        let downsample_factor = (sample_rate as f32 / 16000.0).round() as usize;
-        let downsample_factor = if downsample_factor < 1 { 1 } else { downsample_factor };
-        
-        info!("Downsample factor: {}", downsample_factor);
-
-        let stream = input_device.build_input_stream(
-            &config.into(),
-            move |data: &[f32], _: &_| {
-                // Convert f32 samples to i16 (PCM)
-                // cpal gives f32 usually, need to scale to i16 range
-                // Simple downsampling
-                let mut pcm_bytes = Vec::with_capacity((data.len() / downsample_factor) * 2);
-                for (i, &sample) in data.iter().enumerate() {
-                    if i % downsample_factor == 0 {
-                         let s = (sample * 32767.0) as i16;
-                         pcm_bytes.extend_from_slice(&s.to_le_bytes());
-                    }
-                }
-                let _ = audio_tx.send(pcm_bytes);
-            },
-            err_fn,
-            None // None = blocking, use default timeout
-        )?;
-        
-        stream.play()?;
-        
-        // Loop sending audio
-        loop {
-            tokio::select! {
-                _ = cancel_token.cancelled() => {
-                    info!("Recording cancelled");
-                    break;
-                }
-                Some(pcm_data) = audio_rx.recv() => {
-                    // Send audio append event
-                    let b64_audio = BASE64.encode(&pcm_data);
-                    let audio_event = json!({
-                         "event_id": Uuid::new_v4().to_string(),
-                         "type": "input_audio_buffer.append",
-                         "audio": b64_audio
-                    });
-                    
-                    if let Err(e) = write.send(Message::Text(audio_event.to_string().into())).await {
-                        error!("Failed to send audio to WS: {}", e);
-                        break;
-                    }
+            let output = self.gate.process(&chunk);
+            for event in output.events {
+                debug!("[VAD] {:?}", event);
+                if let Some(tx) = &self.events {
+                    let _ = tx.send(event);
                 }
-                else => break,
+            }
+            if !output.audio.is_empty() && audio_tx.send(output.audio).is_err() {
+                break;
             }
         }
-        
-        // Ensure stream is kept alive as long as loop runs
-        drop(stream);
-        
-        // Close WS cleanly if possible
-        let _ = write.close().await;
 
-        Ok(())
+        if let Some(archive) = self.archive.take() {
+            if let Err(e) = archive.finish() {
+                warn!("[Audio Archive] Failed to finalize: {:#}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::asr::{ScriptedBackend, ScriptedTranscript};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mock_backend_drives_callback_without_microphone() {
+        let script = vec![
+            ScriptedTranscript { at_ms: 200, text: "Hello".to_string() },
+            ScriptedTranscript { at_ms: 400, text: "world".to_string() },
+        ];
+        let mut asr = AsrService::with_backend(Arc::new(ScriptedBackend::new(script)));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        asr.set_callback(tx);
+
+        asr.start_recording(CancellationToken::new()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().text, "Hello");
+        assert_eq!(rx.recv().await.unwrap().text, "world");
+    }
+
+    #[tokio::test]
+    async fn test_input_stage_archives_and_clocks_all_microphone_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = ArchiveTarget::new(dir.path(), 60);
+        let clock = AudioClock::default();
+        let stage = InputStage {
+            sample_rate: 16_000,
+            meter: LevelMeter::new(16_000),
+            gate: VadGate::new(&VadConfig::default(), 16_000),
+            archive: Some(AudioArchive::create(&target, 16_000).unwrap()),
+            clock: clock.clone(),
+            levels: None,
+            events: None,
+            settings: SessionControl::default().watch_settings(),
+        };
+        let (mic_tx, mic_rx) = mpsc::unbounded_channel();
+        let (audio_tx, _audio_rx) = mpsc::unbounded_channel();
+        for _ in 0..10 {
+            mic_tx.send(vec![0i16; 1_600]).unwrap();
+        }
+        drop(mic_tx);
+        stage.run(mic_rx, audio_tx).await;
+
+        // Silence never reaches the backend, but it is still archived
+        assert_eq!(clock.now_ms(), 1_000);
+        let segment = target.audio_dir.join(&target.session).join("segment-000.wav");
+        assert_eq!(hound::WavReader::open(segment).unwrap().duration(), 16_000);
     }
 }
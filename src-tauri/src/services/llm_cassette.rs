@@ -0,0 +1,549 @@
+// LLM Cassette
+//
+// Record-and-replay `LLMClient` for tests and offline runs.
+//
+// Record mode passes every call through to a real client and stores the
+// interaction under a hash of the request (label, call kind, messages, and the
+// schema or tools for structured calls): streamed chunks with their offset
+// from the start of the request, JSON text, tool completions, token usage,
+// and "unsupported"/"invalid request" refusals so callers take the same
+// fallback on replay. Replay mode serves interactions from the cassette and
+// fails loudly on a request it has never seen: a `NotRecorded` error (which
+// no caller treats as a reason to fall back), an `error!` log, and an entry
+// in `Cassette::unmatched()` for tests to assert on.
+// A request recorded several times replays its answers in order (the last
+// one repeats).
+//
+// Cassettes are JSONL, one interaction per line:
+//   {"key": "3f9a…", "label": "router", "kind": "stream",
+//    "chunks": [{"at_ms": 412, "text": "[{\"intent\""}, …], "usage": {…}}
+//
+// Chunks replay back to back unless the cassette is `with_realtime`, which
+// keeps the recorded pacing (for latency-sensitive code paths).
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use super::llm_client::{ChatMessage, LLMClient, LLMError, TokenUsage, UsageSink};
+use super::llm_json::ResponseFormat;
+use super::llm_tools::{ToolChoice, ToolCompletion, ToolDefinition};
+
+type ChunkStream = Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>;
+
+/// Quiet period after the last call before background work counts as done
+const SETTLE_MS: u64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimedChunk {
+    /// Milliseconds since the request was sent
+    pub at_ms: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Recorded {
+    Stream { chunks: Vec<TimedChunk> },
+    Json { text: String },
+    Tools { completion: ToolCompletion },
+    Unsupported { message: String },
+    InvalidRequest { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    key: String,
+    label: String,
+    #[serde(flatten)]
+    recorded: Recorded,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Default)]
+struct Tape {
+    interactions: Vec<Interaction>,
+    /// Replays served so far, per key
+    served: HashMap<String, usize>,
+    unmatched: Vec<String>,
+}
+
+/// Interactions shared by any number of `CassetteClient`s
+#[derive(Default)]
+pub struct Cassette {
+    tape: Mutex<Tape>,
+    realtime: bool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl Cassette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a cassette; a missing file is an empty cassette
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read cassette {}", path.display())),
+        };
+        let interactions = content.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line)
+                .with_context(|| format!("Invalid cassette entry at {}:{}", path.display(), i + 1)))
+            .collect::<Result<Vec<Interaction>>>()?;
+        Ok(Self { tape: Mutex::new(Tape { interactions, ..Default::default() }), ..Default::default() })
+    }
+
+    /// Replay chunks at their recorded pace instead of back to back
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tape = self.tape.lock().unwrap();
+        let mut out = String::new();
+        for interaction in &tape.interactions {
+            out.push_str(&serde_json::to_string(interaction)?);
+            out.push('\n');
+        }
+        std::fs::write(path, out).with_context(|| format!("Failed to write cassette {}", path.display()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tape.lock().unwrap().interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Requests replay had no interaction for, described for a test failure message
+    pub fn unmatched(&self) -> Vec<String> {
+        self.tape.lock().unwrap().unmatched.clone()
+    }
+
+    /// Wait until no call has been in flight for a short while, so background
+    /// work started by the last call (focus, todos, naming) has finished
+    pub async fn settle(&self) {
+        loop {
+            while self.in_flight.load(Ordering::SeqCst) > 0 {
+                let idle = self.idle.notified();
+                if self.in_flight.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                idle.await;
+            }
+            tokio::time::sleep(Duration::from_millis(SETTLE_MS)).await;
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+        }
+    }
+
+    fn record(&self, key: String, label: &str, recorded: Recorded, usage: Option<TokenUsage>) {
+        self.tape.lock().unwrap().interactions.push(Interaction { key, label: label.to_string(), recorded, usage });
+    }
+
+    fn replay(&self, key: &str, label: &str, request: &[ChatMessage]) -> Result<Interaction, LLMError> {
+        let mut tape = self.tape.lock().unwrap();
+        let matches: Vec<usize> = tape.interactions.iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.key == key)
+            .map(|(i, _)| i)
+            .collect();
+        let Some(&last) = matches.last() else {
+            let preview: String = request.last().map(|m| m.content.chars().take(120).collect()).unwrap_or_default();
+            let description = format!("{} request {} ({:?})", label, &key[..12], preview);
+            error!("[Cassette] No recorded interaction for {}", description);
+            tape.unmatched.push(description);
+            return Err(LLMError::NotRecorded(format!(
+                "cassette has no {} request {}; re-record it", label, &key[..12]
+            )));
+        };
+        let served = tape.served.entry(key.to_string()).or_insert(0);
+        let index = matches.get(*served).copied().unwrap_or(last);
+        *served += 1;
+        Ok(tape.interactions[index].clone())
+    }
+}
+
+/// Marks a call in flight for `Cassette::settle`; lives as long as its stream
+struct InFlight(Arc<Cassette>);
+
+impl InFlight {
+    fn start(cassette: &Arc<Cassette>) -> Self {
+        cassette.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(cassette.clone())
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+fn request_key(label: &str, kind: &str, messages: &[ChatMessage], extra: serde_json::Value) -> String {
+    let request = json!({ "label": label, "kind": kind, "messages": messages, "extra": extra });
+    let mut hasher = Sha256::new();
+    hasher.update(request.to_string().as_bytes());
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Captures the usage the real client reports, on top of the caller's sink
+fn capture_usage(usage: UsageSink) -> (UsageSink, Arc<Mutex<Option<TokenUsage>>>) {
+    let captured = Arc::new(Mutex::new(None));
+    let slot = captured.clone();
+    (UsageSink::new(move |u| *slot.lock().unwrap() = Some(u)).and(usage), captured)
+}
+
+/// Records through `inner` when set, replays from the cassette otherwise.
+/// `label` tells clients sharing a cassette apart (the model role, say).
+pub struct CassetteClient {
+    label: String,
+    inner: Option<Arc<dyn LLMClient>>,
+    cassette: Arc<Cassette>,
+}
+
+impl CassetteClient {
+    pub fn record(label: impl Into<String>, inner: Arc<dyn LLMClient>, cassette: Arc<Cassette>) -> Self {
+        Self { label: label.into(), inner: Some(inner), cassette }
+    }
+
+    pub fn replay(label: impl Into<String>, cassette: Arc<Cassette>) -> Self {
+        Self { label: label.into(), inner: None, cassette }
+    }
+
+    /// Record a refusal that callers fall back on; other errors are not kept
+    fn record_error(&self, key: String, error: &LLMError) {
+        let recorded = match error {
+            LLMError::Unsupported(message) => Recorded::Unsupported { message: message.clone() },
+            LLMError::InvalidRequest(message) => Recorded::InvalidRequest { message: message.clone() },
+            _ => return,
+        };
+        self.cassette.record(key, &self.label, recorded, None);
+    }
+
+    fn replay_interaction(&self, key: &str, messages: &[ChatMessage], usage: &UsageSink) -> Result<Recorded, LLMError> {
+        let interaction = self.cassette.replay(key, &self.label, messages)?;
+        if let Some(u) = interaction.usage {
+            usage.report(u);
+        }
+        match interaction.recorded {
+            Recorded::Unsupported { message } => Err(LLMError::Unsupported(message)),
+            Recorded::InvalidRequest { message } => Err(LLMError::InvalidRequest(message)),
+            recorded => Ok(recorded),
+        }
+    }
+
+    fn mismatch(&self, key: &str, expected: &str) -> LLMError {
+        LLMError::NotRecorded(format!("cassette entry {} for {} is not a {} response", &key[..12], self.label, expected))
+    }
+}
+
+#[async_trait]
+impl LLMClient for CassetteClient {
+    async fn stream_completion(&self, messages: Vec<ChatMessage>) -> Result<ChunkStream, LLMError> {
+        self.stream_completion_with_usage(messages, UsageSink::none()).await
+    }
+
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<ChunkStream, LLMError> {
+        let in_flight = InFlight::start(&self.cassette);
+        let key = request_key(&self.label, "stream", &messages, serde_json::Value::Null);
+
+        let Some(inner) = &self.inner else {
+            let chunks = match self.replay_interaction(&key, &messages, &usage)? {
+                Recorded::Stream { chunks } => chunks,
+                _ => return Err(self.mismatch(&key, "streamed")),
+            };
+            let realtime = self.cassette.realtime;
+            let started = tokio::time::Instant::now();
+            return Ok(Box::pin(futures_util::stream::iter(chunks).then(move |chunk| {
+                let _in_flight = &in_flight;
+                async move {
+                    if realtime {
+                        tokio::time::sleep_until(started + Duration::from_millis(chunk.at_ms)).await;
+                    }
+                    Ok(chunk.text)
+                }
+            })));
+        };
+
+        let started = tokio::time::Instant::now();
+        let (usage, captured) = capture_usage(usage);
+        let stream = match inner.stream_completion_with_usage(messages, usage).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.record_error(key, &e);
+                return Err(e);
+            }
+        };
+
+        // Pass chunks through as they arrive; the interaction is kept once the stream ends cleanly
+        let chunks = Arc::new(Mutex::new(Some(Vec::new())));
+        let tap = chunks.clone();
+        let passthrough = stream.map(move |item| {
+            let mut tap = tap.lock().unwrap();
+            match &item {
+                Ok(text) => if let Some(chunks) = tap.as_mut() {
+                    chunks.push(TimedChunk { at_ms: started.elapsed().as_millis() as u64, text: text.clone() });
+                },
+                Err(_) => *tap = None,
+            }
+            item
+        });
+        let cassette = self.cassette.clone();
+        let label = self.label.clone();
+        let finish = futures_util::stream::once(async move {
+            if let Some(chunks) = chunks.lock().unwrap().take() {
+                let usage = captured.lock().unwrap().take();
+                cassette.record(key, &label, Recorded::Stream { chunks }, usage);
+            }
+            drop(in_flight);
+            None
+        }).filter_map(futures_util::future::ready);
+        Ok(Box::pin(passthrough.chain(finish)))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        let _in_flight = InFlight::start(&self.cassette);
+        let extra = json!({
+            "tools": tools.iter().map(ToolDefinition::to_openai).collect::<Vec<_>>(),
+            "tool_choice": tool_choice.to_openai(),
+        });
+        let key = request_key(&self.label, "tools", &messages, extra);
+
+        let Some(inner) = &self.inner else {
+            return match self.replay_interaction(&key, &messages, &usage)? {
+                Recorded::Tools { completion } => Ok(completion),
+                _ => Err(self.mismatch(&key, "tool")),
+            };
+        };
+        let (usage, captured) = capture_usage(usage);
+        match inner.complete_with_tools(messages, tools, tool_choice, usage).await {
+            Ok(completion) => {
+                let usage = captured.lock().unwrap().take();
+                self.cassette.record(key, &self.label, Recorded::Tools { completion: completion.clone() }, usage);
+                Ok(completion)
+            }
+            Err(e) => {
+                self.record_error(key, &e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        let _in_flight = InFlight::start(&self.cassette);
+        let key = request_key(&self.label, "json", &messages, format.to_openai());
+
+        let Some(inner) = &self.inner else {
+            return match self.replay_interaction(&key, &messages, &usage)? {
+                Recorded::Json { text } => Ok(text),
+                _ => Err(self.mismatch(&key, "JSON")),
+            };
+        };
+        let (usage, captured) = capture_usage(usage);
+        match inner.complete_json(messages, format, usage).await {
+            Ok(text) => {
+                let usage = captured.lock().unwrap().take();
+                self.cassette.record(key, &self.label, Recorded::Json { text: text.clone() }, usage);
+                Ok(text)
+            }
+            Err(e) => {
+                self.record_error(key, &e);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Helpers for recording cassettes in other modules' tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::services::llm_tools::ToolCall;
+
+    /// Stands in for the live provider while recording: one fixed answer,
+    /// streamed in two chunks, plus an optional tool call
+    pub(crate) struct ScriptedClient {
+        text: String,
+        tool_call: Option<ToolCall>,
+    }
+
+    impl ScriptedClient {
+        pub(crate) fn text(text: &str) -> Self {
+            Self { text: text.to_string(), tool_call: None }
+        }
+
+        pub(crate) fn tool_call(name: &str, arguments: serde_json::Value) -> Self {
+            let call = ToolCall { id: "call_1".to_string(), name: name.to_string(), arguments: arguments.to_string() };
+            Self { text: String::new(), tool_call: Some(call) }
+        }
+    }
+
+    #[async_trait]
+    impl LLMClient for ScriptedClient {
+        async fn stream_completion(&self, _messages: Vec<ChatMessage>) -> Result<ChunkStream, LLMError> {
+            let mid = self.text.char_indices().nth(self.text.chars().count() / 2).map_or(0, |(i, _)| i);
+            let (head, tail) = self.text.split_at(mid);
+            let chunks = vec![Ok(head.to_string()), Ok(tail.to_string())];
+            Ok(Box::pin(futures_util::stream::iter(chunks)))
+        }
+
+        async fn complete_with_tools(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Vec<ToolDefinition>,
+            _tool_choice: ToolChoice,
+            _usage: UsageSink,
+        ) -> Result<ToolCompletion, LLMError> {
+            match &self.tool_call {
+                Some(call) => Ok(ToolCompletion { content: String::new(), tool_calls: vec![call.clone()] }),
+                None => Err(LLMError::Unsupported("tool calling".to_string())),
+            }
+        }
+    }
+
+    /// Save `cassette` and load it back, as a test would from a committed file
+    pub(crate) fn reload(cassette: &Cassette) -> Arc<Cassette> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.jsonl");
+        cassette.save(&path).unwrap();
+        Arc::new(Cassette::load(&path).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_client::collect_stream;
+    use crate::services::llm_tools::ToolCall;
+
+    /// Stands in for a provider: two chunks per answer, no native JSON mode
+    struct Provider;
+
+    #[async_trait]
+    impl LLMClient for Provider {
+        async fn stream_completion(&self, messages: Vec<ChatMessage>) -> Result<ChunkStream, LLMError> {
+            let last = messages.last().map(|m| m.content.clone()).unwrap_or_default();
+            let chunks = vec![Ok("re: ".to_string()), Ok(last)];
+            Ok(Box::pin(futures_util::stream::iter(chunks).then(|chunk| async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                chunk
+            })))
+        }
+
+        async fn stream_completion_with_usage(&self, messages: Vec<ChatMessage>, usage: UsageSink) -> Result<ChunkStream, LLMError> {
+            usage.report(TokenUsage { model: "fake".into(), prompt_tokens: 7, completion_tokens: 2 });
+            self.stream_completion(messages).await
+        }
+
+        async fn complete_with_tools(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _tools: Vec<ToolDefinition>,
+            _tool_choice: ToolChoice,
+            _usage: UsageSink,
+        ) -> Result<ToolCompletion, LLMError> {
+            Ok(ToolCompletion {
+                content: String::new(),
+                tool_calls: vec![ToolCall { id: "call_1".into(), name: "noop".into(), arguments: "{}".into() }],
+            })
+        }
+    }
+
+    fn user(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage { role: "user".to_string(), content: content.to_string() }]
+    }
+
+    #[tokio::test]
+    async fn test_records_then_replays_every_call_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("router.jsonl");
+
+        let cassette = Arc::new(Cassette::new());
+        let recorder = CassetteClient::record("router", Arc::new(Provider), cassette.clone());
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = { let r = reported.clone(); UsageSink::new(move |u| r.lock().unwrap().push(u)) };
+        let text = collect_stream(recorder.stream_completion_with_usage(user("plan"), sink.clone()).await.unwrap()).await.unwrap();
+        assert_eq!(text, "re: plan");
+        let tools = recorder.complete_with_tools(user("plan"), vec![], ToolChoice::Auto, UsageSink::none()).await.unwrap();
+        let format = ResponseFormat::new("plan", json!({}));
+        assert!(matches!(recorder.complete_json(user("plan"), format.clone(), UsageSink::none()).await, Err(LLMError::Unsupported(_))));
+        cassette.save(&path).unwrap();
+
+        let cassette = Arc::new(Cassette::load(&path).unwrap());
+        assert_eq!(cassette.len(), 3);
+        let replay = CassetteClient::replay("router", cassette.clone());
+        let chunks: Vec<String> = replay.stream_completion_with_usage(user("plan"), sink).await.unwrap()
+            .map(|c| c.unwrap()).collect().await;
+        assert_eq!(chunks, vec!["re: ", "plan"]);
+        assert_eq!(reported.lock().unwrap().len(), 2, "usage is reported on record and on replay");
+        assert_eq!(replay.complete_with_tools(user("plan"), vec![], ToolChoice::Auto, UsageSink::none()).await.unwrap(), tools);
+        // The provider's refusal replays too, so callers take the same fallback
+        assert!(matches!(replay.complete_json(user("plan"), format, UsageSink::none()).await, Err(LLMError::Unsupported(_))));
+        assert!(cassette.unmatched().is_empty());
+
+        // Unknown requests (changed prompt, other label) fail and are listed
+        assert!(matches!(replay.chat(user("plan more"), None).await, Err(LLMError::NotRecorded(_))));
+        let coder = CassetteClient::replay("coder", cassette.clone());
+        assert!(coder.chat(user("plan"), None).await.is_err());
+        assert_eq!(cassette.unmatched().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_realtime_replay_keeps_recorded_pacing() {
+        let cassette = Arc::new(Cassette::new());
+        let recorder = CassetteClient::record("flash", Arc::new(Provider), cassette.clone());
+        collect_stream(recorder.stream_completion(user("hi")).await.unwrap()).await.unwrap();
+        let recorded = match &cassette.tape.lock().unwrap().interactions[0].recorded {
+            Recorded::Stream { chunks } => chunks.iter().map(|c| c.at_ms).collect::<Vec<_>>(),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(recorded, vec![20, 40]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flash.jsonl");
+        cassette.save(&path).unwrap();
+        let cassette = Arc::new(Cassette::load(&path).unwrap().with_realtime(true));
+        let replay = CassetteClient::replay("flash", cassette);
+        let started = tokio::time::Instant::now();
+        assert_eq!(collect_stream(replay.stream_completion(user("hi")).await.unwrap()).await.unwrap(), "re: hi");
+        assert_eq!(started.elapsed(), Duration::from_millis(40));
+    }
+}
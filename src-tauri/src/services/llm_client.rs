@@ -5,6 +5,10 @@ use serde_json::{json, Value};
 use futures_util::StreamExt;
 use futures_util::future;
 use std::pin::Pin;
+use std::sync::Arc;
+
+use super::llm_json::ResponseFormat;
+use super::llm_tools::{ToolCallAccumulator, ToolCallDelta, ToolChoice, ToolCompletion, ToolDefinition};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
@@ -12,10 +16,181 @@ pub struct ChatMessage {
     pub content: String,
 }
 
+/// Per-role request settings. Unset fields use the provider's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelSettings {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Total request deadline, including the streamed body
+    pub timeout_ms: Option<u64>,
+    pub enable_thinking: Option<bool>,
+}
+
+impl ModelSettings {
+    /// Fill unset fields from `fallback`
+    pub fn or(self, fallback: &ModelSettings) -> Self {
+        Self {
+            temperature: self.temperature.or(fallback.temperature),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            timeout_ms: self.timeout_ms.or(fallback.timeout_ms),
+            enable_thinking: self.enable_thinking.or(fallback.enable_thinking),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.timeout_ms.map(std::time::Duration::from_millis)
+    }
+}
+
+/// Provider-reported token counts for one completion
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Receives the token usage of a completion once the provider reports it
+#[derive(Clone, Default)]
+pub struct UsageSink(Option<Arc<dyn Fn(TokenUsage) + Send + Sync>>);
+
+impl UsageSink {
+    pub fn new(f: impl Fn(TokenUsage) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(f)))
+    }
+
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    pub fn report(&self, usage: TokenUsage) {
+        if let Some(f) = &self.0 {
+            f(usage);
+        }
+    }
+
+    /// A sink that reports to both `self` and `other`
+    pub fn and(&self, other: UsageSink) -> UsageSink {
+        match (&self.0, &other.0) {
+            (None, _) => other,
+            (_, None) => self.clone(),
+            (Some(first), Some(second)) => {
+                let (first, second) = (first.clone(), second.clone());
+                UsageSink::new(move |usage| {
+                    first(usage.clone());
+                    second(usage);
+                })
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum LLMError {
     RequestFailed(String),
     ParseError(String),
+    /// HTTP 429; `retry_after` comes from the `Retry-After` header when present
+    RateLimited { retry_after: Option<std::time::Duration> },
+    /// HTTP 401/403
+    Auth(String),
+    /// Other 4xx: the request itself is wrong and will fail again
+    InvalidRequest(String),
+    /// Prompt exceeds the model's context window
+    ContextTooLong(String),
+    Timeout,
+    /// Connection dropped after the response started streaming
+    StreamInterrupted(String),
+    /// Too many recent failures on this endpoint; not attempted
+    CircuitOpen(String),
+    /// The provider/client doesn't implement the requested feature (e.g. tool calling)
+    Unsupported(String),
+    /// Replay has no recorded response for this request (see llm_cassette.rs).
+    /// Never a reason to fall back: the cassette needs re-recording.
+    NotRecorded(String),
+}
+
+impl LLMError {
+    /// Transient failures worth another attempt
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            LLMError::RequestFailed(_)
+                | LLMError::RateLimited { .. }
+                | LLMError::Timeout
+                | LLMError::StreamInterrupted(_)
+        )
+    }
+
+    /// Transport error from reqwest before or while sending
+    pub(crate) fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            LLMError::Timeout
+        } else {
+            LLMError::RequestFailed(e.to_string())
+        }
+    }
+
+    /// Transport error while reading the streamed body
+    pub(crate) fn from_stream(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            LLMError::Timeout
+        } else {
+            LLMError::StreamInterrupted(e.to_string())
+        }
+    }
+
+    /// Map a non-2xx response to a typed error
+    pub(crate) async fn from_response(resp: reqwest::Response) -> Self {
+        let status = resp.status();
+        let retry_after = resp.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(std::time::Duration::from_secs);
+        let body = resp.text().await.unwrap_or_default();
+        Self::from_status(status.as_u16(), retry_after, &body)
+    }
+
+    pub(crate) fn from_status(status: u16, retry_after: Option<std::time::Duration>, body: &str) -> Self {
+        let detail = format!("HTTP Error: {} {}", status, body.chars().take(300).collect::<String>());
+        match status {
+            429 => LLMError::RateLimited { retry_after },
+            401 | 403 => LLMError::Auth(detail),
+            408 | 504 => LLMError::Timeout,
+            400 | 413 if is_context_length_message(body) => LLMError::ContextTooLong(detail),
+            500..=599 => LLMError::RequestFailed(detail),
+            _ => LLMError::InvalidRequest(detail),
+        }
+    }
+}
+
+impl std::fmt::Display for LLMError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LLMError::RequestFailed(msg) => write!(f, "request failed: {}", msg),
+            LLMError::ParseError(msg) => write!(f, "parse error: {}", msg),
+            LLMError::RateLimited { retry_after: Some(d) } => write!(f, "rate limited (retry after {}s)", d.as_secs()),
+            LLMError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            LLMError::Auth(msg) => write!(f, "authentication failed: {}", msg),
+            LLMError::InvalidRequest(msg) => write!(f, "invalid request: {}", msg),
+            LLMError::ContextTooLong(msg) => write!(f, "context too long: {}", msg),
+            LLMError::Timeout => write!(f, "timed out"),
+            LLMError::StreamInterrupted(msg) => write!(f, "stream interrupted: {}", msg),
+            LLMError::CircuitOpen(endpoint) => write!(f, "circuit open for {}", endpoint),
+            LLMError::Unsupported(what) => write!(f, "unsupported: {}", what),
+            LLMError::NotRecorded(what) => write!(f, "not recorded: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for LLMError {}
+
+fn is_context_length_message(body: &str) -> bool {
+    let body = body.to_lowercase();
+    ["context_length", "context length", "maximum context", "too many tokens", "too long"]
+        .iter()
+        .any(|needle| body.contains(needle))
 }
 
 #[async_trait]
@@ -25,26 +200,68 @@ pub trait LLMClient: Send + Sync {
         messages: Vec<ChatMessage>,
     ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError>;
 
+    /// Same as `stream_completion`, reporting token usage to `usage`.
+    /// Clients that can't see usage never call the sink.
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        let _ = usage;
+        self.stream_completion(messages).await
+    }
+
     async fn chat(
         &self,
         messages: Vec<ChatMessage>,
         _options: Option<serde_json::Value>,
     ) -> Result<String, LLMError> {
-        let mut stream = self.stream_completion(messages).await?;
-        let mut full_text = String::new();
-        while let Some(chunk) = stream.next().await {
-            full_text.push_str(&chunk?);
-        }
-        Ok(full_text)
+        collect_stream(self.stream_completion(messages).await?).await
+    }
+
+    /// Completion with native tool calling; returns once the response is complete.
+    /// Callers should fall back to text parsing on `LLMError::Unsupported`.
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        let _ = (messages, tools, tool_choice, usage);
+        Err(LLMError::Unsupported("tool calling".to_string()))
+    }
+
+    /// Completion constrained to a JSON schema. Returns the raw JSON text;
+    /// parse it with `llm_json::parse_lenient`. Falls back like `complete_with_tools`.
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        let _ = (messages, format, usage);
+        Err(LLMError::Unsupported("structured output".to_string()))
     }
 }
 
+/// Drain a completion stream into a single string
+pub(crate) async fn collect_stream(
+    mut stream: Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>,
+) -> Result<String, LLMError> {
+    let mut full_text = String::new();
+    while let Some(chunk) = stream.next().await {
+        full_text.push_str(&chunk?);
+    }
+    Ok(full_text)
+}
+
 pub struct OpenAILikeClient {
     client: Client,
     api_key: String,
     base_url: String,
     model: String,
-    enable_thinking: Option<bool>,
+    settings: ModelSettings,
 }
 
 impl OpenAILikeClient {
@@ -54,7 +271,7 @@ impl OpenAILikeClient {
             api_key,
             base_url,
             model,
-            enable_thinking: None,
+            settings: ModelSettings::default(),
         }
     }
 
@@ -64,99 +281,209 @@ impl OpenAILikeClient {
         model: String,
         enable_thinking: bool,
     ) -> Self {
-        Self {
-            client: Client::new(),
-            api_key,
-            base_url,
-            model,
+        Self::new(base_url, api_key, model).with_settings(ModelSettings {
             enable_thinking: Some(enable_thinking),
-        }
+            ..Default::default()
+        })
     }
-}
 
-#[async_trait]
-impl LLMClient for OpenAILikeClient {
-    async fn stream_completion(
-        &self,
-        messages: Vec<ChatMessage>,
-    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
-        let url = format!("{}/chat/completions", self.base_url);
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    fn request_body(&self, messages: Vec<ChatMessage>) -> Value {
         let mut body = json!({
             "model": self.model,
             "messages": messages,
             "stream": true,
-            "temperature": 1,
+            "stream_options": { "include_usage": true },
+            "temperature": self.settings.temperature.unwrap_or(1.0),
         });
 
-        if let Some(enable_thinking) = self.enable_thinking {
+        if let Some(max_tokens) = self.settings.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+
+        if let Some(enable_thinking) = self.settings.enable_thinking {
             body["extra_body"] = json!({
                 "enable_thinking": enable_thinking
             });
         }
+        body
+    }
 
-        let resp = self.client
+    async fn post(&self, body: &Value) -> Result<reqwest::Response, LLMError> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let mut req = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
-            .json(&body)
+            .json(body);
+        if let Some(timeout) = self.settings.timeout() {
+            req = req.timeout(timeout);
+        }
+
+        let resp = req
             .send()
             .await
-            .map_err(|e| LLMError::RequestFailed(e.to_string()))?;
+            .map_err(LLMError::from_reqwest)?;
 
         if !resp.status().is_success() {
-             return Err(LLMError::RequestFailed(format!("HTTP Error: {}", resp.status())));
+            return Err(LLMError::from_response(resp).await);
         }
+        Ok(resp)
+    }
 
+    /// POST a chat body and stream back the content deltas
+    async fn stream_body(
+        &self,
+        body: &Value,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        let resp = self.post(body).await?;
         let stream = resp.bytes_stream();
 
         // Robust-ish SSE line buffering:
         // - bytes chunks can split a single SSE "data: {json}" line (very common)
         // - we buffer partial lines across chunks and only parse complete lines
-        let stream_mapped = stream.scan(String::new(), |buf, chunk_result| {
+        let model = self.model.clone();
+        let stream_mapped = stream.scan(String::new(), move |buf, chunk_result| {
             future::ready(match chunk_result {
                 Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    buf.push_str(&text);
+                    buf.push_str(&String::from_utf8_lossy(&bytes));
 
                     let mut delta_content = String::new();
-
-                    // Consume complete lines (ending with '\n'). Keep remainder in `buf`.
-                    while let Some(nl_idx) = buf.find('\n') {
-                        // Take line (without '\n')
-                        let mut line = buf[..nl_idx].to_string();
-                        // Drain including '\n'
-                        buf.drain(..=nl_idx);
-
-                        // Handle CRLF
-                        if line.ends_with('\r') {
-                            line.pop();
-                        }
-
-                        let line = line.trim_start();
-                        // SSE allows "data:" or "data: " prefixes
-                        let data = if let Some(rest) = line.strip_prefix("data:") {
-                            rest.trim_start()
-                        } else {
-                            continue;
-                        };
-
-                        if data == "[DONE]" {
-                            continue;
-                        }
-
-                        if let Ok(val) = serde_json::from_str::<Value>(data) {
+                    for data in drain_sse_data(buf) {
+                        if let Ok(val) = serde_json::from_str::<Value>(&data) {
                             if let Some(content) = val["choices"][0]["delta"]["content"].as_str() {
                                 delta_content.push_str(content);
                             }
+                            if let Some(tokens) = Self::usage_of(&model, &val) {
+                                usage.report(tokens);
+                            }
                         }
                     }
 
                     Some(Ok(delta_content))
                 }
-                Err(e) => Some(Err(LLMError::RequestFailed(e.to_string()))),
+                Err(e) => Some(Err(LLMError::from_stream(e))),
             })
         });
 
         Ok(Box::pin(stream_mapped))
     }
+
+    /// The final chunk (empty `choices`) carries `usage` when include_usage is set
+    fn usage_of(model: &str, val: &Value) -> Option<TokenUsage> {
+        val["usage"].is_object().then(|| TokenUsage {
+            model: model.to_string(),
+            prompt_tokens: val["usage"]["prompt_tokens"].as_u64().unwrap_or(0),
+            completion_tokens: val["usage"]["completion_tokens"].as_u64().unwrap_or(0),
+        })
+    }
+}
+
+#[async_trait]
+impl LLMClient for OpenAILikeClient {
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        self.stream_completion_with_usage(messages, UsageSink::none()).await
+    }
+
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        self.stream_body(&self.request_body(messages), usage).await
+    }
+
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        let mut body = self.request_body(messages);
+        body["response_format"] = format.to_openai();
+        collect_stream(self.stream_body(&body, usage).await?).await
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        let mut body = self.request_body(messages);
+        body["tools"] = Value::Array(tools.iter().map(ToolDefinition::to_openai).collect());
+        body["tool_choice"] = tool_choice.to_openai();
+
+        let resp = self.post(&body).await?;
+        let mut stream = resp.bytes_stream();
+
+        // Tool-call arguments arrive as fragments keyed by `index`; stitch them together
+        let mut buf = String::new();
+        let mut content = String::new();
+        let mut calls = ToolCallAccumulator::default();
+        while let Some(chunk) = stream.next().await {
+            let bytes = chunk.map_err(LLMError::from_stream)?;
+            buf.push_str(&String::from_utf8_lossy(&bytes));
+
+            for data in drain_sse_data(&mut buf) {
+                let Ok(val) = serde_json::from_str::<Value>(&data) else { continue };
+                let delta = &val["choices"][0]["delta"];
+                if let Some(text) = delta["content"].as_str() {
+                    content.push_str(text);
+                }
+                for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                    calls.push(ToolCallDelta::from_openai(call));
+                }
+                if let Some(tokens) = Self::usage_of(&self.model, &val) {
+                    usage.report(tokens);
+                }
+            }
+        }
+
+        Ok(ToolCompletion { content, tool_calls: calls.finish() })
+    }
+}
+
+/// Consume complete lines (ending with '\n') from `buf`, keeping the remainder.
+/// Handles CRLF and skips blank lines.
+pub(crate) fn drain_lines(buf: &mut String) -> Vec<String> {
+    let mut lines = Vec::new();
+    while let Some(nl_idx) = buf.find('\n') {
+        let mut line = buf[..nl_idx].to_string();
+        buf.drain(..=nl_idx);
+
+        if line.ends_with('\r') {
+            line.pop();
+        }
+        if !line.trim().is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Consume complete SSE lines from `buf` and return their `data:` payloads.
+/// The terminal `[DONE]` marker is dropped.
+pub(crate) fn drain_sse_data(buf: &mut String) -> Vec<String> {
+    drain_lines(buf)
+        .into_iter()
+        .filter_map(|line| {
+            // SSE allows "data:" or "data: " prefixes
+            let data = line.trim_start().strip_prefix("data:")?.trim_start().to_string();
+            if data == "[DONE]" {
+                None
+            } else {
+                Some(data)
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,181 @@
+// LLM JSON Output
+//
+// Schema-constrained completions. `ResponseFormat` names a JSON schema the
+// reply must follow; providers map it to `response_format: json_schema`
+// (OpenAI-compatible), `format` (Ollama) or a forced tool call (Anthropic).
+//
+// Small models still truncate or wrap their JSON now and then, so
+// `parse_lenient` runs the reply through `repair_json` before giving up.
+// `complete_json_lenient` combines the two and tells the caller when the
+// provider refused JSON mode, so it can fall back to a text prompt.
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use super::llm_client::{ChatMessage, LLMClient, LLMError, UsageSink};
+
+/// A named JSON schema the completion must conform to
+#[derive(Debug, Clone)]
+pub struct ResponseFormat {
+    pub name: String,
+    pub schema: Value,
+}
+
+impl ResponseFormat {
+    pub fn new(name: &str, schema: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            schema,
+        }
+    }
+
+    /// OpenAI-compatible `response_format` field
+    pub(crate) fn to_openai(&self) -> Value {
+        json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": self.name,
+                "schema": self.schema,
+                "strict": true,
+            }
+        })
+    }
+}
+
+/// Ask for a schema-constrained reply and parse it leniently. Ok(None) means
+/// the provider has no JSON mode or rejected the `response_format` field, and
+/// the caller should use its text prompt; any other failure is returned.
+pub async fn complete_json_lenient<T: DeserializeOwned, C: LLMClient + ?Sized>(
+    llm: &C,
+    messages: Vec<ChatMessage>,
+    format: ResponseFormat,
+    usage: UsageSink,
+) -> Result<Option<T>, LLMError> {
+    match llm.complete_json(messages, format, usage).await {
+        Ok(raw) => parse_lenient(&raw).map(Some),
+        Err(LLMError::Unsupported(_)) | Err(LLMError::InvalidRequest(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Deserialize a model reply, repairing it first if it isn't valid JSON as-is
+pub fn parse_lenient<T: DeserializeOwned>(raw: &str) -> Result<T, LLMError> {
+    let raw = raw.trim();
+    let first_err = match serde_json::from_str(raw) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    let repaired = repair_json(raw)
+        .ok_or_else(|| LLMError::ParseError(format!("No JSON in response: '{}'", raw)))?;
+    serde_json::from_str(&repaired).map_err(|e| {
+        LLMError::ParseError(format!("Invalid JSON ({}; after repair: {}): '{}'", first_err, e, raw))
+    })
+}
+
+/// Best-effort fix-up of common malformed JSON from small models:
+/// - prose or markdown fences around the object
+/// - trailing commas
+/// - output truncated mid-string / mid-object (closes open strings and brackets)
+///
+/// Returns None if there is no JSON object or array to salvage.
+pub fn repair_json(raw: &str) -> Option<String> {
+    let start = raw.find(['{', '['])?;
+    let mut out = String::with_capacity(raw.len());
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in raw[start..].chars() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' => {
+                closers.push('}');
+                out.push(c);
+            }
+            '[' => {
+                closers.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                // Stray closer: drop it
+                if closers.last() != Some(&c) {
+                    continue;
+                }
+                trim_dangling(&mut out);
+                out.push(c);
+                closers.pop();
+                // Anything after the top-level value is prose
+                if closers.is_empty() {
+                    return Some(out);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    // Truncated output: close whatever is still open
+    if in_string {
+        if escaped {
+            out.pop();
+        }
+        out.push('"');
+    }
+    while let Some(closer) = closers.pop() {
+        trim_dangling(&mut out);
+        out.push(closer);
+    }
+    Some(out)
+}
+
+/// Drop a trailing comma and complete a dangling `"key":` before a closer is appended
+fn trim_dangling(out: &mut String) {
+    let trimmed_len = out.trim_end().len();
+    out.truncate(trimmed_len);
+    if out.ends_with(',') {
+        out.pop();
+    } else if out.ends_with(':') {
+        out.push_str(" null");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_strips_prose_and_fences() {
+        let raw = "Sure! Here is the plan:\n```json\n{\"steps\": [{\"intent\": \"EDIT\"}]}\n```\nLet me know.";
+        let val: Value = parse_lenient(raw).unwrap();
+        assert_eq!(val["steps"][0]["intent"], "EDIT");
+    }
+
+    #[test]
+    fn test_repair_trailing_commas_and_truncation() {
+        assert_eq!(repair_json("{\"a\": [1, 2,], }").unwrap(), "{\"a\": [1, 2]}");
+        assert_eq!(repair_json("{\"a\": {\"b\": \"unfinished").unwrap(), "{\"a\": {\"b\": \"unfinished\"}}");
+        assert_eq!(repair_json("{\"a\": 1, \"b\":").unwrap(), "{\"a\": 1, \"b\": null}");
+        // Braces inside strings don't count
+        assert_eq!(repair_json("{\"a\": \"}{\"").unwrap(), "{\"a\": \"}{\"}");
+    }
+
+    #[test]
+    fn test_parse_lenient_reports_unsalvageable() {
+        assert!(matches!(parse_lenient::<Value>("true or false?"), Err(LLMError::ParseError(_))));
+    }
+}
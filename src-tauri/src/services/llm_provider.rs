@@ -0,0 +1,615 @@
+// LLM Provider Registry
+//
+// Maps each model role (router, coder, flash, ...) to a concrete `LLMClient`
+// built from `creek.toml`. Providers are named endpoints; roles pick a
+// provider and a model. Anything missing from the file falls back to the
+// built-in DashScope defaults. Each role may also carry request settings
+// (temperature, max_tokens, timeout_ms, enable_thinking); the pipeline polls
+// the file and rebuilds the clients when it changes.
+//
+// Transient failures are retried per `[retry]` / `[circuit_breaker]` (see
+// llm_retry.rs); a role with `fallback` switches to another role's client
+// when its own endpoint is rate limited or down. Coder falls back to flash
+// unless configured otherwise.
+//
+// `[router] output` picks how the intent routers ask for their decision:
+// "json" (schema-constrained, default) or "text" (legacy line formats).
+// `[router] mode` picks "parallel" (three router calls per turn, default) or
+// "fused" (one JSON call that returns plan, RAG need and tool intent together).
+// `[router.rules]` tunes the local rule fast path that runs before either:
+//   [router.rules]
+//   min_confidence = 0.85
+//   languages = ["en", "zh"]
+//
+//   [[router.rules.custom]]
+//   intent = "UNDO"
+//   pattern = "(?i)^nope,? take that back$"
+//   confidence = 0.95
+//
+// The file also holds the app's non-LLM settings; those sections are
+// documented on their types (see the `LLMConfig` fields).
+//
+// Example:
+//   [providers.local]
+//   kind = "ollama"
+//   base_url = "http://localhost:11434"
+//
+//   [roles.coder]
+//   provider = "local"
+//   model = "qwen2.5-coder:7b"
+//   temperature = 0.2
+//
+//   [roles.todo]
+//   provider = "dashscope"
+//   model = "qwen-flash"
+//   timeout_ms = 20000
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use log::info;
+
+use super::anthropic_client::AnthropicClient;
+use super::asr::AsrConfig;
+use super::telemetry::TelemetryConfig;
+use super::llm_client::{LLMClient, ModelSettings, OpenAILikeClient};
+use super::llm_retry::{BreakerPolicy, CircuitBreaker, ResilientClient, RetryPolicy};
+use super::llm_usage::{BudgetConfig, ModelPrice};
+use super::ollama_client::OllamaClient;
+
+pub const DEFAULT_PROVIDER: &str = "dashscope";
+const DASHSCOPE_BASE_URL: &str = "https://dashscope.aliyuncs.com/compatible-mode/v1";
+
+/// The places in the pipeline that talk to an LLM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRole {
+    Router,
+    Coder,
+    Flash,
+    Focus,
+    Todo,
+    CommitMessage,
+    RagQuery,
+    Socratic,
+}
+
+impl ModelRole {
+    pub const ALL: [ModelRole; 8] = [
+        ModelRole::Router,
+        ModelRole::Coder,
+        ModelRole::Flash,
+        ModelRole::Focus,
+        ModelRole::Todo,
+        ModelRole::CommitMessage,
+        ModelRole::RagQuery,
+        ModelRole::Socratic,
+    ];
+
+    /// Name as written in creek.toml
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelRole::Router => "router",
+            ModelRole::Coder => "coder",
+            ModelRole::Flash => "flash",
+            ModelRole::Focus => "focus",
+            ModelRole::Todo => "todo",
+            ModelRole::CommitMessage => "commit_message",
+            ModelRole::RagQuery => "rag_query",
+            ModelRole::Socratic => "socratic",
+        }
+    }
+
+    fn default_model(&self) -> &'static str {
+        match self {
+            ModelRole::Coder => "qwen3-coder-flash",
+            _ => "qwen-flash",
+        }
+    }
+
+    /// Deadlines for the background roles; a slow answer there is worth less than a fallback
+    fn default_settings(&self) -> ModelSettings {
+        let timeout_ms = match self {
+            ModelRole::Focus | ModelRole::CommitMessage => Some(10_000),
+            ModelRole::Todo | ModelRole::Socratic => Some(15_000),
+            ModelRole::RagQuery => Some(3_000),
+            _ => None,
+        };
+        ModelSettings { timeout_ms, ..Default::default() }
+    }
+
+    fn default_fallback(&self) -> Option<ModelRole> {
+        match self {
+            ModelRole::Coder => Some(ModelRole::Flash),
+            _ => None,
+        }
+    }
+}
+
+/// Wire protocol spoken by a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Any OpenAI-compatible `/chat/completions` endpoint (DashScope, vLLM, llama.cpp-server, gateways)
+    #[serde(alias = "openai_compatible")]
+    OpenAI,
+    Anthropic,
+    Ollama,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    pub base_url: String,
+    /// Literal API key (prefer `api_key_env` to keep secrets out of the file)
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Environment variable holding the API key
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleConfig {
+    pub provider: String,
+    pub model: String,
+    /// Overrides the provider's base_url for this role only
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Role whose client takes over when this one is rate limited or its circuit is open
+    #[serde(default)]
+    pub fallback: Option<ModelRole>,
+    #[serde(flatten)]
+    pub settings: ModelSettings,
+}
+
+/// How the intent routers request their output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouterOutput {
+    /// Schema-constrained JSON; falls back to text if the provider can't do it
+    #[default]
+    Json,
+    /// Numbered plan list / `true` / `SEARCH` strings
+    Text,
+}
+
+/// How many router calls a turn makes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouterMode {
+    /// Three independent calls, each with its own prompt and copy of the document
+    #[default]
+    Parallel,
+    /// One JSON call for all three decisions; needs JSON output
+    Fused,
+}
+
+impl RouterMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouterMode::Parallel => "parallel",
+            RouterMode::Fused => "fused",
+        }
+    }
+}
+
+/// A user-defined routing rule. `pattern` is matched against the whole
+/// utterance; `instruction` may reference capture groups (`$1`, `${name}`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSpec {
+    pub intent: String,
+    pub pattern: String,
+    #[serde(default = "default_rule_confidence")]
+    pub confidence: f32,
+    #[serde(default)]
+    pub instruction: Option<String>,
+}
+
+fn default_rule_confidence() -> f32 {
+    0.95
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouterRules {
+    pub enabled: bool,
+    /// A rule must be at least this confident to skip the LLM routers
+    pub min_confidence: f32,
+    /// Built-in rule tables to load
+    pub languages: Vec<String>,
+    pub custom: Vec<RuleSpec>,
+}
+
+impl Default for RouterRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_confidence: 0.85,
+            languages: vec!["en".to_string(), "zh".to_string()],
+            custom: Vec::new(),
+        }
+    }
+}
+
+impl RouterRules {
+    fn validate(&self) -> Result<()> {
+        for rule in &self.custom {
+            regex::Regex::new(&rule.pattern)
+                .with_context(|| format!("Invalid router rule pattern '{}'", rule.pattern))?;
+        }
+        Ok(())
+    }
+}
+
+/// What happens when a turn fires while another is still being processed
+/// (see turn_queue.rs)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TurnPolicy {
+    /// Queue it behind the running turn
+    Serial,
+    /// Merge it into the turn still waiting, if any
+    #[default]
+    CoalescePending,
+    /// Abort the running turn and run this one
+    CancelPrevious,
+}
+
+/// `[turns]`: `policy = "serial" | "coalesce-pending" | "cancel-previous"`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnsConfig {
+    pub policy: TurnPolicy,
+}
+
+/// `[chunking]`: how final transcripts are batched into turns (see
+/// `SpeechAggregator`). Long monologues become several turns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingConfig {
+    /// A finished sentence is released once the buffer holds at least this much
+    pub min_chars: usize,
+    /// Longest turn; longer speech is split at sentence boundaries
+    pub max_chars: usize,
+    /// Quiet time after a transcript before the turn fires
+    pub holdback_ms: u64,
+    /// Longest an unfinished sentence waits for the rest of it
+    pub flush_timeout_ms: u64,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            min_chars: 40,
+            max_chars: 500,
+            holdback_ms: 450,
+            flush_timeout_ms: 2000,
+        }
+    }
+}
+
+impl ChunkingConfig {
+    fn validate(&self) -> Result<()> {
+        if self.max_chars == 0 || self.min_chars > self.max_chars {
+            anyhow::bail!(
+                "Invalid [chunking]: need 0 < min_chars <= max_chars (got {} / {})",
+                self.min_chars, self.max_chars
+            );
+        }
+        Ok(())
+    }
+}
+
+/// `[socratic]`: the agent that questions the document after each turn
+/// (see socratic_agent.rs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SocraticConfig {
+    pub enabled: bool,
+    /// New questions asked after a single turn
+    pub max_questions: usize,
+    /// No new questions while this many are still unanswered
+    pub max_open: usize,
+    /// Have the frontend read new questions aloud
+    pub speak: bool,
+}
+
+impl Default for SocraticConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_questions: 2,
+            max_open: 5,
+            speak: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouterConfig {
+    pub output: RouterOutput,
+    pub mode: RouterMode,
+    pub rules: RouterRules,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LLMConfig {
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderConfig>,
+    #[serde(default)]
+    pub roles: HashMap<ModelRole, RoleConfig>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub circuit_breaker: BreakerPolicy,
+    /// Keyed by model name (see llm_usage.rs)
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPrice>,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub router: RouterConfig,
+    /// Overlapping turns (see `TurnsConfig`)
+    #[serde(default)]
+    pub turns: TurnsConfig,
+    /// Transcript batching into turns (see `ChunkingConfig`)
+    #[serde(default)]
+    pub chunking: ChunkingConfig,
+    /// Speech recognition backend (see asr/mod.rs)
+    #[serde(default)]
+    pub asr: AsrConfig,
+    /// Metric export (see telemetry/mod.rs)
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// Socratic questions (see `SocraticConfig`)
+    #[serde(default)]
+    pub socratic: SocraticConfig,
+}
+
+impl LLMConfig {
+    /// Built-in DashScope configuration
+    pub fn defaults() -> Self {
+        Self::default().with_defaults()
+    }
+
+    /// Load config from a TOML file. A missing file yields the defaults.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::defaults());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: LLMConfig = toml::from_str(content)
+            .context("Failed to parse LLM config")?;
+        config.router.rules.validate()?;
+        config.chunking.validate()?;
+        Ok(config.with_defaults())
+    }
+
+    /// Fill in the built-in provider, any role not configured explicitly,
+    /// and any role setting left unset
+    fn with_defaults(mut self) -> Self {
+        self.providers.entry(DEFAULT_PROVIDER.to_string()).or_insert_with(|| ProviderConfig {
+            kind: ProviderKind::OpenAI,
+            base_url: DASHSCOPE_BASE_URL.to_string(),
+            api_key: None,
+            api_key_env: None,
+        });
+        for role in ModelRole::ALL {
+            let role_cfg = self.roles.entry(role).or_insert_with(|| RoleConfig {
+                provider: DEFAULT_PROVIDER.to_string(),
+                model: role.default_model().to_string(),
+                base_url: None,
+                fallback: None,
+                settings: ModelSettings::default(),
+            });
+            role_cfg.settings = role_cfg.settings.clone().or(&role.default_settings());
+            role_cfg.fallback = role_cfg.fallback.or(role.default_fallback());
+        }
+        self
+    }
+}
+
+/// Endpoint a role talks to (role override, else the provider's)
+fn endpoint(provider: &ProviderConfig, role: &RoleConfig) -> String {
+    role.base_url.as_deref()
+        .unwrap_or(&provider.base_url)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Build a client for one provider/role pair (no retries)
+pub fn build_client(provider: &ProviderConfig, role: &RoleConfig, fallback_api_key: &str) -> Arc<dyn LLMClient> {
+    let api_key = provider.api_key.clone()
+        .or_else(|| provider.api_key_env.as_ref().and_then(|var| std::env::var(var).ok()))
+        .unwrap_or_else(|| fallback_api_key.to_string());
+    let base_url = endpoint(provider, role);
+    let model = role.model.clone();
+    let settings = role.settings.clone();
+
+    match provider.kind {
+        ProviderKind::OpenAI => Arc::new(OpenAILikeClient::new(base_url, api_key, model).with_settings(settings)),
+        ProviderKind::Anthropic => Arc::new(AnthropicClient::new(base_url, api_key, model).with_settings(settings)),
+        ProviderKind::Ollama => Arc::new(OllamaClient::new(base_url, model).with_settings(settings)),
+    }
+}
+
+/// Resolved clients, one per role
+pub struct LLMRegistry {
+    clients: HashMap<ModelRole, Arc<dyn LLMClient>>,
+}
+
+impl LLMRegistry {
+    pub fn from_config(config: &LLMConfig, fallback_api_key: &str) -> Result<Self> {
+        // Raw provider clients first, so fallbacks can be wrapped from the same set
+        let mut raw: HashMap<ModelRole, (Arc<dyn LLMClient>, String)> = HashMap::new();
+        for role in ModelRole::ALL {
+            let role_cfg = config.roles.get(&role)
+                .ok_or_else(|| anyhow::anyhow!("No model configured for role {:?}", role))?;
+            let provider = config.providers.get(&role_cfg.provider)
+                .ok_or_else(|| anyhow::anyhow!("Unknown provider '{}' for role {:?}", role_cfg.provider, role))?;
+
+            info!("[LLM Registry] {:?} -> {} ({:?}) {}", role, role_cfg.provider, provider.kind, role_cfg.model);
+            raw.insert(role, (build_client(provider, role_cfg, fallback_api_key), endpoint(provider, role_cfg)));
+        }
+
+        let resilient = |role: ModelRole| {
+            let (inner, endpoint) = &raw[&role];
+            let breaker = CircuitBreaker::for_endpoint(endpoint, &config.circuit_breaker);
+            ResilientClient::new(inner.clone(), endpoint, config.retry.clone(), breaker)
+        };
+
+        // Fallbacks are one level deep: a fallback client never falls back again
+        let mut clients: HashMap<ModelRole, Arc<dyn LLMClient>> = HashMap::new();
+        for role in ModelRole::ALL {
+            let mut client = resilient(role);
+            if let Some(fallback) = config.roles[&role].fallback.filter(|fb| *fb != role) {
+                client = client.with_fallback(Arc::new(resilient(fallback)));
+            }
+            clients.insert(role, Arc::new(client));
+        }
+        Ok(Self { clients })
+    }
+
+    /// One client per role from `make` (offline replay, wrapping another registry's clients)
+    pub fn from_fn(mut make: impl FnMut(ModelRole) -> Arc<dyn LLMClient>) -> Self {
+        let clients = ModelRole::ALL.iter().map(|&role| (role, make(role))).collect();
+        Self { clients }
+    }
+
+    /// Every role is populated by `from_config` and `from_fn`, so this never misses.
+    pub fn get(&self, role: ModelRole) -> Arc<dyn LLMClient> {
+        self.clients[&role].clone()
+    }
+}
+
+/// Polls the config file's mtime so the pipeline can rebuild clients on change
+pub struct ConfigWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let last_modified = Self::modified(&path);
+        Self { path, last_modified }
+    }
+
+    /// Returns the re-read config if the file changed (or was created/removed) since the last poll
+    pub fn poll(&mut self) -> Option<Result<LLMConfig>> {
+        let modified = Self::modified(&self.path);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+        info!("[LLM Registry] {} changed, reloading", self.path.display());
+        Some(LLMConfig::load(&self.path))
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_cover_all_roles() {
+        let config = LLMConfig::parse("").unwrap();
+        assert_eq!(config.roles.len(), ModelRole::ALL.len());
+        assert_eq!(config.roles[&ModelRole::Coder].model, "qwen3-coder-flash");
+        assert_eq!(config.providers[DEFAULT_PROVIDER].kind, ProviderKind::OpenAI);
+    }
+
+    #[test]
+    fn test_role_override() {
+        let config = LLMConfig::parse(r#"
+            [providers.local]
+            kind = "ollama"
+            base_url = "http://localhost:11434"
+
+            [roles.coder]
+            provider = "local"
+            model = "qwen2.5-coder:7b"
+        "#).unwrap();
+
+        assert_eq!(config.roles[&ModelRole::Coder].provider, "local");
+        assert_eq!(config.roles[&ModelRole::Router].provider, DEFAULT_PROVIDER);
+        assert!(LLMRegistry::from_config(&config, "sk-test").is_ok());
+    }
+
+    #[test]
+    fn test_unknown_provider_is_rejected() {
+        let config = LLMConfig::parse(r#"
+            [roles.router]
+            provider = "missing"
+            model = "x"
+        "#).unwrap();
+        assert!(LLMRegistry::from_config(&config, "sk-test").is_err());
+    }
+
+    #[test]
+    fn test_role_settings_merge_with_defaults() {
+        let config = LLMConfig::parse(r#"
+            [roles.todo]
+            provider = "dashscope"
+            model = "qwen-plus"
+            temperature = 0.3
+            max_tokens = 512
+
+            [roles.router]
+            provider = "dashscope"
+            model = "qwen-flash"
+            base_url = "http://localhost:8000/v1"
+            enable_thinking = false
+        "#).unwrap();
+
+        let todo = &config.roles[&ModelRole::Todo].settings;
+        assert_eq!(todo.temperature, Some(0.3));
+        assert_eq!(todo.max_tokens, Some(512));
+        assert_eq!(todo.timeout_ms, Some(15_000));
+
+        let router = &config.roles[&ModelRole::Router];
+        assert_eq!(router.base_url.as_deref(), Some("http://localhost:8000/v1"));
+        assert_eq!(router.settings.enable_thinking, Some(false));
+        assert_eq!(router.settings.timeout_ms, None);
+
+        assert_eq!(config.roles[&ModelRole::RagQuery].settings.timeout_ms, Some(3_000));
+        assert_eq!(config.roles[&ModelRole::Coder].fallback, Some(ModelRole::Flash));
+        assert_eq!(config.retry, RetryPolicy::default());
+    }
+
+    #[test]
+    fn test_chunking_overrides_and_validation() {
+        let config = LLMConfig::parse("[chunking]\nmax_chars = 200\nholdback_ms = 600\n").unwrap();
+        assert_eq!(config.chunking.max_chars, 200);
+        assert_eq!(config.chunking.min_chars, 40);
+        assert_eq!(config.chunking.holdback_ms, 600);
+
+        assert!(LLMConfig::parse("[chunking]\nmin_chars = 300\nmax_chars = 200\n").is_err());
+    }
+
+    #[test]
+    fn test_watcher_picks_up_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("creek.toml");
+        let mut watcher = ConfigWatcher::new(path.clone());
+        assert!(watcher.poll().is_none());
+
+        std::fs::write(&path, "[roles.coder]\nprovider = \"dashscope\"\nmodel = \"qwen-max\"\n").unwrap();
+        let config = watcher.poll().expect("new file should be reported").unwrap();
+        assert_eq!(config.roles[&ModelRole::Coder].model, "qwen-max");
+        assert!(watcher.poll().is_none());
+    }
+}
@@ -0,0 +1,574 @@
+// LLM Retry / Circuit Breaker
+//
+// `ResilientClient` wraps a provider client with:
+// - retries with jittered exponential backoff (honouring 429 `Retry-After`
+//   up to `max_delay_ms`; a longer wait fails fast so the fallback can answer)
+// - a circuit breaker shared by every client on the same endpoint, so an
+//   outage fails fast instead of stalling each turn for the full retry budget.
+//   After the cooldown it lets a single probe through (half-open); the
+//   probe's outcome closes or re-opens it
+// - an optional fallback client (e.g. coder -> flash) used when the primary
+//   is rate limited or its circuit is open
+//
+// A stream that drops after its first chunk can't be retried transparently
+// (the caller has already consumed part of it): it ends with
+// `StreamInterrupted`, which counts against the breaker of the endpoint that
+// served it. `chat()` collects the whole response, so it replays those.
+//
+// Tuned from creek.toml:
+//   [retry]
+//   max_retries = 3
+//   base_delay_ms = 500
+//
+//   [circuit_breaker]
+//   failure_threshold = 5
+//   cooldown_ms = 30000
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::llm_client::{ChatMessage, LLMClient, LLMError, UsageSink};
+use super::llm_json::ResponseFormat;
+use super::llm_tools::{ToolChoice, ToolCompletion, ToolDefinition};
+
+type ChunkStream = Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Equal-jitter exponential backoff: half the exponential step plus a random half
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay_ms
+            .saturating_mul(1u64 << attempt.min(16))
+            .min(self.max_delay_ms);
+        let half = exp / 2;
+        Duration::from_millis(half + (half as f64 * jitter()) as u64)
+    }
+
+    /// None when the server asks us to wait longer than `max_delay_ms`
+    fn delay_for(&self, err: &LLMError, attempt: u32) -> Option<Duration> {
+        match err {
+            LLMError::RateLimited { retry_after: Some(d) } => {
+                (*d <= Duration::from_millis(self.max_delay_ms)).then_some(*d)
+            }
+            _ => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BreakerPolicy {
+    /// Consecutive endpoint failures before the circuit opens
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a trial request is let through
+    pub cooldown_ms: u64,
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// Half-open: when the single trial request was let through
+    probe_started: Option<Instant>,
+}
+
+pub struct CircuitBreaker {
+    policy: Mutex<BreakerPolicy>,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(policy: BreakerPolicy) -> Self {
+        Self {
+            policy: Mutex::new(policy),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Shared breaker for an endpoint; the latest policy (e.g. after a config reload) applies
+    pub fn for_endpoint(endpoint: &str, policy: &BreakerPolicy) -> Arc<Self> {
+        static BREAKERS: OnceLock<Mutex<HashMap<String, Arc<CircuitBreaker>>>> = OnceLock::new();
+        let breaker = BREAKERS.get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry(endpoint.to_string())
+            .or_insert_with(|| Arc::new(Self::new(policy.clone())))
+            .clone();
+        *breaker.policy.lock().unwrap() = policy.clone();
+        breaker
+    }
+
+    fn cooldown(&self) -> Duration {
+        Duration::from_millis(self.policy.lock().unwrap().cooldown_ms)
+    }
+
+    /// Closed, or open long enough that this request may be the one trial request.
+    /// A probe that never reports back (cancelled) is replaced after another cooldown.
+    pub fn allow(&self) -> bool {
+        let cooldown = self.cooldown();
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match state.open_until {
+            None => true,
+            Some(until) if now < until => false,
+            Some(_) => match state.probe_started {
+                Some(started) if now < started + cooldown => false,
+                _ => {
+                    state.probe_started = Some(now);
+                    true
+                }
+            },
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let policy = self.policy.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        // A failed probe re-opens the circuit straight away
+        if state.probe_started.take().is_some() || state.consecutive_failures >= policy.failure_threshold {
+            state.open_until = Some(Instant::now() + Duration::from_millis(policy.cooldown_ms));
+        }
+    }
+
+    /// The probe got an answer that says nothing about the endpoint's health
+    /// (rate limit, bad request); the next request may probe again
+    pub fn release_probe(&self) {
+        self.state.lock().unwrap().probe_started = None;
+    }
+}
+
+/// Failures that say something about the endpoint's health.
+/// Rate limits and bad requests don't: the endpoint is up, it just said no.
+fn is_endpoint_failure(err: &LLMError) -> bool {
+    matches!(
+        err,
+        LLMError::RequestFailed(_) | LLMError::Timeout | LLMError::StreamInterrupted(_)
+    )
+}
+
+fn should_fall_back(err: &LLMError) -> bool {
+    matches!(err, LLMError::RateLimited { .. } | LLMError::CircuitOpen(_))
+}
+
+pub struct ResilientClient {
+    inner: Arc<dyn LLMClient>,
+    endpoint: String,
+    policy: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    fallback: Option<Arc<dyn LLMClient>>,
+}
+
+impl ResilientClient {
+    pub fn new(inner: Arc<dyn LLMClient>, endpoint: &str, policy: RetryPolicy, breaker: Arc<CircuitBreaker>) -> Self {
+        Self {
+            inner,
+            endpoint: endpoint.to_string(),
+            policy,
+            breaker,
+            fallback: None,
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: Arc<dyn LLMClient>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Run `op` against this endpoint, retrying transient failures. `settled`:
+    /// the answer is complete when `op` returns; a stream still has to be read,
+    /// so its success is recorded when it ends (see `guard_stream`).
+    async fn with_retries<T, F, Fut>(&self, settled: bool, mut op: F) -> Result<T, LLMError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, LLMError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            if !self.breaker.allow() {
+                return Err(LLMError::CircuitOpen(self.endpoint.clone()));
+            }
+
+            let err = match op().await {
+                Ok(value) => {
+                    if settled {
+                        self.breaker.record_success();
+                    }
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+
+            if is_endpoint_failure(&err) {
+                self.breaker.record_failure();
+            } else {
+                self.breaker.release_probe();
+            }
+            if !err.is_retryable() || attempt >= self.policy.max_retries {
+                return Err(err);
+            }
+
+            let Some(delay) = self.policy.delay_for(&err, attempt) else {
+                warn!("[LLM Retry] {} asked to wait longer than {}ms, giving up", self.endpoint, self.policy.max_delay_ms);
+                return Err(err);
+            };
+            warn!("[LLM Retry] {} failed ({}), retry {}/{} in {:?}",
+                self.endpoint, err, attempt + 1, self.policy.max_retries, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Open the stream, retrying transient failures on this endpoint, else on
+    /// the fallback. The flag says whether this endpoint served it.
+    async fn open_stream(&self, messages: Vec<ChatMessage>, usage: UsageSink) -> Result<(ChunkStream, bool), LLMError> {
+        match self.with_retries(false, || self.inner.stream_completion_with_usage(messages.clone(), usage.clone())).await {
+            Ok(stream) => Ok((stream, true)),
+            Err(err) if should_fall_back(&err) && self.fallback.is_some() => {
+                warn!("[LLM Retry] {} unavailable ({}), using fallback model", self.endpoint, err);
+                let stream = self.fallback.as_ref().unwrap().stream_completion_with_usage(messages, usage).await?;
+                Ok((stream, false))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Record how this endpoint's stream ends: cleanly, or dropped midway
+    fn guard_stream(&self, stream: ChunkStream) -> ChunkStream {
+        let endpoint = self.endpoint.clone();
+        Box::pin(futures_util::stream::unfold(
+            (stream, Some(self.breaker.clone())),
+            move |(mut stream, breaker)| {
+                let endpoint = endpoint.clone();
+                async move {
+                    let breaker = breaker?;
+                    match stream.next().await {
+                        Some(Err(LLMError::StreamInterrupted(msg))) => {
+                            warn!("[LLM Retry] {} stream interrupted ({})", endpoint, msg);
+                            breaker.record_failure();
+                            Some((Err(LLMError::StreamInterrupted(msg)), (stream, None)))
+                        }
+                        Some(chunk) => Some((chunk, (stream, Some(breaker)))),
+                        None => {
+                            breaker.record_success();
+                            None
+                        }
+                    }
+                }
+            },
+        ))
+    }
+
+    async fn collect(&self, messages: &[ChatMessage]) -> Result<String, LLMError> {
+        let mut stream = self.stream_completion(messages.to_vec()).await?;
+        let mut full_text = String::new();
+        while let Some(chunk) = stream.next().await {
+            full_text.push_str(&chunk?);
+        }
+        Ok(full_text)
+    }
+}
+
+#[async_trait]
+impl LLMClient for ResilientClient {
+    async fn stream_completion(&self, messages: Vec<ChatMessage>) -> Result<ChunkStream, LLMError> {
+        self.stream_completion_with_usage(messages, UsageSink::none()).await
+    }
+
+    /// A mid-stream drop of this endpoint's stream counts as a breaker failure;
+    /// the fallback's drops are its own breaker's business
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<ChunkStream, LLMError> {
+        let (stream, primary) = self.open_stream(messages, usage).await?;
+        Ok(if primary { self.guard_stream(stream) } else { stream })
+    }
+
+    /// Whole-response calls can also be replayed when the stream drops midway
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        _options: Option<serde_json::Value>,
+    ) -> Result<String, LLMError> {
+        let mut attempt = 0;
+        loop {
+            match self.collect(&messages).await {
+                Err(LLMError::StreamInterrupted(msg)) if attempt < self.policy.max_retries => {
+                    let delay = self.policy.backoff(attempt);
+                    warn!("[LLM Retry] {} stream interrupted ({}), replaying in {:?}", self.endpoint, msg, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        let result = self.with_retries(true, || {
+            self.inner.complete_with_tools(messages.clone(), tools.clone(), tool_choice.clone(), usage.clone())
+        }).await;
+        match result {
+            Err(err) if should_fall_back(&err) && self.fallback.is_some() => {
+                warn!("[LLM Retry] {} unavailable ({}), using fallback model", self.endpoint, err);
+                self.fallback.as_ref().unwrap().complete_with_tools(messages, tools, tool_choice, usage).await
+            }
+            result => result,
+        }
+    }
+
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        let result = self.with_retries(true, || {
+            self.inner.complete_json(messages.clone(), format.clone(), usage.clone())
+        }).await;
+        match result {
+            Err(err) if should_fall_back(&err) && self.fallback.is_some() => {
+                warn!("[LLM Retry] {} unavailable ({}), using fallback model", self.endpoint, err);
+                self.fallback.as_ref().unwrap().complete_json(messages, format, usage).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Uniform-ish value in [0, 1) without pulling in a rand crate
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos());
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with the queued errors, then answers "ok"
+    struct Flaky {
+        errors: Mutex<Vec<LLMError>>,
+        calls: AtomicU32,
+    }
+
+    impl Flaky {
+        fn new(mut errors: Vec<LLMError>) -> Arc<Self> {
+            errors.reverse();
+            Arc::new(Self { errors: Mutex::new(errors), calls: AtomicU32::new(0) })
+        }
+    }
+
+    #[async_trait]
+    impl LLMClient for Flaky {
+        async fn stream_completion(
+            &self,
+            _messages: Vec<ChatMessage>,
+        ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if let Some(err) = self.errors.lock().unwrap().pop() {
+                return Err(err);
+            }
+            Ok(Box::pin(futures_util::stream::iter(vec![Ok("ok".to_string())])))
+        }
+    }
+
+    /// Streams one chunk, then drops
+    struct Interrupted {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl LLMClient for Interrupted {
+        async fn stream_completion(&self, _messages: Vec<ChatMessage>) -> Result<ChunkStream, LLMError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::pin(futures_util::stream::iter(vec![
+                Ok("par".to_string()),
+                Err(LLMError::StreamInterrupted("connection reset".into())),
+            ])))
+        }
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy { max_retries: 2, base_delay_ms: 1, max_delay_ms: 2 }
+    }
+
+    fn breaker() -> Arc<CircuitBreaker> {
+        Arc::new(CircuitBreaker::new(BreakerPolicy { failure_threshold: 2, cooldown_ms: 60_000 }))
+    }
+
+    #[test]
+    fn test_status_classification() {
+        assert!(matches!(LLMError::from_status(429, Some(Duration::from_secs(3)), ""),
+            LLMError::RateLimited { retry_after: Some(d) } if d.as_secs() == 3));
+        assert!(matches!(LLMError::from_status(401, None, ""), LLMError::Auth(_)));
+        assert!(matches!(LLMError::from_status(400, None, "Range of input length should be... context length exceeded"),
+            LLMError::ContextTooLong(_)));
+        assert!(matches!(LLMError::from_status(400, None, "bad field"), LLMError::InvalidRequest(_)));
+        assert!(LLMError::from_status(503, None, "").is_retryable());
+    }
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let policy = RetryPolicy { max_retries: 5, base_delay_ms: 100, max_delay_ms: 1_000 };
+        for attempt in 0..10 {
+            let delay = policy.backoff(attempt).as_millis() as u64;
+            let step = (100u64 << attempt).min(1_000);
+            assert!(delay >= step / 2 && delay <= step, "attempt {} gave {}ms", attempt, delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let inner = Flaky::new(vec![
+            LLMError::RequestFailed("502".into()),
+            LLMError::RateLimited { retry_after: Some(Duration::from_millis(1)) },
+        ]);
+        let client = ResilientClient::new(inner.clone(), "test", fast_policy(), breaker());
+        assert_eq!(client.chat(vec![], None).await.unwrap(), "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_auth() {
+        let inner = Flaky::new(vec![LLMError::Auth("bad key".into())]);
+        let client = ResilientClient::new(inner.clone(), "test", fast_policy(), breaker());
+        assert!(matches!(client.chat(vec![], None).await, Err(LLMError::Auth(_))));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_and_falls_back() {
+        let inner = Flaky::new((0..10).map(|_| LLMError::Timeout).collect());
+        let fallback = Flaky::new(vec![]);
+        let client = ResilientClient::new(inner.clone(), "test", fast_policy(), breaker())
+            .with_fallback(fallback.clone());
+
+        // Two timeouts trip the breaker; the third attempt is refused and the fallback answers
+        assert_eq!(client.chat(vec![], None).await.unwrap(), "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(fallback.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_long_retry_after_fails_fast_to_fallback() {
+        let inner = Flaky::new(vec![LLMError::RateLimited { retry_after: Some(Duration::from_secs(3600)) }]);
+        let fallback = Flaky::new(vec![]);
+        let client = ResilientClient::new(inner.clone(), "test", fast_policy(), breaker())
+            .with_fallback(fallback.clone());
+        assert_eq!(client.chat(vec![], None).await.unwrap(), "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(fallback.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_half_open_admits_one_probe() {
+        let breaker = CircuitBreaker::new(BreakerPolicy { failure_threshold: 1, cooldown_ms: 20 });
+        breaker.record_failure();
+        assert!(!breaker.allow());
+        std::thread::sleep(Duration::from_millis(25));
+
+        // One probe; concurrent requests wait for its outcome
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        breaker.record_failure();
+        assert!(!breaker.allow());
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert!(breaker.allow() && breaker.allow());
+    }
+
+    #[test]
+    fn test_endpoint_breaker_picks_up_new_policy() {
+        let endpoint = "test-policy-reload";
+        let first = CircuitBreaker::for_endpoint(endpoint, &BreakerPolicy { failure_threshold: 5, cooldown_ms: 60_000 });
+        let reloaded = CircuitBreaker::for_endpoint(endpoint, &BreakerPolicy { failure_threshold: 1, cooldown_ms: 60_000 });
+        assert!(Arc::ptr_eq(&first, &reloaded));
+        first.record_failure();
+        assert!(!first.allow());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_tools_are_not_retried() {
+        let inner = Flaky::new(vec![]);
+        let client = ResilientClient::new(inner.clone(), "test", fast_policy(), breaker());
+        let result = client.complete_with_tools(vec![], vec![], ToolChoice::Auto, UsageSink::none()).await;
+        assert!(matches!(result, Err(LLMError::Unsupported(_))));
+        assert!(client.breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn test_mid_stream_drop_counts_against_the_serving_endpoint_only() {
+        // Primary drops mid-stream: the caller gets the typed error, the breaker a failure
+        let inner = Arc::new(Interrupted { calls: AtomicU32::new(0) });
+        let client = ResilientClient::new(inner.clone(), "test", fast_policy(), breaker());
+        for _ in 0..2 {
+            let chunks: Vec<_> = client.stream_completion(vec![]).await.unwrap().collect().await;
+            assert!(matches!(chunks.last(), Some(Err(LLMError::StreamInterrupted(_)))));
+        }
+        assert!(!client.breaker.allow());
+
+        // The fallback's drops leave the primary's breaker alone
+        let inner = Flaky::new((0..10).map(|_| LLMError::RateLimited { retry_after: Some(Duration::from_secs(3600)) }).collect());
+        let fallback = Arc::new(Interrupted { calls: AtomicU32::new(0) });
+        let client = ResilientClient::new(inner, "test", fast_policy(), breaker())
+            .with_fallback(fallback.clone());
+        assert!(matches!(client.chat(vec![], None).await, Err(LLMError::StreamInterrupted(_))));
+        assert_eq!(fallback.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(client.breaker.state.lock().unwrap().consecutive_failures, 0);
+        assert!(client.breaker.allow());
+    }
+}
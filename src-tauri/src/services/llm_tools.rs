@@ -0,0 +1,206 @@
+// LLM Tool Calling
+//
+// Provider-neutral types for native function/tool calling. A caller offers
+// `ToolDefinition`s (name + JSON schema for the arguments); the model answers
+// with `ToolCall`s whose arguments are deserialized into a typed struct, so a
+// malformed answer is a typed error instead of a regex miss.
+//
+// Providers stream tool calls as fragments (`ToolCallDelta`) keyed by index;
+// `ToolCallAccumulator` stitches them back together.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use super::llm_client::LLMError;
+
+/// A function the model may call. `parameters` is a JSON schema object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+impl ToolDefinition {
+    pub fn new(name: &str, description: &str, parameters: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+        }
+    }
+
+    /// OpenAI / Ollama `tools[]` entry
+    pub(crate) fn to_openai(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+    }
+
+    /// Anthropic `tools[]` entry
+    pub(crate) fn to_anthropic(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "input_schema": self.parameters,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolChoice {
+    /// Model decides whether to call a tool
+    Auto,
+    /// Model must call some tool
+    Required,
+    /// Model must call this tool
+    Tool(String),
+}
+
+impl ToolChoice {
+    pub(crate) fn to_openai(&self) -> Value {
+        match self {
+            ToolChoice::Auto => json!("auto"),
+            ToolChoice::Required => json!("required"),
+            ToolChoice::Tool(name) => json!({ "type": "function", "function": { "name": name } }),
+        }
+    }
+
+    pub(crate) fn to_anthropic(&self) -> Value {
+        match self {
+            ToolChoice::Auto => json!({ "type": "auto" }),
+            ToolChoice::Required => json!({ "type": "any" }),
+            ToolChoice::Tool(name) => json!({ "type": "tool", "name": name }),
+        }
+    }
+}
+
+/// A complete tool call. `arguments` is the raw JSON text produced by the model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+impl ToolCall {
+    /// Deserialize the arguments into the tool's typed input
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T, LLMError> {
+        serde_json::from_str(&self.arguments).map_err(|e| {
+            LLMError::ParseError(format!("Invalid arguments for tool '{}': {} ({})", self.name, e, self.arguments))
+        })
+    }
+}
+
+/// One streamed fragment of a tool call
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallDelta {
+    pub index: u64,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+impl ToolCallDelta {
+    /// Parse an OpenAI `choices[0].delta.tool_calls[]` entry
+    pub(crate) fn from_openai(val: &Value) -> Self {
+        Self {
+            index: val["index"].as_u64().unwrap_or(0),
+            id: val["id"].as_str().map(str::to_string),
+            name: val["function"]["name"].as_str().map(str::to_string),
+            arguments: val["function"]["arguments"].as_str().map(str::to_string),
+        }
+    }
+}
+
+/// Reassembles streamed tool-call fragments, in index order
+#[derive(Debug, Default)]
+pub struct ToolCallAccumulator {
+    calls: BTreeMap<u64, ToolCall>,
+}
+
+impl ToolCallAccumulator {
+    pub fn push(&mut self, delta: ToolCallDelta) {
+        let call = self.calls.entry(delta.index).or_default();
+        if let Some(id) = delta.id {
+            call.id = id;
+        }
+        if let Some(name) = delta.name {
+            call.name = name;
+        }
+        if let Some(arguments) = delta.arguments {
+            call.arguments.push_str(&arguments);
+        }
+    }
+
+    pub fn finish(self) -> Vec<ToolCall> {
+        self.calls.into_values().collect()
+    }
+}
+
+/// Result of a tool-enabled completion
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCompletion {
+    /// Any plain text the model produced alongside (or instead of) tool calls
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ToolCompletion {
+    /// Arguments of the first call to `name`, typed
+    pub fn arguments_for<T: DeserializeOwned>(&self, name: &str) -> Result<T, LLMError> {
+        self.tool_calls.iter()
+            .find(|call| call.name == name)
+            .ok_or_else(|| LLMError::ParseError(format!("Model did not call tool '{}'", name)))?
+            .parse_arguments()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulates_interleaved_fragments() {
+        let mut acc = ToolCallAccumulator::default();
+        let chunks = [
+            json!({"index": 0, "id": "call_a", "function": {"name": "update_todos", "arguments": ""}}),
+            json!({"index": 1, "id": "call_b", "function": {"name": "noop", "arguments": "{}"}}),
+            json!({"index": 0, "function": {"arguments": "{\"operations\": ["}}),
+            json!({"index": 0, "function": {"arguments": "{\"action\": \"add\", \"desc\": \"x\"}]}"}}),
+        ];
+        for chunk in &chunks {
+            acc.push(ToolCallDelta::from_openai(chunk));
+        }
+
+        let calls = acc.finish();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_a");
+        let args: Value = calls[0].parse_arguments().unwrap();
+        assert_eq!(args["operations"][0]["desc"], "x");
+        assert_eq!(calls[1].name, "noop");
+    }
+
+    #[test]
+    fn test_missing_or_malformed_call_is_parse_error() {
+        #[derive(Debug, Deserialize)]
+        struct Args {
+            #[allow(dead_code)]
+            query: String,
+        }
+
+        let completion = ToolCompletion {
+            content: String::new(),
+            tool_calls: vec![ToolCall { id: "1".into(), name: "search".into(), arguments: "{\"q\": 1}".into() }],
+        };
+        assert!(matches!(completion.arguments_for::<Args>("search"), Err(LLMError::ParseError(_))));
+        assert!(matches!(completion.arguments_for::<Args>("other"), Err(LLMError::ParseError(_))));
+    }
+}
@@ -0,0 +1,129 @@
+// LLM Usage: pricing/budget config and a client wrapper that meters every call
+//
+// Token counts come from the providers (see `UsageSink`); `MeteredClient` binds a
+// sink to a client so callers that only know `stream_completion` are still
+// accounted for. Persistence and attribution live in modules::usage_tracker.
+//
+// creek.toml:
+//   [pricing.qwen-flash]
+//   input_per_mtok = 0.15
+//   output_per_mtok = 1.5
+//
+//   [budget]
+//   recording_tokens = 500000
+//   workspace_cost = 10.0
+//   action = "block"
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+use futures_util::StreamExt;
+
+use super::llm_client::{ChatMessage, LLMClient, LLMError, TokenUsage, UsageSink};
+use super::llm_json::ResponseFormat;
+use super::llm_tools::{ToolChoice, ToolCompletion, ToolDefinition};
+
+/// USD per million tokens
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_per_mtok
+            + usage.completion_tokens as f64 * self.output_per_mtok) / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Toast once when a limit is crossed, keep going
+    #[default]
+    Warn,
+    /// Stop processing turns until the limit is raised
+    Block,
+}
+
+/// Optional spending limits. Unset limits are not enforced.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    pub recording_tokens: Option<u64>,
+    pub recording_cost: Option<f64>,
+    pub workspace_tokens: Option<u64>,
+    pub workspace_cost: Option<f64>,
+    pub action: BudgetAction,
+}
+
+/// Reports the usage of every completion made through it to a fixed sink,
+/// in addition to any sink the caller passes. Streams also report their
+/// time to first token (`llm_ttft_ms` histogram, see telemetry/mod.rs).
+pub struct MeteredClient {
+    inner: Arc<dyn LLMClient>,
+    sink: UsageSink,
+    caller: &'static str,
+}
+
+impl MeteredClient {
+    pub fn new(inner: Arc<dyn LLMClient>, sink: UsageSink) -> Self {
+        Self { inner, sink, caller: "unknown" }
+    }
+
+    /// Label for the client's metrics
+    pub fn with_caller(mut self, caller: &'static str) -> Self {
+        self.caller = caller;
+        self
+    }
+}
+
+#[async_trait]
+impl LLMClient for MeteredClient {
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        self.stream_completion_with_usage(messages, UsageSink::none()).await
+    }
+
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        let started = Instant::now();
+        let stream = self.inner.stream_completion_with_usage(messages, self.sink.and(usage)).await?;
+        let caller = self.caller;
+        let mut first = true;
+        Ok(Box::pin(stream.inspect(move |chunk| {
+            if first && chunk.is_ok() {
+                first = false;
+                tracing::info!(histogram.llm_ttft_ms = started.elapsed().as_millis() as u64, caller);
+            }
+        })))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        self.inner.complete_with_tools(messages, tools, tool_choice, self.sink.and(usage)).await
+    }
+
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        self.inner.complete_json(messages, format, self.sink.and(usage)).await
+    }
+}
@@ -1,2 +1,12 @@
 pub mod llm_client;
+pub mod llm_tools;
+pub mod llm_json;
+pub mod llm_provider;
+pub mod llm_retry;
+pub mod llm_usage;
+pub mod llm_cassette;
+pub mod anthropic_client;
+pub mod ollama_client;
+pub mod asr;
 pub mod asr_service;
+pub mod telemetry;
@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use futures_util::StreamExt;
+use futures_util::future;
+use std::pin::Pin;
+
+use super::llm_json::ResponseFormat;
+use super::llm_client::{collect_stream, drain_lines, ChatMessage, LLMClient, LLMError, ModelSettings, TokenUsage, UsageSink};
+use super::llm_tools::{ToolCall, ToolChoice, ToolCompletion, ToolDefinition};
+
+/// Native Ollama chat client (`POST {base_url}/api/chat`).
+/// Ollama streams newline-delimited JSON objects rather than SSE.
+/// For llama.cpp-server use the OpenAI-compatible provider instead.
+pub struct OllamaClient {
+    client: Client,
+    base_url: String,
+    model: String,
+    settings: ModelSettings,
+}
+
+impl OllamaClient {
+    pub fn new(base_url: String, model: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+            model,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    fn request_body(&self, messages: Vec<ChatMessage>) -> Value {
+        let mut body = json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": true,
+        });
+
+        // Sampling knobs live under `options`; `num_predict` is Ollama's max_tokens
+        let mut options = serde_json::Map::new();
+        if let Some(temperature) = self.settings.temperature {
+            options.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(max_tokens) = self.settings.max_tokens {
+            options.insert("num_predict".into(), json!(max_tokens));
+        }
+        if !options.is_empty() {
+            body["options"] = Value::Object(options);
+        }
+        if let Some(enable_thinking) = self.settings.enable_thinking {
+            body["think"] = json!(enable_thinking);
+        }
+        body
+    }
+
+    async fn post(&self, body: &Value) -> Result<reqwest::Response, LLMError> {
+        let url = format!("{}/api/chat", self.base_url);
+        let mut req = self.client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(body);
+        if let Some(timeout) = self.settings.timeout() {
+            req = req.timeout(timeout);
+        }
+
+        let resp = req
+            .send()
+            .await
+            .map_err(LLMError::from_reqwest)?;
+
+        if !resp.status().is_success() {
+            return Err(LLMError::from_response(resp).await);
+        }
+        Ok(resp)
+    }
+
+    /// POST a chat body and stream back the content deltas
+    async fn stream_body(
+        &self,
+        body: &Value,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        let resp = self.post(body).await?;
+
+        // The final object (`done: true`) carries prompt_eval_count / eval_count
+        let model = self.model.clone();
+        let stream_mapped = resp.bytes_stream().scan(String::new(), move |buf, chunk_result| {
+            future::ready(match chunk_result {
+                Ok(bytes) => {
+                    buf.push_str(&String::from_utf8_lossy(&bytes));
+
+                    let mut delta_content = String::new();
+                    for line in drain_lines(buf) {
+                        if let Ok(val) = serde_json::from_str::<Value>(&line) {
+                            if let Some(content) = val["message"]["content"].as_str() {
+                                delta_content.push_str(content);
+                            }
+                            if val["done"].as_bool() == Some(true) {
+                                usage.report(Self::usage_of(&model, &val));
+                            }
+                        }
+                    }
+
+                    Some(Ok(delta_content))
+                }
+                Err(e) => Some(Err(LLMError::from_stream(e))),
+            })
+        });
+
+        Ok(Box::pin(stream_mapped))
+    }
+
+    fn usage_of(model: &str, val: &Value) -> TokenUsage {
+        TokenUsage {
+            model: model.to_string(),
+            prompt_tokens: val["prompt_eval_count"].as_u64().unwrap_or(0),
+            completion_tokens: val["eval_count"].as_u64().unwrap_or(0),
+        }
+    }
+}
+
+#[async_trait]
+impl LLMClient for OllamaClient {
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        self.stream_completion_with_usage(messages, UsageSink::none()).await
+    }
+
+    async fn stream_completion_with_usage(
+        &self,
+        messages: Vec<ChatMessage>,
+        usage: UsageSink,
+    ) -> Result<Pin<Box<dyn futures_util::Stream<Item = Result<String, LLMError>> + Send>>, LLMError> {
+        self.stream_body(&self.request_body(messages), usage).await
+    }
+
+    /// Ollama takes the JSON schema directly in `format`
+    async fn complete_json(
+        &self,
+        messages: Vec<ChatMessage>,
+        format: ResponseFormat,
+        usage: UsageSink,
+    ) -> Result<String, LLMError> {
+        let mut body = self.request_body(messages);
+        body["format"] = format.schema;
+        collect_stream(self.stream_body(&body, usage).await?).await
+    }
+
+    /// Ollama has no `tool_choice`; the choice is ignored and the model decides.
+    /// Tool calls are not streamed incrementally, so this uses a single non-streaming request.
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+        _tool_choice: ToolChoice,
+        usage: UsageSink,
+    ) -> Result<ToolCompletion, LLMError> {
+        let mut body = self.request_body(messages);
+        body["stream"] = json!(false);
+        body["tools"] = Value::Array(tools.iter().map(ToolDefinition::to_openai).collect());
+
+        let resp = self.post(&body).await?;
+        let val: Value = resp.json().await.map_err(|e| LLMError::ParseError(e.to_string()))?;
+        usage.report(Self::usage_of(&self.model, &val));
+
+        // Arguments come back as a JSON object rather than a string
+        let tool_calls = val["message"]["tool_calls"].as_array().into_iter().flatten()
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: format!("call_{}", i),
+                name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call["function"]["arguments"].to_string(),
+            })
+            .collect();
+
+        Ok(ToolCompletion {
+            content: val["message"]["content"].as_str().unwrap_or_default().to_string(),
+            tool_calls,
+        })
+    }
+}
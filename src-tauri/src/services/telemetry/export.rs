@@ -0,0 +1,209 @@
+// Metric exporters: Prometheus text (pushed or written to a file) and OTLP/HTTP JSON
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use super::{ExporterKind, Labels, MetricSeries, MetricValue, TelemetryConfig};
+use crate::utils::paths::get_app_data_dir;
+
+const METRIC_PREFIX: &str = "creek_";
+const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318";
+const DEFAULT_PUSH_URL: &str = "http://localhost:9091/metrics/job/creek";
+
+pub async fn export(
+    config: &TelemetryConfig,
+    http: &reqwest::Client,
+    series: &[MetricSeries],
+    started: SystemTime,
+) -> Result<()> {
+    match config.exporter {
+        ExporterKind::Off => Ok(()),
+        ExporterKind::Otlp => {
+            let base = config.endpoint.as_deref().unwrap_or(DEFAULT_OTLP_ENDPOINT);
+            let url = format!("{}/v1/metrics", base.trim_end_matches('/'));
+            http.post(&url)
+                .json(&otlp_json(series, started, SystemTime::now()))
+                .send().await
+                .and_then(|r| r.error_for_status())
+                .with_context(|| format!("OTLP export to {} failed", url))?;
+            Ok(())
+        }
+        ExporterKind::Prometheus => {
+            let url = config.endpoint.as_deref().unwrap_or(DEFAULT_PUSH_URL);
+            http.post(url)
+                .header(reqwest::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(prometheus_text(series))
+                .send().await
+                .and_then(|r| r.error_for_status())
+                .with_context(|| format!("Prometheus push to {} failed", url))?;
+            Ok(())
+        }
+        ExporterKind::File => {
+            let path = config.path.clone().unwrap_or_else(|| get_app_data_dir().join("metrics.prom"));
+            write_atomically(&path, &prometheus_text(series))
+        }
+    }
+}
+
+/// Readers (e.g. a textfile collector) never see a half-written file
+fn write_atomically(path: &PathBuf, content: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("prom.tmp");
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Prometheus text exposition format, version 0.0.4
+pub fn prometheus_text(series: &[MetricSeries]) -> String {
+    let mut out = String::new();
+    let mut last_name = "";
+    for s in series {
+        let name = format!("{}{}", METRIC_PREFIX, s.name);
+        match &s.value {
+            MetricValue::Counter(total) => {
+                if s.name != last_name {
+                    out.push_str(&format!("# TYPE {}_total counter\n", name));
+                }
+                out.push_str(&format!("{}_total{} {}\n", name, prom_labels(&s.labels, None), total));
+            }
+            MetricValue::Histogram { bounds, counts, sum, count } => {
+                if s.name != last_name {
+                    out.push_str(&format!("# TYPE {} histogram\n", name));
+                }
+                let mut cumulative = 0;
+                for (bound, n) in bounds.iter().zip(counts) {
+                    cumulative += n;
+                    let le = bound.to_string();
+                    out.push_str(&format!("{}_bucket{} {}\n", name, prom_labels(&s.labels, Some(&le)), cumulative));
+                }
+                out.push_str(&format!("{}_bucket{} {}\n", name, prom_labels(&s.labels, Some("+Inf")), count));
+                out.push_str(&format!("{}_sum{} {}\n", name, prom_labels(&s.labels, None), sum));
+                out.push_str(&format!("{}_count{} {}\n", name, prom_labels(&s.labels, None), count));
+            }
+        }
+        last_name = &s.name;
+    }
+    out
+}
+
+fn prom_labels(labels: &Labels, le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels.iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+/// OTLP/HTTP JSON `ExportMetricsServiceRequest`, cumulative temporality
+pub fn otlp_json(series: &[MetricSeries], started: SystemTime, now: SystemTime) -> Value {
+    const CUMULATIVE: u8 = 2;
+    let (start, time) = (unix_nanos(started), unix_nanos(now));
+
+    let metrics: Vec<Value> = series.iter().map(|s| {
+        let attributes: Vec<Value> = s.labels.iter()
+            .map(|(k, v)| json!({"key": k, "value": {"stringValue": v}}))
+            .collect();
+        let name = format!("{}{}", METRIC_PREFIX, s.name);
+        match &s.value {
+            MetricValue::Counter(total) => json!({
+                "name": name,
+                "sum": {
+                    "aggregationTemporality": CUMULATIVE,
+                    "isMonotonic": true,
+                    "dataPoints": [{
+                        "attributes": attributes,
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": time,
+                        "asInt": total.to_string(),
+                    }],
+                },
+            }),
+            MetricValue::Histogram { bounds, counts, sum, count } => json!({
+                "name": name,
+                "unit": if s.name.ends_with("_ms") { "ms" } else { "1" },
+                "histogram": {
+                    "aggregationTemporality": CUMULATIVE,
+                    "dataPoints": [{
+                        "attributes": attributes,
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": time,
+                        "count": count.to_string(),
+                        "sum": sum,
+                        "bucketCounts": counts.iter().map(u64::to_string).collect::<Vec<_>>(),
+                        "explicitBounds": bounds,
+                    }],
+                },
+            }),
+        }
+    }).collect();
+
+    json!({
+        "resourceMetrics": [{
+            "resource": {"attributes": [{"key": "service.name", "value": {"stringValue": "creek"}}]},
+            "scopeMetrics": [{"scope": {"name": "creek"}, "metrics": metrics}],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::telemetry::Metrics;
+
+    fn sample() -> Vec<MetricSeries> {
+        let metrics = Metrics::default();
+        let labels = |k: &str, v: &str| Labels::from([(k.to_string(), v.to_string())]);
+        metrics.add("asr_reconnects", labels("cause", "dropped"), 2);
+        metrics.observe("llm_ttft_ms", labels("caller", "edit_agent"), 40.0);
+        metrics.observe("llm_ttft_ms", labels("caller", "edit_agent"), 700.0);
+        metrics.observe("llm_ttft_ms", labels("caller", "append\"agent"), 3.0);
+        metrics.snapshot()
+    }
+
+    #[test]
+    fn test_prometheus_text_buckets_are_cumulative() {
+        let text = prometheus_text(&sample());
+        assert!(text.contains("# TYPE creek_asr_reconnects_total counter\ncreek_asr_reconnects_total{cause=\"dropped\"} 2\n"));
+        assert_eq!(text.matches("# TYPE creek_llm_ttft_ms histogram").count(), 1);
+        assert!(text.contains("creek_llm_ttft_ms_bucket{caller=\"edit_agent\",le=\"50\"} 1\n"));
+        assert!(text.contains("creek_llm_ttft_ms_bucket{caller=\"edit_agent\",le=\"1000\"} 2\n"));
+        assert!(text.contains("creek_llm_ttft_ms_bucket{caller=\"edit_agent\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("creek_llm_ttft_ms_sum{caller=\"edit_agent\"} 740\n"));
+        assert!(text.contains("caller=\"append\\\"agent\""));
+    }
+
+    #[test]
+    fn test_otlp_json_shape() {
+        let body = otlp_json(&sample(), SystemTime::UNIX_EPOCH, SystemTime::UNIX_EPOCH);
+        let metrics = &body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], "creek_asr_reconnects");
+        assert_eq!(metrics[0]["sum"]["isMonotonic"], true);
+        assert_eq!(metrics[0]["sum"]["dataPoints"][0]["asInt"], "2");
+
+        let ttft = &metrics[2]["histogram"]["dataPoints"][0];
+        assert_eq!(metrics[2]["unit"], "ms");
+        assert_eq!(ttft["count"], "2");
+        let buckets = ttft["bucketCounts"].as_array().unwrap();
+        assert_eq!(buckets.len(), ttft["explicitBounds"].as_array().unwrap().len() + 1);
+        assert_eq!(ttft["attributes"][0]["value"]["stringValue"], "edit_agent");
+    }
+}
@@ -0,0 +1,385 @@
+// Telemetry
+//
+// Aggregate operational metrics, built on `tracing`. Pipeline stages run in
+// spans (`turn`, `route`, `rag`, `agent_step`, `commit`), and every closed
+// span feeds a `<span>_duration_ms` histogram labelled with the span's
+// fields. Point measurements are plain events in the OpenTelemetry-tracing
+// style, where the field prefix names the instrument and the other fields
+// become labels:
+//
+//   tracing::info!(monotonic_counter.asr_reconnects = 1, cause = "dropped");
+//   tracing::info!(histogram.llm_ttft_ms = 420, caller = "edit_agent");
+//
+// Only spans and events from creek itself are collected. The totals are
+// exported every `interval_secs` to a local collector or a file, per the
+// `[telemetry]` section of creek.toml (read at startup):
+//
+//   [telemetry]
+//   exporter = "otlp"        # OTLP/HTTP JSON, POST {endpoint}/v1/metrics
+//   endpoint = "http://localhost:4318"
+//
+//   [telemetry]
+//   exporter = "prometheus"  # text format, pushed to a Pushgateway
+//   endpoint = "http://localhost:9091/metrics/job/creek"
+//
+//   [telemetry]
+//   exporter = "file"        # text format, rewritten in place (textfile collector)
+//   path = "/tmp/creek.prom"
+//
+// The default is "off"; exporters and their formats live in export.rs.
+
+pub mod export;
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const COUNTER_PREFIX: &str = "monotonic_counter.";
+const HISTOGRAM_PREFIX: &str = "histogram.";
+
+const LATENCY_BUCKETS_MS: &[f64] = &[
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0,
+];
+/// Includes the RAG similarity threshold (0.7), so hit rate can be read off the buckets
+const SIMILARITY_BUCKETS: &[f64] = &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0];
+const COUNT_BUCKETS: &[f64] = &[0.0, 1.0, 2.0, 3.0, 5.0, 10.0];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExporterKind {
+    #[default]
+    Off,
+    Otlp,
+    Prometheus,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub exporter: ExporterKind,
+    /// Collector URL (otlp: base URL; prometheus: full push URL)
+    pub endpoint: Option<String>,
+    /// Output file for the file exporter (default: metrics.prom in the app data dir)
+    pub path: Option<PathBuf>,
+    pub interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            exporter: ExporterKind::Off,
+            endpoint: None,
+            path: None,
+            interval_secs: 15,
+        }
+    }
+}
+
+pub type Labels = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricValue {
+    Counter(u64),
+    Histogram {
+        bounds: &'static [f64],
+        /// Per bucket (not cumulative); one more than `bounds`, the last is overflow
+        counts: Vec<u64>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSeries {
+    pub name: String,
+    pub labels: Labels,
+    pub value: MetricValue,
+}
+
+/// Running totals since startup, one series per name and label set
+#[derive(Default)]
+pub struct Metrics {
+    series: Mutex<BTreeMap<(String, Labels), MetricValue>>,
+}
+
+impl Metrics {
+    pub fn add(&self, name: &str, labels: Labels, delta: u64) {
+        let mut series = self.series.lock().unwrap();
+        let value = series.entry((name.to_string(), labels)).or_insert(MetricValue::Counter(0));
+        if let MetricValue::Counter(total) = value {
+            *total += delta;
+        }
+    }
+
+    pub fn observe(&self, name: &str, labels: Labels, sample: f64) {
+        let mut series = self.series.lock().unwrap();
+        let value = series.entry((name.to_string(), labels)).or_insert_with(|| {
+            let bounds = buckets_for(name);
+            MetricValue::Histogram { bounds, counts: vec![0; bounds.len() + 1], sum: 0.0, count: 0 }
+        });
+        if let MetricValue::Histogram { bounds, counts, sum, count } = value {
+            let bucket = bounds.iter().position(|b| sample <= *b).unwrap_or(bounds.len());
+            counts[bucket] += 1;
+            *sum += sample;
+            *count += 1;
+        }
+    }
+
+    /// Ordered by name, then labels
+    pub fn snapshot(&self) -> Vec<MetricSeries> {
+        self.series.lock().unwrap().iter()
+            .map(|((name, labels), value)| MetricSeries {
+                name: name.clone(),
+                labels: labels.clone(),
+                value: value.clone(),
+            })
+            .collect()
+    }
+}
+
+fn buckets_for(name: &str) -> &'static [f64] {
+    if name.ends_with("_ms") {
+        LATENCY_BUCKETS_MS
+    } else if name.ends_with("similarity") {
+        SIMILARITY_BUCKETS
+    } else {
+        COUNT_BUCKETS
+    }
+}
+
+fn metric_name(name: &str) -> String {
+    name.replace(['.', '-'], "_")
+}
+
+/// Splits an event's fields into instruments and labels
+#[derive(Default)]
+struct FieldVisitor {
+    counters: Vec<(String, u64)>,
+    histograms: Vec<(String, f64)>,
+    labels: Labels,
+}
+
+impl FieldVisitor {
+    fn number(&mut self, field: &Field, value: f64) -> bool {
+        if let Some(name) = field.name().strip_prefix(COUNTER_PREFIX) {
+            self.counters.push((metric_name(name), value.max(0.0) as u64));
+        } else if let Some(name) = field.name().strip_prefix(HISTOGRAM_PREFIX) {
+            self.histograms.push((metric_name(name), value));
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn label(&mut self, field: &Field, value: String) {
+        if field.name() != "message" {
+            self.labels.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if !self.number(field, value as f64) {
+            self.label(field, value.to_string());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if !self.number(field, value as f64) {
+            self.label(field, value.to_string());
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if !self.number(field, value) {
+            self.label(field, value.to_string());
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.label(field, value.to_string());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.label(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.label(field, format!("{:?}", value));
+    }
+}
+
+struct SpanTiming {
+    labels: Labels,
+    started: Instant,
+}
+
+/// Turns creek's spans and metric events into `Metrics`
+pub struct MetricsLayer {
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        metadata.target().starts_with("creek")
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming { labels: visitor.labels, started: Instant::now() });
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            timing.labels.extend(visitor.labels);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        for (name, delta) in visitor.counters {
+            self.metrics.add(&name, visitor.labels.clone(), delta);
+        }
+        for (name, sample) in visitor.histograms {
+            self.metrics.observe(&name, visitor.labels.clone(), sample);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let extensions = span.extensions();
+        if let Some(timing) = extensions.get::<SpanTiming>() {
+            let elapsed = timing.started.elapsed().as_secs_f64() * 1000.0;
+            let name = format!("{}_duration_ms", metric_name(span.name()));
+            self.metrics.observe(&name, timing.labels.clone(), elapsed);
+        }
+    }
+}
+
+/// Installed metrics pipeline: the collected totals and where they go
+pub struct Telemetry {
+    metrics: Arc<Metrics>,
+    config: TelemetryConfig,
+    started: SystemTime,
+    http: reqwest::Client,
+}
+
+impl Telemetry {
+    /// Install the metrics layer as the global `tracing` subscriber. None when
+    /// the exporter is off or another subscriber is already installed.
+    pub fn init(config: &TelemetryConfig) -> Option<Arc<Self>> {
+        if config.exporter == ExporterKind::Off {
+            return None;
+        }
+        let metrics = Arc::new(Metrics::default());
+        let subscriber = tracing_subscriber::registry().with(MetricsLayer::new(metrics.clone()));
+        if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+            warn!("[Telemetry] Not installed: {}", e);
+            return None;
+        }
+        info!("[Telemetry] Exporting metrics via {:?} every {}s", config.exporter, config.interval_secs);
+        Some(Arc::new(Self {
+            metrics,
+            config: config.clone(),
+            started: SystemTime::now(),
+            http: reqwest::Client::new(),
+        }))
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Push the current totals once (also used to flush before exit)
+    pub async fn export(&self) -> Result<()> {
+        export::export(&self.config, &self.http, &self.metrics.snapshot(), self.started).await
+    }
+
+    /// Export periodically for the rest of the process
+    pub fn spawn(self: &Arc<Self>) {
+        let telemetry = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(telemetry.config.interval_secs.max(1)));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = telemetry.export().await {
+                    warn!("[Telemetry] Export failed: {:#}", e);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(f: impl FnOnce()) -> Vec<MetricSeries> {
+        let metrics = Arc::new(Metrics::default());
+        let subscriber = tracing_subscriber::registry().with(MetricsLayer::new(metrics.clone()));
+        tracing::subscriber::with_default(subscriber, f);
+        metrics.snapshot()
+    }
+
+    #[test]
+    fn test_events_and_spans_become_metrics() {
+        let series = collect(|| {
+            tracing::info!(monotonic_counter.patches = 1, result = "applied");
+            tracing::info!(monotonic_counter.patches = 1, result = "not_found");
+            tracing::info!(monotonic_counter.patches = 1, result = "applied");
+            tracing::info!(histogram.rag_similarity = 0.75, outcome = "hit");
+            tracing::info!(histogram.rag_similarity = 0.65, outcome = "hit");
+            tracing::info!("plain log lines are ignored");
+
+            let span = tracing::info_span!("route", mode = tracing::field::Empty);
+            span.record("mode", "fused");
+            drop(span);
+        });
+
+        let find = |name: &str, label: (&str, &str)| series.iter()
+            .find(|s| s.name == name && s.labels.get(label.0).map(String::as_str) == Some(label.1))
+            .map(|s| s.value.clone());
+        assert_eq!(find("patches", ("result", "applied")), Some(MetricValue::Counter(2)));
+        assert_eq!(find("patches", ("result", "not_found")), Some(MetricValue::Counter(1)));
+
+        let Some(MetricValue::Histogram { counts, count, .. }) = find("rag_similarity", ("outcome", "hit")) else {
+            panic!("no similarity histogram");
+        };
+        assert_eq!(count, 2);
+        // 0.65 falls in (0.6, 0.7], 0.75 in (0.7, 0.8]
+        assert_eq!((counts[6], counts[7]), (1, 1));
+
+        assert!(matches!(find("route_duration_ms", ("mode", "fused")), Some(MetricValue::Histogram { count: 1, .. })));
+        assert_eq!(series.len(), 4);
+    }
+}
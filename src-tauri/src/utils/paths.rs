@@ -24,3 +24,8 @@ pub fn get_state_db_path() -> PathBuf {
 pub fn get_recording_doc_path(recording_id: &str) -> PathBuf {
     get_recordings_dir().join(recording_id).join(format!("{}.md", recording_id))
 }
+
+/// Get user config file path (model providers, tuning)
+pub fn get_config_path() -> PathBuf {
+    get_app_data_dir().join("creek.toml")
+}